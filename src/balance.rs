@@ -0,0 +1,324 @@
+//! Consistency checks for fetched statements.
+//!
+//! [`check_balance`] verifies that a statement's transactions explain the
+//! difference between its opening and closing balance. [`check_continuity`]
+//! compares consecutive statements or period fetches of one account: the
+//! closing balance must carry over, the covered dates must neither leave
+//! gaps nor overlap, and no transaction may appear twice. [`validate`] runs
+//! both. Statements are identified by their position in the input slice.
+
+use crate::models::Statement;
+use chrono::{Days, NaiveDate};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// A discrepancy found in or between statements.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Finding {
+    /// The statement lacks opening or closing balance.
+    MissingBalance { statement: usize },
+    /// `opening + sum(amounts) != closing`.
+    BalanceMismatch {
+        statement: usize,
+        opening: Decimal,
+        movements: Decimal,
+        closing: Decimal,
+        difference: Decimal,
+    },
+    /// Transactions do not span `id_from..=id_to` of the statement info.
+    IdRangeMismatch {
+        statement: usize,
+        id_from: i64,
+        id_to: i64,
+        first: i64,
+        last: i64,
+    },
+    /// A transaction is dated outside the statement period.
+    OutsidePeriod {
+        statement: usize,
+        transaction_id: i64,
+        date: NaiveDate,
+    },
+    /// Closing balance of one statement differs from the next opening one.
+    BalanceBreak {
+        previous: usize,
+        next: usize,
+        closing: Decimal,
+        opening: Decimal,
+    },
+    /// No statement covers these days.
+    DateGap {
+        previous: usize,
+        next: usize,
+        from: NaiveDate,
+        to: NaiveDate,
+    },
+    /// Both statements cover these days.
+    DateOverlap {
+        previous: usize,
+        next: usize,
+        from: NaiveDate,
+        to: NaiveDate,
+    },
+    /// The same transaction appears in two statements.
+    DuplicateTransaction {
+        previous: usize,
+        next: usize,
+        transaction_id: i64,
+    },
+}
+
+/// Check one statement's balances, ID range and transaction dates.
+pub fn check_balance(index: usize, statement: &Statement) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let info = &statement.info;
+    match (info.opening_balance, info.closing_balance) {
+        (Some(opening), Some(closing)) => {
+            let movements: Decimal = statement.transactions.iter().map(|t| t.amount).sum();
+            let difference = opening + movements - closing;
+            if !difference.is_zero() {
+                findings.push(Finding::BalanceMismatch {
+                    statement: index,
+                    opening,
+                    movements,
+                    closing,
+                    difference,
+                });
+            }
+        }
+        _ => findings.push(Finding::MissingBalance { statement: index }),
+    }
+
+    let ids = statement.transactions.iter().map(|t| t.transaction_id);
+    if let (Some(id_from), Some(id_to), Some(first), Some(last)) =
+        (info.id_from, info.id_to, ids.clone().min(), ids.max())
+        && (first, last) != (id_from, id_to)
+    {
+        findings.push(Finding::IdRangeMismatch {
+            statement: index,
+            id_from,
+            id_to,
+            first,
+            last,
+        });
+    }
+
+    if let (Some(start), Some(end)) = (info.date_start, info.date_end) {
+        for txn in &statement.transactions {
+            if !(start..=end).contains(&txn.date) {
+                findings.push(Finding::OutsidePeriod {
+                    statement: index,
+                    transaction_id: txn.transaction_id,
+                    date: txn.date,
+                });
+            }
+        }
+    }
+    findings
+}
+
+/// Check that statements follow each other without breaks.
+///
+/// Statements are ordered by `date_start` first, so they may be passed in
+/// any order; ones without a start date come first, in input order.
+pub fn check_continuity(statements: &[Statement]) -> Vec<Finding> {
+    let mut order: Vec<usize> = (0..statements.len()).collect();
+    order.sort_by_key(|&i| statements[i].info.date_start);
+
+    let mut findings = Vec::new();
+    for pair in order.windows(2) {
+        let (previous, next) = (pair[0], pair[1]);
+        let (a, b) = (&statements[previous].info, &statements[next].info);
+        if let (Some(closing), Some(opening)) = (a.closing_balance, b.opening_balance)
+            && closing != opening
+        {
+            findings.push(Finding::BalanceBreak {
+                previous,
+                next,
+                closing,
+                opening,
+            });
+        }
+        if let (Some(end), Some(start)) = (a.date_end, b.date_start) {
+            let day_after = end + Days::new(1);
+            if start > day_after {
+                findings.push(Finding::DateGap {
+                    previous,
+                    next,
+                    from: day_after,
+                    to: start - Days::new(1),
+                });
+            } else if start <= end {
+                findings.push(Finding::DateOverlap {
+                    previous,
+                    next,
+                    from: start,
+                    to: end.min(b.date_end.unwrap_or(end)),
+                });
+            }
+        }
+        let seen: HashSet<i64> = statements[previous]
+            .transactions
+            .iter()
+            .map(|t| t.transaction_id)
+            .collect();
+        for txn in &statements[next].transactions {
+            if seen.contains(&txn.transaction_id) {
+                findings.push(Finding::DuplicateTransaction {
+                    previous,
+                    next,
+                    transaction_id: txn.transaction_id,
+                });
+            }
+        }
+    }
+    findings
+}
+
+/// Run [`check_balance`] on every statement and [`check_continuity`] on all.
+pub fn validate(statements: &[Statement]) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = statements
+        .iter()
+        .enumerate()
+        .flat_map(|(i, s)| check_balance(i, s))
+        .collect();
+    findings.extend(check_continuity(statements));
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn statement(
+        start: &str,
+        end: &str,
+        opening: i64,
+        closing: i64,
+        txns: &[(i64, &str, i64)],
+    ) -> Statement {
+        serde_json::from_value(json!({
+            "info": {
+                "openingBalance": opening,
+                "closingBalance": closing,
+                "dateStart": start,
+                "dateEnd": end,
+                "idFrom": txns.iter().map(|t| t.0).min(),
+                "idTo": txns.iter().map(|t| t.0).max(),
+            },
+            "transactions": txns.iter().map(|(id, date, amount)| json!({
+                "transaction_id": id,
+                "date": date,
+                "amount": amount,
+                "currency": "CZK",
+            })).collect::<Vec<_>>(),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn consistent_statements_have_no_findings() {
+        let statements = [
+            statement(
+                "2025-01-01",
+                "2025-01-31",
+                100,
+                150,
+                &[(1, "2025-01-10", 50)],
+            ),
+            statement(
+                "2025-02-01",
+                "2025-02-28",
+                150,
+                120,
+                &[(2, "2025-02-03", -30)],
+            ),
+        ];
+        assert_eq!(validate(&statements), []);
+    }
+
+    #[test]
+    fn reports_balance_and_continuity_findings() {
+        let mut broken = statement(
+            "2025-03-10",
+            "2025-03-31",
+            140,
+            200,
+            &[(2, "2025-03-01", 50)],
+        );
+        broken.info.id_from = Some(1);
+        let statements = [
+            broken,
+            statement(
+                "2025-01-01",
+                "2025-01-31",
+                100,
+                150,
+                &[(1, "2025-01-10", 50)],
+            ),
+            statement(
+                "2025-01-20",
+                "2025-02-28",
+                150,
+                120,
+                &[(1, "2025-01-10", 50), (3, "2025-02-03", -80)],
+            ),
+        ];
+        let findings = validate(&statements);
+        assert_eq!(
+            findings,
+            [
+                Finding::BalanceMismatch {
+                    statement: 0,
+                    opening: Decimal::from(140),
+                    movements: Decimal::from(50),
+                    closing: Decimal::from(200),
+                    difference: Decimal::from(-10),
+                },
+                Finding::IdRangeMismatch {
+                    statement: 0,
+                    id_from: 1,
+                    id_to: 2,
+                    first: 2,
+                    last: 2,
+                },
+                Finding::OutsidePeriod {
+                    statement: 0,
+                    transaction_id: 2,
+                    date: NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(),
+                },
+                Finding::OutsidePeriod {
+                    statement: 2,
+                    transaction_id: 1,
+                    date: NaiveDate::from_ymd_opt(2025, 1, 10).unwrap(),
+                },
+                Finding::DateOverlap {
+                    previous: 1,
+                    next: 2,
+                    from: NaiveDate::from_ymd_opt(2025, 1, 20).unwrap(),
+                    to: NaiveDate::from_ymd_opt(2025, 1, 31).unwrap(),
+                },
+                Finding::DuplicateTransaction {
+                    previous: 1,
+                    next: 2,
+                    transaction_id: 1,
+                },
+                Finding::BalanceBreak {
+                    previous: 2,
+                    next: 0,
+                    closing: Decimal::from(120),
+                    opening: Decimal::from(140),
+                },
+                Finding::DateGap {
+                    previous: 2,
+                    next: 0,
+                    from: NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(),
+                    to: NaiveDate::from_ymd_opt(2025, 3, 9).unwrap(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,291 @@
+//! Synchronous wrapper around [`crate::Client`].
+//!
+//! [`Client`] owns a private single-threaded tokio runtime and blocks on
+//! each request, so it can be used from scripts and build tools that have no
+//! async runtime of their own. It must not be created, used or dropped
+//! inside an async context; use the async [`crate::Client`] there instead.
+//!
+//! ```no_run
+//! # #[cfg(feature = "reqwest")]
+//! # fn demo() -> Result<(), fioapi::FioError> {
+//! use fioapi::TransactionReportFmt;
+//! use fioapi::blocking::Client;
+//!
+//! let client = Client::new(std::env::var("FIO_TOKEN").unwrap())?;
+//! let info = client.fetch_last_account_statement_info()?;
+//! println!("last statement {}/{}", info.year, info.statement_id);
+//! let json = client.fetch_transaction_report_since_last_download(TransactionReportFmt::Json)?;
+//! let transactions = client.parse_transactions(&json)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::cassette::Cassette;
+use crate::client::{
+    AccountStatementFmt, ImportFmt, LastStatementInfo, StatementData, TransactionReportFmt,
+};
+#[cfg(feature = "reqwest")]
+use crate::credentials::TokenSource;
+use crate::error::FioError;
+use crate::merchant::{MerchantReportFmt, MerchantTransaction};
+use crate::models::{AccountInfo, LenientTransactions, Statement, Transaction};
+use crate::payments::{ImportResponse, PaymentBatch};
+#[cfg(feature = "reqwest")]
+use crate::token::FioToken;
+use crate::token::{ApiToken, ReadToken, TokenScope, WriteToken};
+use crate::transport::{Transport, TransportError};
+use chrono::NaiveDate;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::runtime::{Builder, Runtime};
+
+/// Blocking Fio API client.
+///
+/// Mirrors the methods of [`crate::Client`]; clones share the runtime.
+#[derive(Debug, Clone)]
+pub struct Client<T: ApiToken = ReadToken> {
+    inner: crate::Client<T>,
+    runtime: Arc<Runtime>,
+}
+
+impl Client {
+    /// Create a new read-only client with the default base URL.
+    #[cfg(feature = "reqwest")]
+    pub fn new(token: impl Into<String>) -> Result<Self, FioError> {
+        Self::from_async(crate::Client::new(token)?)
+    }
+
+    /// Create a new read-only client that sends requests through a custom transport.
+    pub fn from_transport(
+        token: impl Into<String>,
+        transport: impl Transport + 'static,
+    ) -> Result<Self, FioError> {
+        Self::from_async(crate::Client::from_transport(token, transport)?)
+    }
+
+    /// Probe the token and return a write-capable client if it can submit payments.
+    ///
+    /// Like [`crate::Client::into_write`], this uses up the token's 30 s slot.
+    pub fn into_write(self) -> Result<Client<WriteToken>, FioError> {
+        let inner = self.runtime.block_on(self.inner.into_write())?;
+        Ok(Client {
+            inner,
+            runtime: self.runtime,
+        })
+    }
+}
+
+impl<T: ApiToken> Client<T> {
+    /// Create a new client for a typed token with the default base URL.
+    #[cfg(feature = "reqwest")]
+    pub fn from_token(token: T) -> Result<Self, FioError> {
+        Self::from_async(crate::Client::from_token(token)?)
+    }
+
+    /// Create a new client with a token loaded from `source`.
+    #[cfg(feature = "reqwest")]
+    pub fn from_credentials(source: &TokenSource) -> Result<Self, FioError>
+    where
+        T: From<FioToken>,
+    {
+        Self::from_async(crate::Client::from_credentials(source)?)
+    }
+
+    /// Wrap a configured async client.
+    pub fn from_async(inner: crate::Client<T>) -> Result<Self, FioError> {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| TransportError::with_source("cannot start runtime", e))?;
+        Ok(Self {
+            inner,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// The wrapped async client.
+    pub fn as_async(&self) -> &crate::Client<T> {
+        &self.inner
+    }
+
+    /// Override the base URL (useful for tests or proxies).
+    pub fn with_base_url(self, base_url: impl Into<String>) -> Self {
+        self.map(|inner| inner.with_base_url(base_url))
+    }
+
+    /// Space requests at least `interval` apart instead of failing with 409.
+    #[cfg(feature = "rate-limit")]
+    pub fn with_rate_limit(self, interval: std::time::Duration) -> Self {
+        self.map(|inner| inner.with_rate_limit(interval))
+    }
+
+    /// Record every request and response into a cassette file at `path`.
+    pub fn with_recording(self, path: impl Into<PathBuf>) -> Self {
+        self.map(|inner| inner.with_recording(path))
+    }
+
+    /// Answer requests from a previously recorded cassette instead of the network.
+    pub fn with_replay(self, cassette: Cassette) -> Self {
+        self.map(|inner| inner.with_replay(cassette))
+    }
+
+    /// Fetch transaction report for the given period in the requested format.
+    pub fn fetch_transaction_report_for_period(
+        &self,
+        date_from: NaiveDate,
+        date_to: NaiveDate,
+        fmt: TransactionReportFmt,
+    ) -> Result<String, FioError> {
+        self.block_on(
+            self.inner
+                .fetch_transaction_report_for_period(date_from, date_to, fmt),
+        )
+    }
+
+    /// Fetch card payments accepted by the merchant's POS terminals and
+    /// payment gateway in the given period.
+    pub fn fetch_merchant_transactions_for_period(
+        &self,
+        date_from: NaiveDate,
+        date_to: NaiveDate,
+        fmt: MerchantReportFmt,
+    ) -> Result<String, FioError> {
+        self.block_on(
+            self.inner
+                .fetch_merchant_transactions_for_period(date_from, date_to, fmt),
+        )
+    }
+
+    /// Fetch account statement identified by year and statement id.
+    pub fn fetch_account_statement(
+        &self,
+        year: i32,
+        statement_id: i64,
+        fmt: AccountStatementFmt,
+    ) -> Result<StatementData, FioError> {
+        self.block_on(self.inner.fetch_account_statement(year, statement_id, fmt))
+    }
+
+    /// Fetch transactions since the last successful download.
+    pub fn fetch_transaction_report_since_last_download(
+        &self,
+        fmt: TransactionReportFmt,
+    ) -> Result<String, FioError> {
+        self.block_on(self.inner.fetch_transaction_report_since_last_download(fmt))
+    }
+
+    /// Retrieve metadata about the last available account statement.
+    pub fn fetch_last_account_statement_info(&self) -> Result<LastStatementInfo, FioError> {
+        self.block_on(self.inner.fetch_last_account_statement_info())
+    }
+
+    /// Set ID of last successfully downloaded transaction.
+    pub fn set_last_downloaded_transaction_id(&self, transaction_id: i64) -> Result<(), FioError> {
+        self.block_on(
+            self.inner
+                .set_last_downloaded_transaction_id(transaction_id),
+        )
+    }
+
+    /// Set date of last unsuccessful download.
+    pub fn set_last_unsuccessful_download_date(
+        &self,
+        download_date: NaiveDate,
+    ) -> Result<(), FioError> {
+        self.block_on(
+            self.inner
+                .set_last_unsuccessful_download_date(download_date),
+        )
+    }
+
+    /// Discover whether the token can submit payment orders.
+    ///
+    /// Counts towards the 30 s limit per token like any other request.
+    pub fn probe_token_scope(&self) -> Result<TokenScope, FioError> {
+        self.block_on(self.inner.probe_token_scope())
+    }
+
+    /// Parse account info from a JSON string returned by Fio API.
+    pub fn parse_account_info(&self, data: &str) -> Result<AccountInfo, FioError> {
+        self.inner.parse_account_info(data)
+    }
+
+    /// Parse transactions from a JSON string returned by Fio API.
+    pub fn parse_transactions(&self, data: &str) -> Result<Vec<Transaction>, FioError> {
+        self.inner.parse_transactions(data)
+    }
+
+    /// Parse account info and transactions from a JSON string returned by Fio API.
+    pub fn parse_statement(&self, data: &str) -> Result<Statement, FioError> {
+        self.inner.parse_statement(data)
+    }
+
+    /// Parse transactions, keeping malformed entries aside instead of failing.
+    pub fn parse_transactions_lenient(&self, data: &str) -> Result<LenientTransactions, FioError> {
+        self.inner.parse_transactions_lenient(data)
+    }
+
+    /// Parse a JSON merchant report returned by Fio API.
+    pub fn parse_merchant_transactions(
+        &self,
+        data: &str,
+    ) -> Result<Vec<MerchantTransaction>, FioError> {
+        self.inner.parse_merchant_transactions(data)
+    }
+
+    fn map(self, f: impl FnOnce(crate::Client<T>) -> crate::Client<T>) -> Self {
+        Self {
+            inner: f(self.inner),
+            runtime: self.runtime,
+        }
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+}
+
+impl Client<WriteToken> {
+    /// Submit a batch of payment orders through the import endpoint.
+    pub fn import_payment_orders(
+        &self,
+        document: impl Into<Vec<u8>>,
+        fmt: ImportFmt,
+    ) -> Result<String, FioError> {
+        self.block_on(self.inner.import_payment_orders(document, fmt))
+    }
+
+    /// Submit a batch as an XML import and parse Fio's per-order results.
+    pub fn submit_payment_batch(&self, batch: &PaymentBatch) -> Result<ImportResponse, FioError> {
+        self.block_on(self.inner.submit_payment_batch(batch))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ApiError, MemoryTransport};
+    use http::StatusCode;
+
+    const IMPORT_OK: &str = "<responseImport><result><errorCode>0</errorCode>\
+        <status>ok</status></result></responseImport>";
+
+    #[test]
+    fn blocks_on_requests_without_a_caller_runtime() {
+        let token = "b".repeat(64);
+        let transport = MemoryTransport::new()
+            .on_get("/lastStatement/", StatusCode::OK, "2025,4")
+            .on_get("/set-last-id/", StatusCode::CONFLICT, "")
+            .on_post("/import/", StatusCode::OK, IMPORT_OK);
+        let client = Client::from_transport(&token, transport)
+            .unwrap()
+            .with_base_url("http://fio.test");
+
+        let info = client.fetch_last_account_statement_info().unwrap();
+        assert_eq!((info.year, info.statement_id), (2025, 4));
+        let err = client.set_last_downloaded_transaction_id(5).unwrap_err();
+        assert!(matches!(err, FioError::Api(ApiError::TimeLimit)));
+        assert!(client.into_write().is_ok());
+    }
+}
@@ -0,0 +1,244 @@
+//! Details of card payments parsed from their message texts.
+//!
+//! Fio describes a card payment in the identification, message and comment
+//! columns with a text such as
+//!
+//! ```text
+//! Nákup: ALBERT 0123, PRAHA 5, CZ, dne 14.3.2025, částka 245.90 CZK
+//! ```
+//!
+//! optionally accompanied by the masked card number (`516844******1234`)
+//! and the merchant category code (`MCC 5411`). [`CardPaymentDetails`]
+//! collects whatever parts are present.
+
+use crate::models::{OriginalAmount, Transaction, TransactionKind};
+use chrono::NaiveDate;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CardPaymentDetails {
+    pub merchant: Option<String>,
+    pub city: Option<String>,
+    /// Two-letter country code.
+    pub country: Option<String>,
+    /// Card number with hidden digits as `*`, e.g. `516844******1234`.
+    pub card_mask: Option<String>,
+    /// Merchant category code.
+    pub mcc: Option<u16>,
+    /// Day of the purchase, which may precede the booking date.
+    pub purchase_date: Option<NaiveDate>,
+    /// Amount charged at the terminal, in its currency.
+    pub purchase_amount: Option<OriginalAmount>,
+}
+
+impl CardPaymentDetails {
+    /// Details of a [`TransactionKind::CardPayment`] from its message texts.
+    ///
+    /// Fields are taken from the first text that has them; `None` when the
+    /// transaction is not a card payment or no text yields anything.
+    pub fn from_transaction(txn: &Transaction) -> Option<Self> {
+        if txn.kind() != TransactionKind::CardPayment {
+            return None;
+        }
+        let mut details = Self::default();
+        for text in [
+            &txn.user_identification,
+            &txn.message_from_sender,
+            &txn.comment,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            details.fill(Self::parse(text));
+        }
+        (details != Self::default()).then_some(details)
+    }
+
+    /// Parse one message text.
+    pub fn parse(text: &str) -> Self {
+        let card_mask = find_card_mask(text);
+        let mcc = find_mcc(text);
+        let text = match text.split_once(':') {
+            Some((label, rest)) if !label.contains(|c: char| c.is_ascii_digit()) => rest,
+            _ => text,
+        };
+        let (head, tail) = text.split_once(", dne ").unwrap_or((text, ""));
+
+        let mut parts: Vec<&str> = head
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty() && find_card_mask(p).is_none() && find_mcc(p).is_none())
+            .collect();
+        let country = match parts.last() {
+            Some(code)
+                if parts.len() > 1
+                    && code.len() == 2
+                    && code.chars().all(|c| c.is_ascii_uppercase()) =>
+            {
+                parts.pop().map(str::to_string)
+            }
+            _ => None,
+        };
+        let mut parts = parts.into_iter().map(str::to_string);
+
+        let purchase_date = tail
+            .split([',', ' '])
+            .next()
+            .and_then(|d| NaiveDate::parse_from_str(d, "%d.%m.%Y").ok());
+        let purchase_amount = ["částka", "castka"]
+            .iter()
+            .find_map(|label| tail.split_once(label))
+            .and_then(|(_, amount)| parse_amount(amount));
+
+        Self {
+            merchant: parts.next(),
+            city: parts.next(),
+            country,
+            card_mask,
+            mcc,
+            purchase_date,
+            purchase_amount,
+        }
+    }
+
+    fn fill(&mut self, other: Self) {
+        self.merchant = self.merchant.take().or(other.merchant);
+        self.city = self.city.take().or(other.city);
+        self.country = self.country.take().or(other.country);
+        self.card_mask = self.card_mask.take().or(other.card_mask);
+        self.mcc = self.mcc.or(other.mcc);
+        self.purchase_date = self.purchase_date.or(other.purchase_date);
+        self.purchase_amount = self.purchase_amount.take().or(other.purchase_amount);
+    }
+}
+
+/// Amount and currency code at the start of `text`, e.g. `1 234,50 EUR, ...`.
+fn parse_amount(text: &str) -> Option<OriginalAmount> {
+    let mut amount = String::new();
+    for word in text.split_whitespace() {
+        let word = word.trim_end_matches([',', ';']);
+        amount.push_str(word);
+        amount.push(' ');
+        if word.len() == 3 && word.chars().all(|c| c.is_ascii_uppercase()) {
+            return amount.parse().ok();
+        }
+    }
+    None
+}
+
+/// A run of digits and `*`/`X` (spaces allowed) with at least four of each.
+fn find_card_mask(text: &str) -> Option<String> {
+    text.split(|c: char| !(c.is_ascii_digit() || matches!(c, '*' | 'X' | 'x' | ' ')))
+        .map(|run| {
+            run.chars()
+                .filter(|c| *c != ' ')
+                .map(|c| if c.is_ascii_digit() { c } else { '*' })
+                .collect::<String>()
+        })
+        .find(|mask| {
+            let digits = mask.chars().filter(char::is_ascii_digit).count();
+            (12..=19).contains(&mask.len()) && digits >= 4 && mask.len() - digits >= 4
+        })
+}
+
+fn find_mcc(text: &str) -> Option<u16> {
+    let (_, rest) = text.split_once("MCC")?;
+    let code: String = rest
+        .trim_start_matches([':', ' '])
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    if code.len() == 4 {
+        code.parse().ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::txn;
+    use rust_decimal::Decimal;
+    use serde_json::json;
+
+    #[test]
+    fn parses_card_payment_texts() {
+        let txn = txn(
+            1,
+            "2025-03-17",
+            "-245.90",
+            json!({
+                "transaction_type": "Platba kartou",
+                "user_identification": "Nákup: ALBERT 0123, PRAHA 5, CZ, dne 14.3.2025, částka 245.90 CZK",
+                "comment": "Karta 516844******1234, MCC 5411",
+            }),
+        );
+        let details = txn.card_details().unwrap();
+        assert_eq!(
+            details,
+            CardPaymentDetails {
+                merchant: Some("ALBERT 0123".to_string()),
+                city: Some("PRAHA 5".to_string()),
+                country: Some("CZ".to_string()),
+                card_mask: Some("516844******1234".to_string()),
+                mcc: Some(5411),
+                purchase_date: NaiveDate::from_ymd_opt(2025, 3, 14),
+                purchase_amount: Some(OriginalAmount {
+                    amount: Decimal::new(24590, 2),
+                    currency: "CZK".to_string(),
+                }),
+            }
+        );
+
+        let abroad = CardPaymentDetails::parse(
+            "Nákup: AMAZON.DE, 5168 **** **** 9876, dne 2.1.2025, částka 12.50 EUR",
+        );
+        assert_eq!(abroad.merchant.as_deref(), Some("AMAZON.DE"));
+        assert_eq!(abroad.city, None);
+        assert_eq!(abroad.card_mask.as_deref(), Some("5168********9876"));
+        assert_eq!(abroad.purchase_amount.unwrap().currency, "EUR");
+
+        let transfer = Transaction {
+            transaction_type: Some("Bezhotovostní platba".to_string()),
+            ..txn
+        };
+        assert_eq!(transfer.card_details(), None);
+    }
+
+    #[test]
+    fn edge_cases() {
+        assert_eq!(CardPaymentDetails::parse(""), CardPaymentDetails::default());
+        let card = Transaction {
+            transaction_type: Some("Platba kartou".to_string()),
+            ..txn(1, "2025-03-17", "-10", json!({}))
+        };
+        assert_eq!(card.card_details(), None);
+
+        // Decimal comma with grouping, an impossible date and a 3-digit MCC.
+        let details = CardPaymentDetails::parse(
+            "Nákup: HOTEL ADLON, BERLIN, DE, dne 31.2.2025, částka 1.234,50 EUR, MCC 701",
+        );
+        assert_eq!(details.country.as_deref(), Some("DE"));
+        assert_eq!(details.purchase_date, None);
+        assert_eq!(details.mcc, None);
+        assert_eq!(
+            details.purchase_amount,
+            Some(OriginalAmount {
+                amount: Decimal::new(123450, 2),
+                currency: "EUR".to_string(),
+            })
+        );
+
+        // The identification wins over the comment where both have a field.
+        let txn = Transaction {
+            user_identification: Some("Nákup: LIDL, BRNO, CZ".to_string()),
+            comment: Some("Nákup: KAUFLAND, OLOMOUC, CZ, MCC 5411".to_string()),
+            ..card
+        };
+        let details = txn.card_details().unwrap();
+        assert_eq!(details.merchant.as_deref(), Some("LIDL"));
+        assert_eq!(details.city.as_deref(), Some("BRNO"));
+        assert_eq!(details.mcc, Some(5411));
+    }
+}
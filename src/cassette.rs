@@ -0,0 +1,333 @@
+//! Record-and-replay of HTTP interactions.
+//!
+//! A client created with [`Client::with_recording`](crate::Client::with_recording)
+//! talks to the real API and appends every exchange to a JSON cassette file,
+//! with the token replaced by `<token>` in paths and bodies. JSON bodies also
+//! have the account number, IBAN and counterparty account and name replaced
+//! by `<redacted>`. A client created with
+//! [`Client::with_replay`](crate::Client::with_replay) never touches the
+//! network and answers requests from that file in the recorded order.
+
+use crate::error::FioError;
+use crate::token::FioToken;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A single recorded request/response pair.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub method: String,
+    /// Request path relative to the base URL, with the token redacted.
+    pub path: String,
+    pub status: u16,
+    pub body: RecordedBody,
+}
+
+/// Response body as stored in the cassette.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordedBody {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl RecordedBody {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => RecordedBody::Text(text.to_string()),
+            Err(_) => RecordedBody::Binary(bytes.to_vec()),
+        }
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        match self {
+            RecordedBody::Text(text) => text.into_bytes(),
+            RecordedBody::Binary(bytes) => bytes,
+        }
+    }
+}
+
+/// An ordered list of recorded interactions.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// Load a cassette from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, FioError> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path)
+            .map_err(|e| FioError::Cassette(format!("cannot read {}: {}", path.display(), e)))?;
+        serde_json::from_str(&data)
+            .map_err(|e| FioError::Cassette(format!("cannot parse {}: {}", path.display(), e)))
+    }
+
+    /// Write the cassette to a JSON file, replacing any previous content.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), FioError> {
+        let path = path.as_ref();
+        let data = serde_json::to_string_pretty(self)
+            .map_err(|e| FioError::Cassette(format!("cannot serialize cassette: {}", e)))?;
+        fs::write(path, data)
+            .map_err(|e| FioError::Cassette(format!("cannot write {}: {}", path.display(), e)))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum CassetteMode {
+    Record {
+        path: PathBuf,
+        cassette: Mutex<Cassette>,
+    },
+    Replay {
+        remaining: Mutex<VecDeque<Interaction>>,
+    },
+}
+
+impl CassetteMode {
+    pub(crate) fn record(path: PathBuf) -> Self {
+        CassetteMode::Record {
+            path,
+            cassette: Mutex::new(Cassette::default()),
+        }
+    }
+
+    pub(crate) fn replay(cassette: Cassette) -> Self {
+        CassetteMode::Replay {
+            remaining: Mutex::new(cassette.interactions.into()),
+        }
+    }
+
+    pub(crate) fn is_replay(&self) -> bool {
+        matches!(self, CassetteMode::Replay { .. })
+    }
+
+    /// Store an exchange with its body redacted and flush the whole
+    /// cassette to disk.
+    pub(crate) fn store(
+        &self,
+        method: &str,
+        path: &str,
+        status: u16,
+        body: &[u8],
+        token: &FioToken,
+    ) -> Result<(), FioError> {
+        let CassetteMode::Record {
+            path: file,
+            cassette,
+        } = self
+        else {
+            return Ok(());
+        };
+        let mut cassette = cassette.lock().unwrap_or_else(|e| e.into_inner());
+        cassette.interactions.push(Interaction {
+            method: method.to_string(),
+            path: path.to_string(),
+            status,
+            body: redact_body(RecordedBody::from_bytes(body), token),
+        });
+        debug!("Recorded {} {} into {}", method, path, file.display());
+        cassette.save(file)
+    }
+
+    /// Answer a request from the next recorded interaction.
+    pub(crate) fn next(&self, method: &str, path: &str) -> Result<(u16, Vec<u8>), FioError> {
+        let CassetteMode::Replay { remaining } = self else {
+            return Err(FioError::Cassette("cassette is not in replay mode".into()));
+        };
+        let mut remaining = remaining.lock().unwrap_or_else(|e| e.into_inner());
+        let actual = format!("{} {}", method, path);
+        let Some(next) = remaining.front() else {
+            return Err(FioError::ReplayMismatch {
+                expected: "end of cassette".into(),
+                actual,
+            });
+        };
+        if next.method != method || next.path != path {
+            return Err(FioError::ReplayMismatch {
+                expected: format!("{} {}", next.method, next.path),
+                actual,
+            });
+        }
+        let interaction = remaining.pop_front().expect("front was checked");
+        debug!("Replayed {}", actual);
+        Ok((interaction.status, interaction.body.into_bytes()))
+    }
+}
+
+/// `info` fields and transaction columns that identify the account holder
+/// or the counterparty.
+const REDACTED_INFO: [&str; 2] = ["accountId", "iban"];
+const REDACTED_COLUMNS: [&str; 2] = ["column2", "column10"];
+
+fn redact_body(body: RecordedBody, token: &FioToken) -> RecordedBody {
+    let RecordedBody::Text(text) = body else {
+        return body;
+    };
+    let text = token.redact(&text);
+    let Ok(mut json) = serde_json::from_str::<Value>(&text) else {
+        return RecordedBody::Text(text);
+    };
+    let Some(statement) = json.get_mut("accountStatement") else {
+        return RecordedBody::Text(text);
+    };
+    if let Some(info) = statement.get_mut("info").and_then(Value::as_object_mut) {
+        for key in REDACTED_INFO {
+            if let Some(value) = info.get_mut(key).filter(|v| !v.is_null()) {
+                *value = Value::from("<redacted>");
+            }
+        }
+    }
+    let transactions = statement
+        .pointer_mut("/transactionList/transaction")
+        .and_then(Value::as_array_mut);
+    for transaction in transactions.into_iter().flatten() {
+        for key in REDACTED_COLUMNS {
+            if let Some(value) = transaction.pointer_mut(&format!("/{key}/value")) {
+                *value = Value::from("<redacted>");
+            }
+        }
+    }
+    RecordedBody::Text(json.to_string())
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod tests {
+    use super::*;
+    use crate::{ApiError, Client, TransactionReportFmt};
+
+    fn token() -> String {
+        "r".repeat(64)
+    }
+
+    fn cassette() -> Cassette {
+        Cassette {
+            interactions: vec![
+                Interaction {
+                    method: "GET".into(),
+                    path: "/lastStatement/<token>/statement".into(),
+                    status: 200,
+                    body: RecordedBody::Text("2024,7".into()),
+                },
+                Interaction {
+                    method: "GET".into(),
+                    path: "/last/<token>/transactions.json".into(),
+                    status: 409,
+                    body: RecordedBody::Text(String::new()),
+                },
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn replays_recorded_interactions() {
+        let client = Client::new(token()).unwrap().with_replay(cassette());
+        let info = client.fetch_last_account_statement_info().await.unwrap();
+        assert_eq!((info.year, info.statement_id), (2024, 7));
+        let err = client
+            .fetch_transaction_report_since_last_download(TransactionReportFmt::Json)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FioError::Api(ApiError::TimeLimit)));
+        let err = client
+            .fetch_last_account_statement_info()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FioError::ReplayMismatch { .. }));
+    }
+
+    #[tokio::test]
+    async fn rejects_mismatched_requests() {
+        let client = Client::new(token()).unwrap().with_replay(cassette());
+        let err = client
+            .fetch_transaction_report_since_last_download(TransactionReportFmt::Xml)
+            .await
+            .unwrap_err();
+        match err {
+            FioError::ReplayMismatch { expected, actual } => {
+                assert_eq!(expected, "GET /lastStatement/<token>/statement");
+                assert_eq!(actual, "GET /last/<token>/transactions.xml");
+            }
+            other => panic!("unexpected error {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn decodes_windows_1250_reports() {
+        let cassette = Cassette {
+            interactions: vec![Interaction {
+                method: "GET".into(),
+                path: "/last/<token>/transactions.gpc".into(),
+                status: 200,
+                body: RecordedBody::Binary(b"074 P\xf8\xedjem".to_vec()),
+            }],
+        };
+        let client = Client::new(token()).unwrap().with_replay(cassette);
+        let text = client
+            .fetch_transaction_report_since_last_download(TransactionReportFmt::Gpc)
+            .await
+            .unwrap();
+        assert_eq!(text, "074 Příjem");
+    }
+
+    #[test]
+    fn redacts_account_data_in_bodies() {
+        let secret = FioToken::new(token()).unwrap();
+        let body = format!(
+            r#"{{"accountStatement":{{"info":{{"accountId":"2000000000","iban":"CZ00","bankId":"2010"}},
+            "transactionList":{{"transaction":[{{"column1":{{"value":5.0,"name":"Objem","id":1}},
+            "column2":{{"value":"1234567890","name":"Protiúčet","id":2}},
+            "column10":{{"value":"Jan Novák","name":"Název protiúčtu","id":10}},
+            "column16":{{"value":"{}","name":"Zpráva pro příjemce","id":16}}}}]}}}}}}"#,
+            token()
+        );
+        let RecordedBody::Text(redacted) = redact_body(RecordedBody::Text(body), &secret) else {
+            panic!("expected a text body");
+        };
+        for secret in ["2000000000", "CZ00", "1234567890", "Jan Novák", &token()] {
+            assert!(!redacted.contains(secret), "{secret} leaked");
+        }
+        assert!(redacted.contains("\"bankId\":\"2010\""));
+        assert!(redacted.contains("<token>"));
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn records_with_redacted_token() {
+        use crate::mock::{MockFixture, MockServer};
+        use std::time::Duration;
+
+        let server = MockServer::start(
+            MockFixture::new(token())
+                .with_rate_limit(Duration::ZERO)
+                .with_statement(2023, 4, []),
+        )
+        .await
+        .unwrap();
+        let path =
+            std::env::temp_dir().join(format!("fioapi-cassette-{}.json", std::process::id()));
+        let client = Client::new(token())
+            .unwrap()
+            .with_base_url(server.base_url())
+            .with_recording(&path);
+        client.fetch_last_account_statement_info().await.unwrap();
+
+        let data = fs::read_to_string(&path).unwrap();
+        assert!(!data.contains(&token()));
+        let recorded = Cassette::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            recorded.interactions[0].path,
+            "/lastStatement/<token>/statement"
+        );
+
+        let replay = Client::new(token()).unwrap().with_replay(recorded);
+        let info = replay.fetch_last_account_statement_info().await.unwrap();
+        assert_eq!((info.year, info.statement_id), (2023, 4));
+    }
+}
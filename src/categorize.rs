@@ -0,0 +1,395 @@
+//! Rule-based categorization of transactions.
+//!
+//! A [`Rule`] assigns a category and tags to every transaction that meets all
+//! of its conditions; a rule without conditions matches everything. Rules
+//! are tried by descending `priority`, ties in the order they were given. The
+//! first matching rule with a category decides it, while tags are collected
+//! from all matching rules. A [`Categorization`] records which rules fired
+//! and on which conditions.
+//!
+//! Rule files are TOML (feature `toml`) or YAML (feature `yaml`):
+//!
+//! ```toml
+//! [[rule]]
+//! name = "rent"
+//! priority = 10
+//! category = "housing"
+//! tags = ["fixed"]
+//! when = { account = "2000000018/2010", message = "(?i)n[aá]jem" }
+//!
+//! [[rule]]
+//! name = "small card payments"
+//! tags = ["pocket-money"]
+//! when = { kind = "card-payment", min_amount = -500 }
+//! ```
+//!
+//! `name` and `message` are regular expressions and need the `regex` feature.
+
+use crate::error::FioError;
+use crate::models::{Transaction, TransactionKind};
+use crate::query::Filter;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+
+/// Conditions of a rule; all given ones must hold.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Conditions {
+    /// Counterparty `number` or `number/bank`.
+    pub account: Option<String>,
+    /// Regular expression on the counterparty name.
+    pub name: Option<String>,
+    /// Regular expression on the message, identification or comment.
+    pub message: Option<String>,
+    pub vs: Option<i64>,
+    pub kind: Option<TransactionKind>,
+    /// Lower bound of the signed amount, inclusive.
+    pub min_amount: Option<Decimal>,
+    /// Upper bound of the signed amount, inclusive.
+    pub max_amount: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Rule {
+    /// Shown in explanations.
+    pub name: String,
+    /// Higher priorities are tried first.
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub when: Conditions,
+}
+
+/// A rule that matched a transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FiredRule {
+    pub rule: String,
+    pub priority: i32,
+    /// The rule's conditions, e.g. `vs = 1234` or `amount >= -500`.
+    pub conditions: Vec<String>,
+}
+
+/// Category and tags of one transaction, with the rules that produced them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Categorization {
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+    /// Name of the rule that set the category.
+    pub decided_by: Option<String>,
+    /// Every matching rule, in evaluation order.
+    pub fired: Vec<FiredRule>,
+}
+
+impl Categorization {
+    /// Human-readable account of which rules fired.
+    pub fn explain(&self) -> String {
+        if self.fired.is_empty() {
+            return "no rule matched".to_string();
+        }
+        let mut text = match (&self.category, &self.decided_by) {
+            (Some(category), Some(rule)) => format!("category {category} from rule `{rule}`"),
+            _ => "no category".to_string(),
+        };
+        if !self.tags.is_empty() {
+            let _ = write!(text, ", tags {}", self.tags.join(", "));
+        }
+        for fired in &self.fired {
+            let conditions = if fired.conditions.is_empty() {
+                "always".to_string()
+            } else {
+                fired.conditions.join(", ")
+            };
+            let _ = write!(
+                text,
+                "\n  `{}` (priority {}): {conditions}",
+                fired.rule, fired.priority
+            );
+        }
+        text
+    }
+}
+
+#[cfg(any(feature = "toml", feature = "yaml"))]
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    #[serde(default, rename = "rule", alias = "rules")]
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone)]
+struct Compiled {
+    rule: Rule,
+    conditions: Vec<(String, Filter)>,
+}
+
+/// Applies an ordered set of [`Rule`]s.
+#[derive(Debug, Clone, Default)]
+pub struct Categorizer {
+    rules: Vec<Compiled>,
+}
+
+impl Categorizer {
+    /// Compile `rules`, failing on invalid patterns or empty amount ranges.
+    pub fn new(rules: impl IntoIterator<Item = Rule>) -> Result<Self, FioError> {
+        let mut rules = rules
+            .into_iter()
+            .map(compile)
+            .collect::<Result<Vec<_>, _>>()?;
+        rules.sort_by_key(|c| std::cmp::Reverse(c.rule.priority));
+        Ok(Self { rules })
+    }
+
+    /// Load rules from a TOML document with a `[[rule]]` array.
+    #[cfg(feature = "toml")]
+    pub fn from_toml(data: &str) -> Result<Self, FioError> {
+        let file: RuleFile =
+            toml::from_str(data).map_err(|e| FioError::InvalidRules(e.to_string()))?;
+        Self::new(file.rules)
+    }
+
+    /// Load rules from a YAML document with a `rules` list.
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(data: &str) -> Result<Self, FioError> {
+        let file: RuleFile =
+            serde_yaml_ng::from_str(data).map_err(|e| FioError::InvalidRules(e.to_string()))?;
+        Self::new(file.rules)
+    }
+
+    /// Rules in evaluation order.
+    pub fn rules(&self) -> impl Iterator<Item = &Rule> {
+        self.rules.iter().map(|c| &c.rule)
+    }
+
+    pub fn categorize(&self, txn: &Transaction) -> Categorization {
+        let mut result = Categorization::default();
+        for compiled in &self.rules {
+            if !compiled.conditions.iter().all(|(_, f)| f.matches(txn)) {
+                continue;
+            }
+            let rule = &compiled.rule;
+            if result.category.is_none() && rule.category.is_some() {
+                result.category = rule.category.clone();
+                result.decided_by = Some(rule.name.clone());
+            }
+            for tag in &rule.tags {
+                if !result.tags.contains(tag) {
+                    result.tags.push(tag.clone());
+                }
+            }
+            result.fired.push(FiredRule {
+                rule: rule.name.clone(),
+                priority: rule.priority,
+                conditions: compiled.conditions.iter().map(|(d, _)| d.clone()).collect(),
+            });
+        }
+        result
+    }
+}
+
+fn compile(rule: Rule) -> Result<Compiled, FioError> {
+    let invalid = |reason: &str| FioError::InvalidRules(format!("rule `{}`: {reason}", rule.name));
+    let when = &rule.when;
+    let mut conditions = Vec::new();
+    if let Some(account) = &when.account {
+        conditions.push((
+            format!("account = {account}"),
+            Filter::Counterparty(account.clone()),
+        ));
+    }
+    if let Some(pattern) = &when.name {
+        conditions.push((
+            format!("name ~ /{pattern}/"),
+            regex_filter(pattern, true).map_err(|e| invalid(&e))?,
+        ));
+    }
+    if let Some(pattern) = &when.message {
+        conditions.push((
+            format!("message ~ /{pattern}/"),
+            regex_filter(pattern, false).map_err(|e| invalid(&e))?,
+        ));
+    }
+    if let Some(vs) = when.vs {
+        conditions.push((format!("vs = {vs}"), Filter::Vs(vs)));
+    }
+    if let Some(kind) = when.kind {
+        conditions.push((format!("kind = {kind}"), Filter::Kind(kind)));
+    }
+    match (when.min_amount, when.max_amount) {
+        (Some(min), Some(max)) if min > max => {
+            return Err(invalid("min_amount exceeds max_amount"));
+        }
+        (Some(min), Some(max)) => {
+            conditions.push((format!("amount {min}..={max}"), Filter::amount(min..=max)))
+        }
+        (Some(min), None) => conditions.push((format!("amount >= {min}"), Filter::amount(min..))),
+        (None, Some(max)) => conditions.push((format!("amount <= {max}"), Filter::amount(..=max))),
+        (None, None) => {}
+    }
+    Ok(Compiled { rule, conditions })
+}
+
+#[cfg(feature = "regex")]
+fn regex_filter(pattern: &str, name: bool) -> Result<Filter, String> {
+    let re = regex::Regex::new(pattern).map_err(|e| e.to_string())?;
+    Ok(if name {
+        Filter::NameRegex(re)
+    } else {
+        Filter::MessageRegex(re)
+    })
+}
+
+#[cfg(not(feature = "regex"))]
+fn regex_filter(_pattern: &str, _name: bool) -> Result<Filter, String> {
+    Err("regular expressions need the `regex` feature".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::txn;
+    use serde_json::json;
+
+    fn rule(
+        name: &str,
+        priority: i32,
+        category: Option<&str>,
+        tags: &[&str],
+        when: Conditions,
+    ) -> Rule {
+        Rule {
+            name: name.to_string(),
+            priority,
+            category: category.map(str::to_string),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            when,
+        }
+    }
+
+    #[test]
+    fn priority_decides_category_and_tags_accumulate() {
+        let categorizer = Categorizer::new([
+            rule("fallback", -100, Some("other"), &[], Conditions::default()),
+            rule(
+                "small card",
+                0,
+                Some("shopping"),
+                &["card"],
+                Conditions {
+                    kind: Some(TransactionKind::CardPayment),
+                    min_amount: Some(Decimal::from(-500)),
+                    ..Conditions::default()
+                },
+            ),
+            rule(
+                "pharmacy",
+                10,
+                Some("health"),
+                &["card"],
+                Conditions {
+                    account: Some("2000000018/0800".to_string()),
+                    ..Conditions::default()
+                },
+            ),
+        ])
+        .unwrap();
+        let names: Vec<_> = categorizer.rules().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["pharmacy", "small card", "fallback"]);
+
+        let card = txn(
+            1,
+            "2025-06-01",
+            "-120",
+            json!({"transaction_type": "Platba kartou", "account_id": "2000000018", "bank_id": "0800"}),
+        );
+        let result = categorizer.categorize(&card);
+        assert_eq!(result.category.as_deref(), Some("health"));
+        assert_eq!(result.decided_by.as_deref(), Some("pharmacy"));
+        assert_eq!(result.tags, ["card"]);
+        assert_eq!(result.fired.len(), 3);
+        assert_eq!(
+            result.fired[1].conditions,
+            ["kind = card-payment", "amount >= -500"]
+        );
+        assert!(
+            result
+                .explain()
+                .starts_with("category health from rule `pharmacy`, tags card")
+        );
+
+        let large = categorizer.categorize(&txn(
+            1,
+            "2025-06-01",
+            "-900",
+            json!({"transaction_type": "Platba kartou"}),
+        ));
+        assert_eq!(large.category.as_deref(), Some("other"));
+        assert!(large.tags.is_empty());
+
+        let empty_range = rule(
+            "broken",
+            0,
+            None,
+            &[],
+            Conditions {
+                min_amount: Some(Decimal::ONE),
+                max_amount: Some(Decimal::ZERO),
+                ..Conditions::default()
+            },
+        );
+        assert!(matches!(
+            Categorizer::new([empty_range]),
+            Err(FioError::InvalidRules(_))
+        ));
+    }
+
+    #[cfg(all(feature = "toml", feature = "yaml", feature = "regex"))]
+    #[test]
+    fn toml_and_yaml_rules_agree() {
+        const TOML: &str = r#"
+            [[rule]]
+            name = "rent"
+            priority = 5
+            category = "housing"
+            when = { vs = 42, name = "(?i)landlord", message = "rent" }
+        "#;
+        const YAML: &str = "
+rules:
+  - name: rent
+    priority: 5
+    category: housing
+    when:
+      vs: 42
+      name: (?i)landlord
+      message: rent
+";
+        let from_toml = Categorizer::from_toml(TOML).unwrap();
+        let from_yaml = Categorizer::from_yaml(YAML).unwrap();
+        assert!(from_toml.rules().eq(from_yaml.rules()));
+
+        let rent = txn(
+            1,
+            "2025-06-01",
+            "-15000",
+            json!({"vs": 42, "account_name": "The Landlord", "message_from_sender": "rent June"}),
+        );
+        assert_eq!(
+            from_toml.categorize(&rent).category.as_deref(),
+            Some("housing")
+        );
+        assert!(
+            from_toml
+                .categorize(&txn(1, "2025-06-01", "-15000", json!({"vs": 42})))
+                .fired
+                .is_empty()
+        );
+        assert!(
+            Categorizer::from_toml("[[rule]]\nname = \"x\"\nwhen = { iban = \"CZ\" }").is_err()
+        );
+    }
+}
@@ -0,0 +1,791 @@
+//! Command definitions and dispatch for the `fioapi` binary.
+
+mod config;
+mod forecast;
+mod output;
+mod pay;
+mod report;
+mod statements;
+mod sync;
+#[cfg(feature = "tui")]
+mod tui;
+
+use chrono::{Local, NaiveDate, Utc};
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use config::Config;
+use fioapi::forecast::DetectionRules;
+use fioapi::query::Filter;
+use fioapi::rate_limit::FIO_REQUEST_INTERVAL;
+use fioapi::reports::{self, GroupBy, Period};
+use fioapi::{
+    AccountStatementFmt, ApiError, ApiToken, Client, FioError, FioToken, StatementData,
+    TransactionReportFmt, WriteToken,
+};
+use output::OutputMode;
+use std::error::Error;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "fioapi",
+    version,
+    about = "Command-line client for the Fio banka API"
+)]
+struct Cli {
+    /// API token; falls back to FIO_API_TOKEN env var, which is only used
+    /// when no accounts are configured
+    #[arg(long, env = "FIO_API_TOKEN", hide_env_values = true, global = true)]
+    token: Option<String>,
+
+    /// The token came from FIO_API_TOKEN rather than `--token`.
+    #[arg(skip)]
+    token_from_env: bool,
+
+    /// Account alias from the config file
+    #[arg(long, short, global = true)]
+    account: Option<String>,
+
+    /// Config file (defaults to $FIOAPI_CONFIG or ~/.config/fioapi/config.toml)
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Debug, Subcommand)]
+enum Commands {
+    /// Fetch transactions for a date range (inclusive)
+    FetchPeriod {
+        /// Start date YYYY-MM-DD
+        #[arg(long, value_parser = parse_date)]
+        start: NaiveDate,
+        /// End date YYYY-MM-DD (defaults to today if omitted)
+        #[arg(long, value_parser = parse_date)]
+        end: Option<NaiveDate>,
+        /// Output format
+        #[arg(long, value_enum, default_value = "json")]
+        format: TxnFmt,
+        /// How to print the result; parsed modes always fetch JSON
+        #[arg(long, short, value_enum, default_value = "raw")]
+        output: OutputMode,
+        /// Only print matching transactions, e.g. "vs=12345 amount>1000 type:card"
+        #[arg(long, short = 'q', value_parser = Filter::parse)]
+        filter: Option<Filter>,
+    },
+    /// Fetch transactions since last successful download
+    FetchLast {
+        #[arg(long, value_enum, default_value = "json")]
+        format: TxnFmt,
+        #[arg(long, short, value_enum, default_value = "raw")]
+        output: OutputMode,
+        #[arg(long, short = 'q', value_parser = Filter::parse)]
+        filter: Option<Filter>,
+    },
+    /// Fetch account statement by year and statement id
+    FetchStatement {
+        #[arg(long)]
+        year: i32,
+        #[arg(long, value_name = "ID")]
+        statement_id: i64,
+        #[arg(long, value_enum, default_value = "json")]
+        format: StatementFmt,
+        /// Output file when requesting PDF
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Show year and ID of last account statement
+    LastInfo,
+    /// Set last downloaded transaction ID
+    SetLastId {
+        #[arg(long, value_name = "ID")]
+        transaction_id: i64,
+    },
+    /// Set last unsuccessful download date
+    SetLastDate {
+        #[arg(long, value_parser = parse_date)]
+        date: NaiveDate,
+    },
+    /// List accounts defined in the config file
+    Accounts,
+    /// Download new transactions of every configured account into a local archive
+    Sync {
+        /// Directory with one JSON lines file per account
+        #[arg(long, default_value = "fio-archive")]
+        archive: PathBuf,
+        /// Store transactions in this SQLite database instead
+        #[cfg(feature = "sqlite")]
+        #[arg(long, conflicts_with = "archive")]
+        sqlite: Option<PathBuf>,
+    },
+    /// Validate and submit a batch of payment orders from a CSV or YAML file
+    Pay {
+        /// Batch file; the format is taken from the .csv/.yaml extension
+        file: PathBuf,
+        /// Batch format, overriding the file extension
+        #[arg(long, value_enum)]
+        format: Option<pay::BatchFormat>,
+        /// Only validate and print the summary
+        #[arg(long)]
+        dry_run: bool,
+        /// Submit without asking for confirmation
+        #[arg(long, short)]
+        yes: bool,
+        /// Receipt file (defaults to <file>.receipt-<timestamp>.json)
+        #[arg(long)]
+        receipt: Option<PathBuf>,
+        /// Submit even if the batch was submitted before or may have been
+        #[arg(long)]
+        force: bool,
+    },
+    /// Summarize income and spending of a date range per week, month or year
+    Report {
+        /// Start date YYYY-MM-DD
+        #[arg(long, value_parser = parse_date)]
+        start: NaiveDate,
+        /// End date YYYY-MM-DD (defaults to today)
+        #[arg(long, value_parser = parse_date)]
+        end: Option<NaiveDate>,
+        /// Period length: week, month or year
+        #[arg(long, default_value = "month", value_parser = Period::from_str)]
+        period: Period,
+        /// Split each period by category, counterparty, kind, card or merchant
+        #[arg(long, default_value = "total", value_parser = GroupBy::from_str)]
+        by: GroupBy,
+        /// Categorization rules (.toml or .yaml) used by --by category
+        #[arg(long)]
+        rules: Option<PathBuf>,
+        /// Only include matching transactions; the balance column is then omitted
+        #[arg(long, short = 'q', value_parser = Filter::parse)]
+        filter: Option<Filter>,
+        #[arg(long, short, value_enum, default_value = "table")]
+        output: report::ReportFormat,
+    },
+    /// Project the balance of every configured account from its recurring payments
+    Forecast {
+        /// Days to project
+        #[arg(long, default_value_t = 90)]
+        days: u64,
+        /// Days of history searched for recurring payments
+        #[arg(long, default_value_t = 365)]
+        history_days: u64,
+        /// Payments needed before a counterparty counts as recurring
+        #[arg(long, default_value_t = 3)]
+        min_occurrences: usize,
+        #[arg(long, short, value_enum, default_value = "table")]
+        output: report::ReportFormat,
+    },
+    /// Browse transactions for a date range interactively
+    #[cfg(feature = "tui")]
+    Tui {
+        /// Start date YYYY-MM-DD (defaults to 30 days before the end)
+        #[arg(long, value_parser = parse_date)]
+        start: Option<NaiveDate>,
+        /// End date YYYY-MM-DD (defaults to today)
+        #[arg(long, value_parser = parse_date)]
+        end: Option<NaiveDate>,
+    },
+    /// Archive official account statements
+    #[command(subcommand)]
+    Statements(StatementsCommand),
+}
+
+#[derive(Debug, Subcommand)]
+enum StatementsCommand {
+    /// Download all statements of every configured account as {account}/{year}/{id}.{ext}
+    Download {
+        /// Target directory
+        #[arg(long, default_value = "fio-statements")]
+        dir: PathBuf,
+        /// Formats to store; repeat for several, e.g. -f pdf -f cba_xml
+        #[arg(long = "format", short, value_enum, default_values = ["pdf"])]
+        formats: Vec<StatementFmt>,
+        /// Oldest year to download (defaults to the first year with statements)
+        #[arg(long)]
+        since_year: Option<i32>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum TxnFmt {
+    Csv,
+    Gpc,
+    Html,
+    Json,
+    Ofx,
+    Xml,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum StatementFmt {
+    Csv,
+    Gpc,
+    Html,
+    Json,
+    Ofx,
+    Xml,
+    Pdf,
+    Mt940,
+    #[value(name = "cba_xml")]
+    CbaXml,
+    #[value(name = "sba_xml")]
+    SbaXml,
+}
+
+fn parse_date(raw: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(raw, "%Y-%m-%d").map_err(|e| e.to_string())
+}
+
+impl From<TxnFmt> for TransactionReportFmt {
+    fn from(value: TxnFmt) -> Self {
+        match value {
+            TxnFmt::Csv => TransactionReportFmt::Csv,
+            TxnFmt::Gpc => TransactionReportFmt::Gpc,
+            TxnFmt::Html => TransactionReportFmt::Html,
+            TxnFmt::Json => TransactionReportFmt::Json,
+            TxnFmt::Ofx => TransactionReportFmt::Ofx,
+            TxnFmt::Xml => TransactionReportFmt::Xml,
+        }
+    }
+}
+
+impl From<StatementFmt> for AccountStatementFmt {
+    fn from(value: StatementFmt) -> Self {
+        match value {
+            StatementFmt::Csv => AccountStatementFmt::Csv,
+            StatementFmt::Gpc => AccountStatementFmt::Gpc,
+            StatementFmt::Html => AccountStatementFmt::Html,
+            StatementFmt::Json => AccountStatementFmt::Json,
+            StatementFmt::Ofx => AccountStatementFmt::Ofx,
+            StatementFmt::Xml => AccountStatementFmt::Xml,
+            StatementFmt::Pdf => AccountStatementFmt::Pdf,
+            StatementFmt::Mt940 => AccountStatementFmt::Mt940,
+            StatementFmt::CbaXml => AccountStatementFmt::CbaXml,
+            StatementFmt::SbaXml => AccountStatementFmt::SbaXml,
+        }
+    }
+}
+
+/// Process exit code for an error returned by [`run`].
+///
+/// API rejections get distinct codes so scripts can react to them, e.g.
+/// retry later on the 30 s limit (11).
+pub fn exit_code(err: &(dyn Error + 'static)) -> u8 {
+    match err.downcast_ref::<FioError>() {
+        Some(FioError::Api(api)) => match api {
+            ApiError::InvalidRequest => 10,
+            ApiError::TimeLimit => 11,
+            ApiError::TooManyItems => 12,
+            ApiError::Authorization => 13,
+            ApiError::InvalidToken => 14,
+            ApiError::UnexpectedStatus(_) => 15,
+        },
+        Some(FioError::Http(_)) => 20,
+        Some(FioError::InvalidResponse | FioError::MalformedTransaction(_)) => 21,
+        Some(FioError::Credentials { .. }) => 3,
+        _ => 1,
+    }
+}
+
+/// Parse the command line, rejecting `--token` together with `--account`.
+///
+/// A token from `FIO_API_TOKEN` is not a conflict; see [`Cli::resolve_token`].
+fn parse_cli(
+    args: impl IntoIterator<Item = impl Into<OsString> + Clone>,
+) -> Result<Cli, clap::Error> {
+    let mut command = Cli::command();
+    let matches = command.try_get_matches_from_mut(args)?;
+    if matches.value_source("token") == Some(ValueSource::CommandLine)
+        && matches.value_source("account").is_some()
+    {
+        return Err(command.error(
+            ErrorKind::ArgumentConflict,
+            "--token cannot be used with --account",
+        ));
+    }
+    let mut cli = Cli::from_arg_matches(&matches)?;
+    cli.token_from_env = matches.value_source("token") == Some(ValueSource::EnvVariable);
+    Ok(cli)
+}
+
+impl Cli {
+    /// Decide whether the token replaces the config file.
+    ///
+    /// `--token` always does. `FIO_API_TOKEN`, possibly loaded from `.env`,
+    /// is dropped when an account is selected or configured, so a leftover
+    /// variable cannot silently redirect commands to another account.
+    fn resolve_token(&mut self, config: &Config) {
+        if self.token.is_none() || config.accounts.is_empty() && self.account.is_none() {
+            return;
+        }
+        if !self.token_from_env {
+            log::warn!("--token overrides the configured accounts");
+        } else {
+            if self.account.is_none() {
+                log::warn!(
+                    "Ignoring FIO_API_TOKEN because accounts are configured; pass --token to use it"
+                );
+            }
+            self.token = None;
+        }
+    }
+}
+
+pub async fn run() -> Result<(), Box<dyn Error>> {
+    let mut cli = parse_cli(std::env::args_os()).unwrap_or_else(|e| e.exit());
+    let config = Config::load(cli.config.as_deref())?;
+    cli.resolve_token(&config);
+
+    if let Commands::Accounts = cli.command {
+        for (alias, account) in &config.accounts {
+            let marker = if config.default_account.as_deref() == Some(alias) {
+                " (default)"
+            } else {
+                ""
+            };
+            println!("{alias}{marker}: {:?}", account.token);
+        }
+        return Ok(());
+    }
+
+    if let Commands::Sync { .. } = cli.command {
+        return run_sync(&cli, &config).await;
+    }
+
+    if let Commands::Statements(StatementsCommand::Download {
+        dir,
+        formats,
+        since_year,
+    }) = &cli.command
+    {
+        let formats: Vec<AccountStatementFmt> = formats.iter().map(|&f| f.into()).collect();
+        for (alias, client) in clients_for(&cli, &config)? {
+            let summary =
+                statements::download_statements(&alias, &client, dir, &formats, *since_year)
+                    .await?;
+            println!(
+                "{alias}: {} downloaded, {} already present",
+                summary.downloaded, summary.skipped
+            );
+        }
+        return Ok(());
+    }
+
+    if let Commands::Forecast {
+        days,
+        history_days,
+        min_occurrences,
+        output,
+    } = cli.command
+    {
+        let rules = DetectionRules {
+            min_occurrences,
+            ..DetectionRules::default()
+        };
+        let today = Utc::now().date_naive();
+        let mut stdout = io::stdout().lock();
+        for (i, (alias, client)) in clients_for(&cli, &config)?.into_iter().enumerate() {
+            let payload = client
+                .fetch_transaction_report_for_period(
+                    today - chrono::Days::new(history_days),
+                    today,
+                    TransactionReportFmt::Json,
+                )
+                .await?;
+            let info = client.parse_account_info(&payload)?;
+            let transactions = client.parse_transactions(&payload)?;
+            let balance = info
+                .closing_balance
+                .ok_or_else(|| format!("{alias}: no closing balance in the report"))?;
+            let series = fioapi::forecast::detect_recurring(&transactions, &rules);
+            let projection =
+                fioapi::forecast::forecast(&series, info.date_end.unwrap_or(today), balance, days);
+            if i > 0 && output == report::ReportFormat::Table {
+                writeln!(stdout)?;
+            }
+            forecast::write_forecast(&mut stdout, output, i == 0, &alias, &series, &projection)?;
+        }
+        return Ok(());
+    }
+
+    if let Commands::Pay { .. } = cli.command {
+        return run_pay(&cli, &config).await;
+    }
+
+    let client: Client = client_for(&cli, &config)?;
+    match cli.command {
+        Commands::FetchPeriod {
+            start,
+            end,
+            format,
+            output,
+            filter,
+        } => {
+            let end = end.unwrap_or_else(|| Utc::now().date_naive());
+            let fmt = report_fmt(format, output, filter.as_ref())?;
+            let payload = client
+                .fetch_transaction_report_for_period(start, end, fmt)
+                .await?;
+            print_report(&client, payload, output, filter.as_ref())?;
+        }
+        Commands::FetchLast {
+            format,
+            output,
+            filter,
+        } => {
+            let fmt = report_fmt(format, output, filter.as_ref())?;
+            let payload = client
+                .fetch_transaction_report_since_last_download(fmt)
+                .await?;
+            print_report(&client, payload, output, filter.as_ref())?;
+        }
+        Commands::FetchStatement {
+            year,
+            statement_id,
+            format,
+            output,
+        } => {
+            let fmt = format.into();
+            let data = client
+                .fetch_account_statement(year, statement_id, fmt)
+                .await?;
+            handle_statement_output(data, fmt, output)?;
+        }
+        Commands::Report {
+            start,
+            end,
+            period,
+            by,
+            rules,
+            filter,
+            output,
+        } => {
+            let categorizer = rules.as_deref().map(report::load_rules).transpose()?;
+            let end = end.unwrap_or_else(|| Utc::now().date_naive());
+            let payload = client
+                .fetch_transaction_report_for_period(start, end, TransactionReportFmt::Json)
+                .await?;
+            let info = client.parse_account_info(&payload)?;
+            let mut transactions = client.parse_transactions(&payload)?;
+            // A running balance only makes sense over all transactions.
+            let opening_balance = match &filter {
+                Some(filter) => {
+                    transactions.retain(|t| filter.matches(t));
+                    None
+                }
+                None => info.opening_balance,
+            };
+            let report = reports::aggregate(
+                &transactions,
+                period,
+                by,
+                categorizer.as_ref(),
+                opening_balance,
+            );
+            report::write_report(&mut io::stdout().lock(), output, &report)?;
+        }
+        #[cfg(feature = "tui")]
+        Commands::Tui { start, end } => {
+            let end = end.unwrap_or_else(|| Utc::now().date_naive());
+            let start = start.unwrap_or(end - chrono::Days::new(30));
+            let payload = client
+                .fetch_transaction_report_for_period(start, end, TransactionReportFmt::Json)
+                .await?;
+            let info = client.parse_account_info(&payload)?;
+            let transactions = client.parse_transactions(&payload)?;
+            tui::browse(info, transactions)?;
+        }
+        Commands::LastInfo => {
+            let info = client.fetch_last_account_statement_info().await?;
+            println!("year={}, statement_id={}", info.year, info.statement_id);
+        }
+        Commands::SetLastId { transaction_id } => {
+            client
+                .set_last_downloaded_transaction_id(transaction_id)
+                .await?;
+            println!("Set last downloaded transaction id to {}", transaction_id);
+        }
+        Commands::SetLastDate { date } => {
+            client.set_last_unsuccessful_download_date(date).await?;
+            println!("Set last unsuccessful download date to {}", date);
+        }
+        Commands::Accounts
+        | Commands::Sync { .. }
+        | Commands::Pay { .. }
+        | Commands::Forecast { .. }
+        | Commands::Statements(_) => {
+            unreachable!("handled before creating a client")
+        }
+    }
+
+    Ok(())
+}
+
+/// Build a client from `--token`/`FIO_API_TOKEN` or the selected config account.
+fn client_for<T: ApiToken + From<FioToken>>(
+    cli: &Cli,
+    config: &Config,
+) -> Result<Client<T>, Box<dyn Error>> {
+    if let (Some(token), None) = (&cli.token, &cli.account) {
+        let client = Client::from_token(T::from(FioToken::new(token.as_str())?))?;
+        return Ok(client.with_rate_limit(FIO_REQUEST_INTERVAL));
+    }
+    let (alias, account) = config.account(cli.account.as_deref())?;
+    log::debug!("Using account {}", alias);
+    account.client()
+}
+
+/// Clients for commands that work on several accounts.
+///
+/// `--account` selects one configured account, a token left by
+/// [`Cli::resolve_token`] is used as account "default", otherwise every
+/// configured account is returned.
+fn clients_for(cli: &Cli, config: &Config) -> Result<Vec<(String, Client)>, Box<dyn Error>> {
+    if cli.account.is_some() || (cli.token.is_none() && config.accounts.is_empty()) {
+        let (alias, account) = config.account(cli.account.as_deref())?;
+        return Ok(vec![(alias.to_string(), account.client()?)]);
+    }
+    if let Some(token) = &cli.token {
+        let client = Client::new(token.as_str())?.with_rate_limit(FIO_REQUEST_INTERVAL);
+        return Ok(vec![("default".to_string(), client)]);
+    }
+    config
+        .accounts
+        .iter()
+        .map(|(alias, account)| Ok((alias.clone(), account.client()?)))
+        .collect()
+}
+
+async fn run_sync(cli: &Cli, config: &Config) -> Result<(), Box<dyn Error>> {
+    let Commands::Sync { archive, .. } = &cli.command else {
+        unreachable!("called for the sync command only");
+    };
+    let mut store: Box<dyn sync::Archive> = match &cli.command {
+        #[cfg(feature = "sqlite")]
+        Commands::Sync {
+            sqlite: Some(path), ..
+        } => Box::new(sync::SqliteArchive::open(path)?),
+        _ => Box::new(sync::JsonLinesArchive::new(archive)?),
+    };
+
+    let mut failures = Vec::new();
+    for (alias, client) in clients_for(cli, config)? {
+        match sync::sync_account(&alias, &client, store.as_mut()).await {
+            Ok(sync::SyncOutcome::Synced {
+                added,
+                rejected,
+                last_id,
+            }) => {
+                let last = last_id.map_or_else(|| "-".to_string(), |id| id.to_string());
+                println!("{alias}: {added} new, {rejected} rejected, last id {last}");
+            }
+            Ok(sync::SyncOutcome::RateLimited) => {
+                println!("{alias}: skipped, token used less than 30 s ago");
+            }
+            Ok(sync::SyncOutcome::Locked) => {
+                println!("{alias}: skipped, another sync is running");
+            }
+            Ok(sync::SyncOutcome::QuarantinedOnly { rejected }) => {
+                let err = format!(
+                    "all {rejected} downloaded rows were quarantined and the server cursor \
+                     has moved past them; review the quarantine file"
+                );
+                eprintln!("{alias}: {err}");
+                failures.push(err.into());
+            }
+            Err(err) => {
+                eprintln!("{alias}: sync failed: {err}");
+                failures.push(err);
+            }
+        }
+    }
+    // Report the first failure through the exit code once all accounts ran.
+    match failures.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+async fn run_pay(cli: &Cli, config: &Config) -> Result<(), Box<dyn Error>> {
+    let Commands::Pay {
+        file,
+        format,
+        dry_run,
+        yes,
+        receipt,
+        force,
+    } = &cli.command
+    else {
+        unreachable!("called for the pay command only");
+    };
+    let batch = pay::load_batch(file, *format)?;
+    let mut stdout = io::stdout().lock();
+    pay::write_summary(&mut stdout, &batch)?;
+    if let Err(issues) = batch.validate(Local::now().date_naive()) {
+        for issue in &issues {
+            eprintln!("{issue}");
+        }
+        return Err(format!("{} problem(s) in {}", issues.len(), file.display()).into());
+    }
+    if *dry_run {
+        println!(
+            "Dry run: {} order(s) valid, nothing submitted",
+            batch.orders.len()
+        );
+        return Ok(());
+    }
+    let receipt = receipt.clone().unwrap_or_else(|| {
+        let mut path = file.clone().into_os_string();
+        path.push(format!(
+            ".receipt-{}.json",
+            Utc::now().format("%Y%m%dT%H%M%S")
+        ));
+        PathBuf::from(path)
+    });
+    let earlier = pay::earlier_receipts(file, &receipt);
+    if !earlier.is_empty() && !*force {
+        for (path, status) in &earlier {
+            eprintln!("{:?} receipt {}", status, path.display());
+        }
+        return Err(format!(
+            "{} may already have been submitted; check the instructions in internet \
+             banking and pass --force to submit it again",
+            file.display()
+        )
+        .into());
+    }
+    if !*yes && !confirm(&format!("Submit {} order(s)?", batch.orders.len()))? {
+        return Err("aborted, nothing submitted".into());
+    }
+
+    let alias = match cli.account.as_deref() {
+        Some(alias) => alias,
+        None if cli.token.is_some() => "default",
+        None => config.account(None)?.0,
+    };
+    let client: Client<WriteToken> = client_for(cli, config)?;
+    let response = pay::submit(alias, &client, &batch, file, &receipt).await?;
+    pay::write_results(&mut stdout, &batch, &response)?;
+    println!("Receipt written to {}", receipt.display());
+    if !response.is_accepted() {
+        return Err(format!(
+            "Fio rejected the batch: status {}, error code {}",
+            response.status, response.error_code
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Ask a yes/no question on the terminal; refuses when stdin is not one.
+fn confirm(question: &str) -> Result<bool, Box<dyn Error>> {
+    if !io::stdin().is_terminal() {
+        return Err("not a terminal; pass --yes to submit".into());
+    }
+    print!("{question} [y/N] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn report_fmt(
+    format: TxnFmt,
+    output: OutputMode,
+    filter: Option<&Filter>,
+) -> Result<TransactionReportFmt, Box<dyn Error>> {
+    match output {
+        OutputMode::Raw if filter.is_some() => {
+            Err("--filter needs parsed output (--output table, jsonl or csv)".into())
+        }
+        OutputMode::Raw => Ok(format.into()),
+        _ => Ok(TransactionReportFmt::Json),
+    }
+}
+
+fn print_report(
+    client: &Client,
+    payload: String,
+    output: OutputMode,
+    filter: Option<&Filter>,
+) -> Result<(), Box<dyn Error>> {
+    if output == OutputMode::Raw {
+        println!("{payload}");
+        return Ok(());
+    }
+    let mut transactions = client.parse_transactions(&payload)?;
+    if let Some(filter) = filter {
+        transactions.retain(|t| filter.matches(t));
+    }
+    output::write_transactions(&mut io::stdout().lock(), output, &transactions)
+}
+
+fn handle_statement_output(
+    data: StatementData,
+    fmt: AccountStatementFmt,
+    output: Option<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    match data {
+        StatementData::Text(text) => match output {
+            Some(path) => fs::write(path, text)?,
+            None => print!("{text}"),
+        },
+        StatementData::Binary(bytes) => {
+            let path = output.ok_or("Output path required for binary formats (e.g., PDF)")?;
+            fs::write(&path, &bytes)?;
+            println!(
+                "Wrote {} bytes to {} ({})",
+                bytes.len(),
+                path.display(),
+                match fmt {
+                    AccountStatementFmt::Pdf => "PDF",
+                    _ => "binary",
+                }
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_conflicts_with_account() {
+        let token = "t".repeat(64);
+        let err =
+            parse_cli(["fioapi", "--token", &token, "--account", "czk", "accounts"]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ArgumentConflict);
+
+        let cli = parse_cli(["fioapi", "accounts", "--account", "czk"]).unwrap();
+        assert_eq!(cli.account.as_deref(), Some("czk"));
+        let cli = parse_cli(["fioapi", "--token", &token, "accounts"]).unwrap();
+        assert_eq!(cli.token, Some(token));
+    }
+
+    #[test]
+    fn env_token_does_not_shadow_configured_accounts() {
+        let token = "t".repeat(64);
+        let configured: Config =
+            toml::from_str("[accounts.czk]\ntoken = { env = \"FIO_TOKEN_CZK\" }").unwrap();
+
+        let mut cli = parse_cli(["fioapi", "accounts"]).unwrap();
+        cli.token = Some(token.clone());
+        cli.token_from_env = true;
+        cli.resolve_token(&Config::default());
+        assert_eq!(cli.token.as_deref(), Some(token.as_str()));
+        cli.resolve_token(&configured);
+        assert_eq!(cli.token, None);
+
+        let mut cli = parse_cli(["fioapi", "--token", &token, "accounts"]).unwrap();
+        cli.resolve_token(&configured);
+        assert_eq!(cli.token, Some(token));
+    }
+}
@@ -0,0 +1,139 @@
+//! Account configuration file for the CLI.
+//!
+//! ```toml
+//! default_account = "czk"
+//!
+//! [accounts.czk]
+//! token = { env = "FIO_TOKEN_CZK" }
+//!
+//! [accounts.eur]
+//! token = { file = "/etc/fioapi/eur.token" }
+//! ```
+
+use fioapi::rate_limit::FIO_REQUEST_INTERVAL;
+use fioapi::{ApiToken, Client, FioToken, TokenSource};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Environment variable overriding the config file location.
+pub const CONFIG_ENV: &str = "FIOAPI_CONFIG";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub default_account: Option<String>,
+    #[serde(default)]
+    pub accounts: BTreeMap<String, AccountConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AccountConfig {
+    pub token: TokenSource,
+    /// Override of the API base URL, e.g. for a proxy.
+    pub base_url: Option<String>,
+}
+
+impl Config {
+    /// Load the config from `path`, `$FIOAPI_CONFIG` or the default location.
+    ///
+    /// A missing file at the default location yields an empty config.
+    pub fn load(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        let explicit = path
+            .map(Path::to_path_buf)
+            .or_else(|| env::var_os(CONFIG_ENV).map(PathBuf::from));
+        let path = match explicit {
+            Some(path) => path,
+            None => match default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Config::default()),
+            },
+        };
+        let data = fs::read_to_string(&path)
+            .map_err(|e| format!("cannot read config {}: {}", path.display(), e))?;
+        let config = toml::from_str(&data)
+            .map_err(|e| format!("invalid config {}: {}", path.display(), e))?;
+        Ok(config)
+    }
+
+    /// Look up an account by alias, falling back to `default_account`.
+    pub fn account(&self, alias: Option<&str>) -> Result<(&str, &AccountConfig), Box<dyn Error>> {
+        let alias = alias
+            .or(self.default_account.as_deref())
+            .ok_or("no token given; pass --token, set FIO_API_TOKEN or configure an account")?;
+        self.accounts
+            .get_key_value(alias)
+            .map(|(k, v)| (k.as_str(), v))
+            .ok_or_else(|| format!("account {alias} is not configured").into())
+    }
+}
+
+impl AccountConfig {
+    /// Load the token and build a client for this account.
+    pub fn client<T: ApiToken + From<FioToken>>(&self) -> Result<Client<T>, Box<dyn Error>> {
+        let client =
+            Client::from_token(T::from(self.token.load()?))?.with_rate_limit(FIO_REQUEST_INTERVAL);
+        Ok(match &self.base_url {
+            Some(url) => client.with_base_url(url),
+            None => client,
+        })
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("fioapi").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+default_account = "czk"
+
+[accounts.czk]
+token = { env = "FIO_TOKEN_CZK" }
+
+[accounts.eur]
+token = { file = "/etc/fioapi/eur.token" }
+base_url = "http://proxy.test"
+"#;
+
+    #[test]
+    fn resolves_accounts_by_alias_and_default() {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        assert_eq!(config.account(None).unwrap().0, "czk");
+        let (alias, eur) = config.account(Some("eur")).unwrap();
+        assert_eq!(alias, "eur");
+        assert_eq!(eur.base_url.as_deref(), Some("http://proxy.test"));
+        let err = config.account(Some("usd")).unwrap_err();
+        assert_eq!(err.to_string(), "account usd is not configured");
+
+        let empty = Config::default();
+        assert!(
+            empty
+                .account(None)
+                .unwrap_err()
+                .to_string()
+                .contains("--token")
+        );
+        assert!(toml::from_str::<Config>("defualt_account = \"czk\"").is_err());
+    }
+
+    #[test]
+    fn loads_explicit_path() {
+        let path = env::temp_dir().join(format!("fioapi-config-{}.toml", std::process::id()));
+        fs::write(&path, CONFIG).unwrap();
+        let config = Config::load(Some(&path)).unwrap();
+        assert_eq!(config.accounts.len(), 2);
+        fs::remove_file(&path).unwrap();
+        assert!(Config::load(Some(&path)).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+//! Rendering of balance forecasts.
+
+use super::output::write_rows;
+use super::report::ReportFormat;
+use fioapi::forecast::{Forecast, RecurringSeries};
+use std::error::Error;
+use std::io::Write;
+
+/// Print the detected series and the projected balance of one account.
+///
+/// The table lists only days with expected payments; CSV has every day and
+/// starts with a header row when `csv_header` is set.
+pub fn write_forecast(
+    out: &mut impl Write,
+    format: ReportFormat,
+    csv_header: bool,
+    alias: &str,
+    series: &[RecurringSeries],
+    forecast: &Forecast,
+) -> Result<(), Box<dyn Error>> {
+    match format {
+        ReportFormat::Table => {
+            writeln!(
+                out,
+                "{alias}: balance {} on {}, {} recurring series",
+                forecast.opening_balance,
+                forecast.start,
+                series.len()
+            )?;
+            let header = ["COUNTERPARTY", "VS", "CADENCE", "AMOUNT", "CUR", "LAST"];
+            let rows: Vec<[String; 6]> = series
+                .iter()
+                .map(|s| {
+                    [
+                        s.counterparty.clone(),
+                        s.vs.map(|v| v.to_string()).unwrap_or_default(),
+                        s.cadence.to_string(),
+                        s.amount.to_string(),
+                        s.currency.clone(),
+                        s.last_date.to_string(),
+                    ]
+                })
+                .collect();
+            write_rows(out, &header, &rows, &[3])?;
+            writeln!(out)?;
+
+            let header = ["DATE", "INFLOW", "OUTFLOW", "BALANCE"];
+            let rows: Vec<[String; 4]> = forecast
+                .points
+                .iter()
+                .filter(|p| !p.inflow.is_zero() || !p.outflow.is_zero())
+                .map(|p| {
+                    [
+                        p.date.to_string(),
+                        p.inflow.to_string(),
+                        p.outflow.to_string(),
+                        p.balance.to_string(),
+                    ]
+                })
+                .collect();
+            write_rows(out, &header, &rows, &[1, 2, 3])?;
+            if let Some(lowest) = forecast.lowest() {
+                writeln!(
+                    out,
+                    "lowest balance {} on {}, {} at the end",
+                    lowest.balance,
+                    lowest.date,
+                    forecast.closing_balance()
+                )?;
+            }
+        }
+        ReportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            if csv_header {
+                writer.write_record(["account", "date", "inflow", "outflow", "balance"])?;
+            }
+            for p in &forecast.points {
+                writer.write_record([
+                    alias.to_string(),
+                    p.date.to_string(),
+                    p.inflow.to_string(),
+                    p.outflow.to_string(),
+                    p.balance.to_string(),
+                ])?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,180 @@
+//! Rendering of parsed transactions.
+
+use clap::ValueEnum;
+use fioapi::Transaction;
+use std::error::Error;
+use std::io::Write;
+
+/// How fetched transactions are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputMode {
+    /// Payload exactly as returned by the API, in the requested format
+    Raw,
+    /// Aligned text table
+    Table,
+    /// One JSON object per line
+    Jsonl,
+    /// CSV with a header row
+    Csv,
+}
+
+pub fn write_transactions(
+    out: &mut impl Write,
+    mode: OutputMode,
+    transactions: &[Transaction],
+) -> Result<(), Box<dyn Error>> {
+    match mode {
+        OutputMode::Raw => return Err("raw output needs the unparsed payload".into()),
+        OutputMode::Table => write_table(out, transactions)?,
+        OutputMode::Jsonl => {
+            for txn in transactions {
+                serde_json::to_writer(&mut *out, txn)?;
+                writeln!(out)?;
+            }
+        }
+        OutputMode::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for txn in transactions {
+                writer.serialize(txn)?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+fn write_table(out: &mut impl Write, transactions: &[Transaction]) -> std::io::Result<()> {
+    let header = [
+        "ID",
+        "DATE",
+        "AMOUNT",
+        "CUR",
+        "COUNTERPARTY",
+        "VS",
+        "MESSAGE",
+    ];
+    let rows: Vec<[String; 7]> = transactions
+        .iter()
+        .map(|t| {
+            let counterparty = match (&t.account_id, &t.bank_id) {
+                (Some(account), Some(bank)) => format!("{account}/{bank}"),
+                (Some(account), None) => account.clone(),
+                _ => t.account_name.clone().unwrap_or_default(),
+            };
+            [
+                t.transaction_id.to_string(),
+                t.date.to_string(),
+                t.amount.to_string(),
+                t.currency.clone(),
+                counterparty,
+                t.vs.map(|v| v.to_string()).unwrap_or_default(),
+                t.message_from_sender
+                    .clone()
+                    .or_else(|| t.user_identification.clone())
+                    .unwrap_or_default(),
+            ]
+        })
+        .collect();
+    write_rows(out, &header, &rows, &[2])
+}
+
+/// Write an aligned table; columns listed in `right` are right-aligned.
+pub fn write_rows<const N: usize>(
+    out: &mut impl Write,
+    header: &[&str; N],
+    rows: &[[String; N]],
+    right: &[usize],
+) -> std::io::Result<()> {
+    let mut widths = header.map(|h| h.chars().count());
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                if right.contains(&i) {
+                    format!("{:>w$}", cell, w = widths[i])
+                } else {
+                    format!("{:<w$}", cell, w = widths[i])
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    writeln!(out, "{}", line(header.to_vec()))?;
+    for row in rows {
+        writeln!(out, "{}", line(row.iter().map(String::as_str).collect()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    fn transactions() -> Vec<Transaction> {
+        let date = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        vec![
+            Transaction {
+                vs: Some(2025001),
+                message_from_sender: Some("Invoice, March".to_string()),
+                ..Transaction::new(1, date, Decimal::new(150050, 2), "CZK")
+            },
+            Transaction {
+                original_amount: Some("0.80 EUR".parse().unwrap()),
+                ..Transaction::new(2, date, Decimal::from(-20), "CZK")
+            },
+        ]
+    }
+
+    fn render(mode: OutputMode) -> String {
+        let mut out = Vec::new();
+        write_transactions(&mut out, mode, &transactions()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn writes_csv_with_header() {
+        let csv = render(OutputMode::Csv);
+        let mut lines = csv.lines();
+        assert!(
+            lines
+                .next()
+                .unwrap()
+                .starts_with("transaction_id,date,amount,currency,")
+        );
+        let first = lines.next().unwrap();
+        assert!(first.starts_with("1,2025-03-01,1500.50,CZK,"));
+        assert!(first.contains(",2025001,"));
+        assert!(first.contains("\"Invoice, March\""));
+        let second = lines.next().unwrap();
+        assert!(second.starts_with("2,2025-03-01,-20,CZK,"));
+        assert!(second.ends_with(",0.80 EUR"));
+        assert_eq!(lines.next(), None);
+    }
+
+    #[test]
+    fn writes_one_json_object_per_line() {
+        let jsonl = render(OutputMode::Jsonl);
+        let rows: Vec<serde_json::Value> = jsonl
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["vs"], 2025001);
+        assert_eq!(rows[1]["amount"], "-20");
+        assert_eq!(rows[1]["original_amount"], "0.80 EUR");
+
+        let mut out = Vec::new();
+        assert!(write_transactions(&mut out, OutputMode::Raw, &transactions()).is_err());
+        assert!(out.is_empty());
+    }
+}
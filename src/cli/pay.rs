@@ -0,0 +1,362 @@
+//! Payment batches read from CSV or YAML files.
+//!
+//! Both formats use the field names of [`PaymentOrder`], e.g.
+//!
+//! ```text
+//! type,account_from,currency,amount,account_to,date,vs,message
+//! domestic,2000000018,CZK,1500.50,19-2000145399/0800,2025-03-10,2025001,Invoice 2025001
+//! ```
+
+use super::output::write_rows;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use fioapi::{Client, FioError, ImportResponse, PaymentBatch, PaymentOrder, WriteToken};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BatchFormat {
+    Csv,
+    Yaml,
+}
+
+/// Progress of a batch as recorded in its receipt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReceiptStatus {
+    /// Sent, or about to be sent, without a definite answer from Fio.
+    Pending,
+    /// Fio answered with an import response.
+    Submitted,
+    /// Fio refused the request; nothing was imported.
+    Failed,
+}
+
+/// Record of a submitted batch kept for the books.
+#[derive(Debug, Serialize)]
+struct Receipt<'a> {
+    submitted_at: DateTime<Utc>,
+    status: ReceiptStatus,
+    account: &'a str,
+    source: String,
+    orders: &'a [PaymentOrder],
+    response: Option<&'a ImportResponse>,
+    error: Option<String>,
+}
+
+impl Receipt<'_> {
+    fn write(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| format!("cannot write receipt {}: {}", path.display(), e).into())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ReceiptHeader {
+    status: ReceiptStatus,
+}
+
+/// Read a batch, guessing the format from the extension unless given.
+pub fn load_batch(
+    path: &Path,
+    format: Option<BatchFormat>,
+) -> Result<PaymentBatch, Box<dyn Error>> {
+    let format = match format {
+        Some(format) => format,
+        None => match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => BatchFormat::Yaml,
+            Some("csv") => BatchFormat::Csv,
+            _ => {
+                return Err(
+                    format!("cannot tell format of {}; pass --format", path.display()).into(),
+                );
+            }
+        },
+    };
+    let data =
+        fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    parse_batch(&data, format)
+        .map_err(|e| format!("invalid batch {}: {}", path.display(), e).into())
+}
+
+fn parse_batch(data: &str, format: BatchFormat) -> Result<PaymentBatch, Box<dyn Error>> {
+    Ok(match format {
+        BatchFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new()
+                .trim(csv::Trim::All)
+                .from_reader(data.as_bytes());
+            let orders = reader
+                .deserialize()
+                .collect::<Result<Vec<PaymentOrder>, _>>()?;
+            PaymentBatch::new(orders)
+        }
+        BatchFormat::Yaml => serde_yaml_ng::from_str(data)?,
+    })
+}
+
+/// Print the orders and per-currency totals before anything is submitted.
+pub fn write_summary(out: &mut impl Write, batch: &PaymentBatch) -> std::io::Result<()> {
+    let header = ["#", "TYPE", "DATE", "AMOUNT", "CUR", "TO", "VS", "MESSAGE"];
+    let rows: Vec<[String; 8]> = batch
+        .orders
+        .iter()
+        .enumerate()
+        .map(|(i, order)| {
+            [
+                (i + 1).to_string(),
+                order.kind.to_string(),
+                order.date.to_string(),
+                order.amount.to_string(),
+                order.currency.clone(),
+                order.account_to.clone(),
+                order.vs.clone().unwrap_or_default(),
+                order.message.clone().unwrap_or_default(),
+            ]
+        })
+        .collect();
+    write_rows(out, &header, &rows, &[0, 3])?;
+    for (currency, total) in batch.totals() {
+        writeln!(out, "Total {total} {currency}")?;
+    }
+    Ok(())
+}
+
+/// Earlier receipts of `source` that were submitted or may have been.
+///
+/// Looks at `receipt` and at the default `<source>.receipt-*.json` files
+/// next to the batch. Failed submissions are left out, since Fio imported
+/// nothing.
+pub fn earlier_receipts(source: &Path, receipt: &Path) -> Vec<(PathBuf, ReceiptStatus)> {
+    let dir = match source.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let prefix = format!(
+        "{}.receipt-",
+        source.file_name().unwrap_or_default().to_string_lossy()
+    );
+    let mut candidates: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            name.starts_with(&prefix) && name.ends_with(".json")
+        })
+        .collect();
+    if !candidates.iter().any(|path| path == receipt) {
+        candidates.push(receipt.to_path_buf());
+    }
+    candidates.sort();
+    candidates
+        .into_iter()
+        .filter_map(|path| {
+            let data = fs::read_to_string(&path).ok()?;
+            let header: ReceiptHeader = serde_json::from_str(&data).ok()?;
+            (header.status != ReceiptStatus::Failed).then_some((path, header.status))
+        })
+        .collect()
+}
+
+/// Submit the batch, keeping the receipt up to date.
+///
+/// A pending receipt is written before anything is sent, so a crash or a
+/// lost response leaves a trace that blocks an accidental resubmission
+/// (see [`earlier_receipts`]).
+/// It is then updated with Fio's response, or with the error. Only a
+/// refusal by Fio marks it failed; transport errors keep it pending
+/// because the batch may have been imported anyway.
+pub async fn submit(
+    alias: &str,
+    client: &Client<WriteToken>,
+    batch: &PaymentBatch,
+    source: &Path,
+    receipt: &Path,
+) -> Result<ImportResponse, Box<dyn Error>> {
+    let mut record = Receipt {
+        submitted_at: Utc::now(),
+        status: ReceiptStatus::Pending,
+        account: alias,
+        source: source.display().to_string(),
+        orders: &batch.orders,
+        response: None,
+        error: None,
+    };
+    record.write(receipt)?;
+    match client.submit_payment_batch(batch).await {
+        Ok(response) => {
+            record.status = ReceiptStatus::Submitted;
+            record.response = Some(&response);
+            record.write(receipt)?;
+            Ok(response)
+        }
+        Err(e) => {
+            if matches!(e, FioError::Api(_)) {
+                record.status = ReceiptStatus::Failed;
+            }
+            record.error = Some(e.to_string());
+            record.write(receipt)?;
+            Err(e.into())
+        }
+    }
+}
+
+/// Print Fio's messages for each order.
+pub fn write_results(
+    out: &mut impl Write,
+    batch: &PaymentBatch,
+    response: &ImportResponse,
+) -> std::io::Result<()> {
+    let header = ["#", "AMOUNT", "CUR", "TO", "STATUS", "MESSAGE"];
+    let rows: Vec<[String; 6]> = response
+        .orders
+        .iter()
+        .flat_map(|result| {
+            let order = result.id.checked_sub(1).and_then(|i| batch.orders.get(i));
+            result.messages.iter().map(move |message| {
+                [
+                    result.id.to_string(),
+                    order.map(|o| o.amount.to_string()).unwrap_or_default(),
+                    order.map(|o| o.currency.clone()).unwrap_or_default(),
+                    order.map(|o| o.account_to.clone()).unwrap_or_default(),
+                    message.status.clone(),
+                    format!("{} ({})", message.text, message.error_code),
+                ]
+            })
+        })
+        .collect();
+    write_rows(out, &header, &rows, &[0, 1])?;
+    let instruction = response
+        .id_instruction
+        .map_or_else(|| "-".to_string(), |id| id.to_string());
+    writeln!(
+        out,
+        "Status {}, error code {}, instruction {}",
+        response.status, response.error_code, instruction
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSV: &str = "\
+type,account_from,currency,amount,account_to,date,vs,message,benef_name
+domestic,2000000018,CZK,1500.50,19-2000145399/0800,2025-03-10,2025001,Invoice 2025001,
+euro,2000000018,EUR,20,DE89370400440532013000,2025-03-10,,,Muster GmbH
+";
+
+    const YAML: &str = "
+- type: domestic
+  account_from: '2000000018'
+  currency: CZK
+  amount: 1500.50
+  account_to: 19-2000145399/0800
+  date: 2025-03-10
+  vs: '2025001'
+  message: Invoice 2025001
+- type: euro
+  account_from: '2000000018'
+  currency: EUR
+  amount: '20'
+  account_to: DE89370400440532013000
+  date: 2025-03-10
+  benef_name: Muster GmbH
+";
+
+    #[test]
+    fn csv_and_yaml_batches_agree() {
+        let csv = parse_batch(CSV, BatchFormat::Csv).unwrap();
+        let yaml = parse_batch(YAML, BatchFormat::Yaml).unwrap();
+        assert_eq!(csv, yaml);
+        assert_eq!(csv.orders[0].amount.to_string(), "1500.50");
+        assert_eq!(csv.orders[1].vs, None);
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        assert_eq!(csv.validate(today), Ok(()));
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn submits_batch_and_tracks_receipt() {
+        use fioapi::TokenScope;
+        use fioapi::mock::{MockFixture, MockServer};
+
+        let token = "e".repeat(64);
+        let server = MockServer::start(
+            MockFixture::new(token.clone()).with_token_scope(TokenScope::ReadWrite),
+        )
+        .await
+        .unwrap();
+        let client = Client::from_token(WriteToken::new(token).unwrap())
+            .unwrap()
+            .with_base_url(server.base_url());
+        let batch = parse_batch(CSV, BatchFormat::Csv).unwrap();
+        let dir = std::env::temp_dir().join(format!("fioapi-pay-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("batch.csv");
+        let receipt = dir.join("batch.csv.receipt-1.json");
+        let read_receipt = |path: &Path| -> serde_json::Value {
+            serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+        };
+
+        let response = submit("czk", &client, &batch, &source, &receipt)
+            .await
+            .unwrap();
+        assert!(response.is_accepted());
+        assert_eq!(response.orders.len(), 2);
+        assert!(server.imports()[0].contains("<T2Transaction>"));
+
+        let record = read_receipt(&receipt);
+        assert_eq!(record["status"], "submitted");
+        assert_eq!(record["account"], "czk");
+        assert_eq!(record["response"]["id_instruction"], 1);
+        assert_eq!(record["orders"][1]["benef_name"], "Muster GmbH");
+        // Running the same batch again finds the submitted receipt.
+        let next = dir.join("batch.csv.receipt-2.json");
+        assert_eq!(
+            earlier_receipts(&source, &next),
+            [(receipt.clone(), ReceiptStatus::Submitted)]
+        );
+
+        // No answer from Fio: the receipt stays pending and blocks a retry.
+        let unreachable = client.clone().with_base_url("http://127.0.0.1:1");
+        assert!(
+            submit("czk", &unreachable, &batch, &source, &next)
+                .await
+                .is_err()
+        );
+        assert_eq!(read_receipt(&next)["status"], "pending");
+        assert!(read_receipt(&next)["error"].is_string());
+        let retry = dir.join("batch.csv.receipt-3.json");
+        assert_eq!(
+            earlier_receipts(&source, &retry),
+            [
+                (receipt, ReceiptStatus::Submitted),
+                (next, ReceiptStatus::Pending)
+            ]
+        );
+
+        // A batch Fio refused does not block.
+        let refused = dir.join("other.csv");
+        let read_only = MockServer::start(MockFixture::new("f".repeat(64)))
+            .await
+            .unwrap();
+        let client = Client::from_token(WriteToken::new("f".repeat(64)).unwrap())
+            .unwrap()
+            .with_base_url(read_only.base_url());
+        let failed = dir.join("other.csv.receipt-1.json");
+        assert!(
+            submit("czk", &client, &batch, &refused, &failed)
+                .await
+                .is_err()
+        );
+        assert_eq!(read_receipt(&failed)["status"], "failed");
+        assert!(earlier_receipts(&refused, &dir.join("other.csv.receipt-2.json")).is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+//! Rendering of period reports and loading of categorization rules.
+
+use super::output::write_rows;
+use clap::ValueEnum;
+use fioapi::categorize::Categorizer;
+use fioapi::reports::{GroupBy, Report};
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// Aligned text table
+    Table,
+    /// CSV with a header row
+    Csv,
+}
+
+/// Read categorization rules from a .toml or .yaml file.
+pub fn load_rules(path: &Path) -> Result<Categorizer, Box<dyn Error>> {
+    let data =
+        fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    let rules = match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => Categorizer::from_toml(&data)?,
+        Some("yaml" | "yml") => Categorizer::from_yaml(&data)?,
+        _ => return Err(format!("{}: expected a .toml or .yaml file", path.display()).into()),
+    };
+    Ok(rules)
+}
+
+/// One row per period and group; `balance` is the period's closing balance.
+pub fn write_report(
+    out: &mut impl Write,
+    format: ReportFormat,
+    report: &Report,
+) -> Result<(), Box<dyn Error>> {
+    let header = [
+        "PERIOD", "GROUP", "COUNT", "INFLOW", "OUTFLOW", "NET", "BALANCE",
+    ];
+    let mut rows: Vec<[String; 7]> = Vec::new();
+    for period in &report.periods {
+        let balance = period
+            .closing_balance
+            .map(|b| b.to_string())
+            .unwrap_or_default();
+        let groups: Vec<_> = match report.group_by {
+            GroupBy::Total => vec![("total", &period.totals)],
+            _ => period
+                .groups
+                .iter()
+                .map(|g| (g.key.as_str(), &g.totals))
+                .collect(),
+        };
+        for (key, totals) in groups {
+            rows.push([
+                period.label.clone(),
+                key.to_string(),
+                totals.count.to_string(),
+                totals.inflow.to_string(),
+                totals.outflow.to_string(),
+                totals.net.to_string(),
+                balance.clone(),
+            ]);
+        }
+    }
+    match format {
+        ReportFormat::Table => write_rows(out, &header, &rows, &[2, 3, 4, 5, 6])?,
+        ReportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            writer.write_record(header.map(str::to_lowercase))?;
+            for row in &rows {
+                writer.write_record(row)?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fioapi::Transaction;
+    use fioapi::reports::{Period, aggregate};
+    use rust_decimal::Decimal;
+    use serde_json::json;
+
+    #[test]
+    fn csv_report_has_one_row_per_group() {
+        let transactions: Vec<Transaction> = serde_json::from_value(json!([
+            {"transaction_id": 1, "date": "2025-03-02", "amount": "120.50", "currency": "CZK",
+             "transaction_type": "Bezhotovostní příjem"},
+            {"transaction_id": 2, "date": "2025-03-09", "amount": "-20", "currency": "CZK",
+             "transaction_type": "Platba kartou"},
+        ]))
+        .unwrap();
+        let report = aggregate(
+            &transactions,
+            Period::Month,
+            GroupBy::Kind,
+            None,
+            Some(Decimal::from(10)),
+        );
+        let mut out = Vec::new();
+        write_report(&mut out, ReportFormat::Csv, &report).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "period,group,count,inflow,outflow,net,balance\n\
+             2025-03,card-payment,1,0,20,-20,110.50\n\
+             2025-03,incoming-transfer,1,120.50,0,120.50,110.50\n"
+        );
+    }
+}
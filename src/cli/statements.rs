@@ -0,0 +1,264 @@
+//! Bulk download of official account statements.
+//!
+//! Statements are stored as `{dir}/{account}/{year}/{id}.{ext}`. The newest
+//! statement is taken from `lastStatement`; older years are walked from id 1
+//! upwards until Fio reports two consecutive statements do not exist. A
+//! missing statement before that is skipped with a warning in every year. Files
+//! already on disk are not requested again, so an interrupted run can simply
+//! be repeated.
+
+use fioapi::{AccountStatementFmt, ApiError, Client, FioError, StatementData};
+use log::{debug, info, warn};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Counts reported after downloading one account.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadSummary {
+    pub downloaded: usize,
+    pub skipped: usize,
+}
+
+/// File extension used for a statement format.
+pub fn extension(fmt: AccountStatementFmt) -> &'static str {
+    match fmt {
+        AccountStatementFmt::Csv => "csv",
+        AccountStatementFmt::Gpc => "gpc",
+        AccountStatementFmt::Html => "html",
+        AccountStatementFmt::Json => "json",
+        AccountStatementFmt::Ofx => "ofx",
+        AccountStatementFmt::Xml => "xml",
+        AccountStatementFmt::Pdf => "pdf",
+        AccountStatementFmt::Mt940 => "mt940",
+        AccountStatementFmt::CbaXml => "cba.xml",
+        AccountStatementFmt::SbaXml => "sba.xml",
+    }
+}
+
+/// Download every statement of one account from the newest back to `since_year`.
+///
+/// Without `since_year` the walk stops at the first year that has no
+/// statements, i.e. before the account was opened. Missing ids are logged
+/// and skipped in every year; see `Year::download` for where a year ends.
+pub async fn download_statements(
+    alias: &str,
+    client: &Client,
+    dir: &Path,
+    formats: &[AccountStatementFmt],
+    since_year: Option<i32>,
+) -> Result<DownloadSummary, Box<dyn Error>> {
+    let mut summary = DownloadSummary::default();
+    let last = client.fetch_last_account_statement_info().await?;
+    if last.statement_id < 1 {
+        info!("{}: no statements issued yet", alias);
+        return Ok(summary);
+    }
+    let account_dir = dir.join(alias);
+    if since_year.is_some_and(|since| last.year < since) {
+        info!(
+            "{}: newest statement is from {}, nothing to download",
+            alias, last.year
+        );
+        return Ok(summary);
+    }
+
+    let year = Year {
+        alias,
+        client,
+        account_dir: &account_dir,
+        formats,
+    };
+    year.download(last.year, Some(i64::from(last.statement_id)), &mut summary)
+        .await?;
+    let mut current = last.year - 1;
+    while since_year.is_none_or(|since| current >= since) {
+        let found = year.download(current, None, &mut summary).await?;
+        if found == 0 && since_year.is_none() {
+            debug!("{}: no statements in {}, stopping", alias, current);
+            break;
+        }
+        current -= 1;
+    }
+    Ok(summary)
+}
+
+/// What is needed to download the statements of one year.
+struct Year<'a> {
+    alias: &'a str,
+    client: &'a Client,
+    account_dir: &'a Path,
+    formats: &'a [AccountStatementFmt],
+}
+
+impl Year<'_> {
+    /// Download the statements of `year` and return how many exist.
+    ///
+    /// Ids are walked from 1 up to `last_id` when it is known (the newest
+    /// year), otherwise until two ids in a row are missing. Missing ids
+    /// before the end are logged and skipped.
+    async fn download(
+        &self,
+        year: i32,
+        last_id: Option<i64>,
+        summary: &mut DownloadSummary,
+    ) -> Result<usize, Box<dyn Error>> {
+        let mut found = 0;
+        let mut missing = Vec::new();
+        let mut id = 1;
+        while last_id.is_none_or(|last| id <= last) {
+            if download_one(
+                self.client,
+                self.account_dir,
+                year,
+                id,
+                self.formats,
+                summary,
+            )
+            .await?
+            {
+                found += 1;
+                self.report_missing(year, missing.drain(..));
+            } else {
+                missing.push(id);
+                if last_id.is_none() && missing.len() == 2 {
+                    return Ok(found);
+                }
+            }
+            id += 1;
+        }
+        self.report_missing(year, missing.drain(..));
+        Ok(found)
+    }
+
+    fn report_missing(&self, year: i32, ids: impl Iterator<Item = i64>) {
+        for id in ids {
+            warn!(
+                "{}: statement {}/{} is missing, continuing",
+                self.alias, year, id
+            );
+        }
+    }
+}
+
+/// Store one statement in all formats; `false` when Fio does not know it.
+async fn download_one(
+    client: &Client,
+    account_dir: &Path,
+    year: i32,
+    id: i64,
+    formats: &[AccountStatementFmt],
+    summary: &mut DownloadSummary,
+) -> Result<bool, Box<dyn Error>> {
+    let year_dir = account_dir.join(year.to_string());
+    for &fmt in formats {
+        let path = year_dir.join(format!("{}.{}", id, extension(fmt)));
+        if path.exists() {
+            summary.skipped += 1;
+            continue;
+        }
+        let data = match client.fetch_account_statement(year, id, fmt).await {
+            Ok(data) => data,
+            Err(FioError::Api(ApiError::InvalidRequest)) => return Ok(false),
+            Err(err) => return Err(err.into()),
+        };
+        fs::create_dir_all(&year_dir)?;
+        write_atomic(&path, data)?;
+        info!("Wrote {}", path.display());
+        summary.downloaded += 1;
+    }
+    Ok(true)
+}
+
+/// Write via a temporary file so an interrupted run never leaves a partial
+/// statement that would be skipped next time.
+fn write_atomic(path: &Path, data: StatementData) -> std::io::Result<()> {
+    let mut tmp = PathBuf::from(path);
+    tmp.as_mut_os_string().push(".part");
+    match data {
+        StatementData::Text(text) => fs::write(&tmp, text)?,
+        StatementData::Binary(bytes) => fs::write(&tmp, bytes)?,
+    }
+    fs::rename(&tmp, path)
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use fioapi::mock::{MockFixture, MockServer};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn downloads_all_years_and_skips_existing_files() {
+        let token = "d".repeat(64);
+        let server = MockServer::start(
+            MockFixture::new(token.clone())
+                .with_rate_limit(Duration::ZERO)
+                .with_statement(2023, 1, [])
+                .with_statement(2023, 2, [])
+                .with_statement(2024, 1, []),
+        )
+        .await
+        .unwrap();
+        let client = Client::new(token).unwrap().with_base_url(server.base_url());
+        let dir = std::env::temp_dir().join(format!("fioapi-statements-{}", std::process::id()));
+        let formats = [AccountStatementFmt::Pdf, AccountStatementFmt::CbaXml];
+
+        let summary = download_statements("czk", &client, &dir, &formats, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            summary,
+            DownloadSummary {
+                downloaded: 6,
+                skipped: 0
+            }
+        );
+        assert!(dir.join("czk/2023/2.cba.xml").exists());
+        assert!(
+            fs::read(dir.join("czk/2024/1.pdf"))
+                .unwrap()
+                .starts_with(b"%PDF")
+        );
+
+        let requests = server.requests().len();
+        let summary = download_statements("czk", &client, &dir, &formats, Some(2023))
+            .await
+            .unwrap();
+        assert_eq!(summary.skipped, 6);
+        assert_eq!(summary.downloaded, 0);
+        // Only the newest-statement lookup and the probes for 2023/3 and 2023/4.
+        assert_eq!(server.requests().len(), requests + 3);
+
+        let summary = download_statements("czk", &client, &dir, &formats, Some(2025))
+            .await
+            .unwrap();
+        assert_eq!(summary, DownloadSummary::default());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn steps_over_missing_statements_in_every_year() {
+        let token = "d".repeat(64);
+        let server = MockServer::start(
+            MockFixture::new(token.clone())
+                .with_rate_limit(Duration::ZERO)
+                .with_statement(2023, 1, [])
+                .with_statement(2023, 3, [])
+                .with_statement(2024, 1, [])
+                .with_statement(2024, 4, []),
+        )
+        .await
+        .unwrap();
+        let client = Client::new(token).unwrap().with_base_url(server.base_url());
+        let dir = std::env::temp_dir().join(format!("fioapi-gap-{}", std::process::id()));
+
+        let summary = download_statements("czk", &client, &dir, &[AccountStatementFmt::Pdf], None)
+            .await
+            .unwrap();
+        assert_eq!(summary.downloaded, 4);
+        assert!(dir.join("czk/2023/3.pdf").exists());
+        assert!(dir.join("czk/2024/4.pdf").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,603 @@
+//! `fioapi sync`: incremental download into a local transaction archive.
+//!
+//! For every account the command downloads the transactions since the last
+//! download, appends the ones not yet archived, and only then confirms the
+//! new position with `set_last_downloaded_transaction_id`. A lock file per
+//! account keeps overlapping cron runs apart, and hitting the 30 s limit is
+//! reported as "nothing to do" rather than as a failure. Before downloading,
+//! the server cursor is set back to the last archived id, so transactions
+//! fetched by a run that died before archiving them are downloaded again.
+//! Clients are expected to carry a rate limiter so these requests wait for
+//! their slot.
+
+use fioapi::{
+    ApiError, Client, FioError, LenientTransactions, RejectedTransaction, Transaction,
+    TransactionReportFmt,
+};
+use log::{info, warn};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Storage for archived transactions.
+pub trait Archive {
+    /// Highest archived transaction id for `account`.
+    fn last_id(&mut self, account: &str) -> Result<Option<i64>, Box<dyn Error>>;
+
+    /// Store transactions not archived yet and return how many were new.
+    fn append(
+        &mut self,
+        account: &str,
+        transactions: &[Transaction],
+    ) -> Result<usize, Box<dyn Error>>;
+
+    /// Keep entries that could not be parsed for later inspection.
+    fn quarantine(
+        &mut self,
+        account: &str,
+        rejected: &[RejectedTransaction],
+    ) -> Result<(), Box<dyn Error>>;
+
+    /// Path of the lock file guarding `account`.
+    fn lock_path(&self, account: &str) -> PathBuf;
+}
+
+/// One `{account}.jsonl` file per account in a directory.
+pub struct JsonLinesArchive {
+    dir: PathBuf,
+}
+
+impl JsonLinesArchive {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, Box<dyn Error>> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, account: &str, suffix: &str) -> PathBuf {
+        self.dir.join(format!("{account}{suffix}"))
+    }
+
+    fn archived_ids(&self, account: &str) -> Result<HashSet<i64>, Box<dyn Error>> {
+        Ok(self.scan(account)?.ids)
+    }
+
+    /// Read the archive of `account`.
+    ///
+    /// A crash in the middle of an append can leave a last line without its
+    /// newline. If that line does not parse it is ignored here and cut off
+    /// by the next append; the transactions in it were never confirmed to
+    /// Fio, so they are downloaded again.
+    fn scan(&self, account: &str) -> Result<Scan, Box<dyn Error>> {
+        let path = self.path(account, ".jsonl");
+        if !path.exists() {
+            return Ok(Scan::default());
+        }
+        let data = fs::read(&path)?;
+        let mut scan = Scan {
+            len: data.len() as u64,
+            ..Scan::default()
+        };
+        let complete = data.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+        let text = std::str::from_utf8(&data[..complete])
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        for (n, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let txn: Transaction = serde_json::from_str(line)
+                .map_err(|e| format!("{}:{}: {}", path.display(), n + 1, e))?;
+            scan.ids.insert(txn.transaction_id);
+        }
+        let tail = &data[complete..];
+        if !tail.iter().all(u8::is_ascii_whitespace) {
+            match serde_json::from_slice::<Transaction>(tail) {
+                Ok(txn) => {
+                    scan.ids.insert(txn.transaction_id);
+                    scan.missing_newline = true;
+                }
+                Err(_) => {
+                    warn!("Ignoring incomplete last line of {}", path.display());
+                    scan.len = complete as u64;
+                }
+            }
+        }
+        Ok(scan)
+    }
+}
+
+/// Contents of a JSON lines archive.
+#[derive(Default)]
+struct Scan {
+    ids: HashSet<i64>,
+    /// Length of the file without an incomplete last line.
+    len: u64,
+    /// The last line is complete apart from its newline.
+    missing_newline: bool,
+}
+
+impl Archive for JsonLinesArchive {
+    fn last_id(&mut self, account: &str) -> Result<Option<i64>, Box<dyn Error>> {
+        Ok(self.archived_ids(account)?.into_iter().max())
+    }
+
+    fn append(
+        &mut self,
+        account: &str,
+        transactions: &[Transaction],
+    ) -> Result<usize, Box<dyn Error>> {
+        let scan = self.scan(account)?;
+        let mut known = scan.ids;
+        let mut buf = Vec::new();
+        if scan.missing_newline {
+            buf.push(b'\n');
+        }
+        let mut added = 0;
+        for txn in transactions {
+            if known.insert(txn.transaction_id) {
+                serde_json::to_writer(&mut buf, txn)?;
+                buf.push(b'\n');
+                added += 1;
+            }
+        }
+        if added > 0 {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(self.path(account, ".jsonl"))?;
+            file.set_len(scan.len)?;
+            file.seek(SeekFrom::End(0))?;
+            file.write_all(&buf)?;
+            file.sync_all()?;
+        }
+        Ok(added)
+    }
+
+    fn quarantine(
+        &mut self,
+        account: &str,
+        rejected: &[RejectedTransaction],
+    ) -> Result<(), Box<dyn Error>> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path(account, ".rejected.jsonl"))?;
+        for entry in rejected {
+            let line = serde_json::json!({
+                "index": entry.index,
+                "error": entry.error.to_string(),
+                "raw": entry.raw,
+            });
+            writeln!(file, "{line}")?;
+        }
+        file.sync_all()?;
+        Ok(())
+    }
+
+    fn lock_path(&self, account: &str) -> PathBuf {
+        self.path(account, ".lock")
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteArchive;
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::Archive;
+    use fioapi::{RejectedTransaction, Transaction};
+    use rusqlite::{Connection, OptionalExtension, params};
+    use std::error::Error;
+    use std::path::{Path, PathBuf};
+
+    /// All accounts in a single SQLite database.
+    pub struct SqliteArchive {
+        path: PathBuf,
+        conn: Connection,
+    }
+
+    impl SqliteArchive {
+        pub fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+            let conn = Connection::open(path)?;
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS transactions (
+                    account TEXT NOT NULL,
+                    transaction_id INTEGER NOT NULL,
+                    date TEXT NOT NULL,
+                    amount TEXT NOT NULL,
+                    currency TEXT NOT NULL,
+                    data TEXT NOT NULL,
+                    PRIMARY KEY (account, transaction_id)
+                );
+                CREATE TABLE IF NOT EXISTS rejected (
+                    account TEXT NOT NULL,
+                    position INTEGER NOT NULL,
+                    error TEXT NOT NULL,
+                    raw TEXT NOT NULL,
+                    recorded_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+                );",
+            )?;
+            Ok(Self {
+                path: path.to_path_buf(),
+                conn,
+            })
+        }
+    }
+
+    impl Archive for SqliteArchive {
+        fn last_id(&mut self, account: &str) -> Result<Option<i64>, Box<dyn Error>> {
+            let id = self
+                .conn
+                .query_row(
+                    "SELECT MAX(transaction_id) FROM transactions WHERE account = ?1",
+                    params![account],
+                    |row| row.get::<_, Option<i64>>(0),
+                )
+                .optional()?
+                .flatten();
+            Ok(id)
+        }
+
+        fn append(
+            &mut self,
+            account: &str,
+            transactions: &[Transaction],
+        ) -> Result<usize, Box<dyn Error>> {
+            let tx = self.conn.transaction()?;
+            let mut added = 0;
+            {
+                let mut insert = tx.prepare(
+                    "INSERT OR IGNORE INTO transactions
+                        (account, transaction_id, date, amount, currency, data)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                )?;
+                for txn in transactions {
+                    added += insert.execute(params![
+                        account,
+                        txn.transaction_id,
+                        txn.date.to_string(),
+                        txn.amount.to_string(),
+                        txn.currency,
+                        serde_json::to_string(txn)?,
+                    ])?;
+                }
+            }
+            tx.commit()?;
+            Ok(added)
+        }
+
+        fn quarantine(
+            &mut self,
+            account: &str,
+            rejected: &[RejectedTransaction],
+        ) -> Result<(), Box<dyn Error>> {
+            let tx = self.conn.transaction()?;
+            for entry in rejected {
+                tx.execute(
+                    "INSERT INTO rejected (account, position, error, raw) VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        account,
+                        entry.index as i64,
+                        entry.error.to_string(),
+                        entry.raw.to_string()
+                    ],
+                )?;
+            }
+            tx.commit()?;
+            Ok(())
+        }
+
+        fn lock_path(&self, account: &str) -> PathBuf {
+            let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+            name.push(format!(".{account}.lock"));
+            self.path.with_file_name(name)
+        }
+    }
+}
+
+/// Outcome of syncing one account.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SyncOutcome {
+    Synced {
+        added: usize,
+        rejected: usize,
+        last_id: Option<i64>,
+    },
+    /// Fio answered 409 because the token was used less than 30 s ago.
+    RateLimited,
+    /// Another sync holds the lock for this account.
+    Locked,
+    /// Every downloaded row was quarantined. Fio's cursor has already moved
+    /// past them, so until someone reviews the quarantine file they exist
+    /// nowhere else.
+    QuarantinedOnly { rejected: usize },
+}
+
+/// Sync a single account into `archive`.
+pub async fn sync_account(
+    alias: &str,
+    client: &Client,
+    archive: &mut dyn Archive,
+) -> Result<SyncOutcome, Box<dyn Error>> {
+    let Some(_lock) = try_lock(&archive.lock_path(alias))? else {
+        return Ok(SyncOutcome::Locked);
+    };
+    let previous = archive.last_id(alias)?;
+    if let Some(previous) = previous {
+        match client.set_last_downloaded_transaction_id(previous).await {
+            Ok(()) => {}
+            Err(FioError::Api(ApiError::TimeLimit)) => return Ok(SyncOutcome::RateLimited),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let payload = match client
+        .fetch_transaction_report_since_last_download(TransactionReportFmt::Json)
+        .await
+    {
+        Ok(payload) => payload,
+        Err(FioError::Api(ApiError::TimeLimit)) => return Ok(SyncOutcome::RateLimited),
+        Err(e) => return Err(e.into()),
+    };
+    let parsed = client.parse_transactions_lenient(&payload)?;
+
+    let stored = archive
+        .append(alias, &parsed.transactions)
+        .and_then(|added| {
+            if !parsed.rejected.is_empty() {
+                archive.quarantine(alias, &parsed.rejected)?;
+            }
+            Ok(added)
+        });
+    let newest = parsed.transactions.iter().map(|t| t.transaction_id).max();
+
+    let added = match stored {
+        Ok(added) => added,
+        Err(e) => {
+            // Fetching moved the server cursor; put it back so the next run
+            // downloads the same transactions again. The archive error is
+            // what the caller needs to see, so a failed reset is only logged.
+            if let Err(reset) = reset_cursor(client, alias, previous, &payload, &parsed).await {
+                warn!("Resetting server cursor of {} failed: {}", alias, reset);
+            }
+            return Err(e);
+        }
+    };
+
+    let Some(newest) = newest else {
+        if parsed.rejected.is_empty() {
+            return Ok(SyncOutcome::Synced {
+                added,
+                rejected: 0,
+                last_id: previous,
+            });
+        }
+        warn!(
+            "All {} downloaded transactions of {} were quarantined",
+            parsed.rejected.len(),
+            alias
+        );
+        return Ok(SyncOutcome::QuarantinedOnly {
+            rejected: parsed.rejected.len(),
+        });
+    };
+    client.set_last_downloaded_transaction_id(newest).await?;
+    info!("Confirmed {} up to transaction {}", alias, newest);
+    Ok(SyncOutcome::Synced {
+        added,
+        rejected: parsed.rejected.len(),
+        last_id: Some(newest),
+    })
+}
+
+/// Move the server cursor back to where this run started.
+///
+/// Uses the last archived id when there is one. On the first sync there is
+/// none, so the cursor goes back to the first day of the downloaded period
+/// (or of its oldest transaction) instead; the next download starts there.
+async fn reset_cursor(
+    client: &Client,
+    alias: &str,
+    previous: Option<i64>,
+    payload: &str,
+    parsed: &LenientTransactions,
+) -> Result<(), FioError> {
+    if let Some(previous) = previous {
+        warn!(
+            "Archiving {} failed, resetting server cursor to {}",
+            alias, previous
+        );
+        return client.set_last_downloaded_transaction_id(previous).await;
+    }
+    let start = client
+        .parse_account_info(payload)
+        .ok()
+        .and_then(|info| info.date_start)
+        .or_else(|| parsed.transactions.iter().map(|t| t.date).min());
+    let Some(start) = start else {
+        return Err(FioError::InvalidResponse);
+    };
+    warn!(
+        "Archiving {} failed, resetting server cursor to {}",
+        alias, start
+    );
+    client.set_last_unsuccessful_download_date(start).await
+}
+
+/// Take an exclusive lock on `path`; `None` when another process holds it.
+fn try_lock(path: &Path) -> Result<Option<File>, Box<dyn Error>> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)?;
+    match file.try_lock() {
+        Ok(()) => Ok(Some(file)),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(e)) => Err(e.into()),
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use fioapi::ReqwestTransport;
+    use fioapi::mock::{MockFixture, MockServer};
+    use fioapi::rate_limit::FIO_REQUEST_INTERVAL;
+    use rust_decimal::Decimal;
+
+    fn token() -> String {
+        "c".repeat(64)
+    }
+
+    /// Client without reqwest's request timeout, which would fire as soon
+    /// as paused test time auto-advances.
+    fn paused_client(server: &MockServer) -> Client {
+        let transport = ReqwestTransport::from_client(reqwest::Client::new());
+        Client::from_transport(token(), transport)
+            .unwrap()
+            .with_base_url(server.base_url())
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("fioapi-sync-{}-{}", name, std::process::id()))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sync_is_incremental_and_idempotent() {
+        let date = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let server = MockServer::start(
+            MockFixture::new(token())
+                .with_rate_limit(FIO_REQUEST_INTERVAL)
+                .with_transactions(
+                    (1..=3).map(|id| Transaction::new(id, date, Decimal::ONE, "CZK")),
+                ),
+        )
+        .await
+        .unwrap();
+        let client = paused_client(&server).with_rate_limit(FIO_REQUEST_INTERVAL);
+        let dir = temp_dir("jsonl");
+        let mut archive = JsonLinesArchive::new(&dir).unwrap();
+
+        let outcome = sync_account("czk", &client, &mut archive).await.unwrap();
+        assert_eq!(
+            outcome,
+            SyncOutcome::Synced {
+                added: 3,
+                rejected: 0,
+                last_id: Some(3)
+            }
+        );
+        assert_eq!(server.last_downloaded_id(), Some(3));
+
+        // Another run right away, e.g. from cron: Fio answers 409, nothing changes.
+        let other_run = paused_client(&server);
+        let outcome = sync_account("czk", &other_run, &mut archive).await.unwrap();
+        assert_eq!(outcome, SyncOutcome::RateLimited);
+
+        // Rewind the server cursor by hand; the next run sets it back to the
+        // last archived id, so nothing is downloaded or duplicated.
+        client.set_last_downloaded_transaction_id(1).await.unwrap();
+        let outcome = sync_account("czk", &client, &mut archive).await.unwrap();
+        assert_eq!(
+            outcome,
+            SyncOutcome::Synced {
+                added: 0,
+                rejected: 0,
+                last_id: Some(3)
+            }
+        );
+        assert_eq!(archive.last_id("czk").unwrap(), Some(3));
+        let lines = fs::read_to_string(dir.join("czk.jsonl"))
+            .unwrap()
+            .lines()
+            .count();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(lines, 3);
+    }
+
+    #[test]
+    fn torn_last_line_is_ignored_and_repaired() {
+        let date = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let dir = temp_dir("torn");
+        let mut archive = JsonLinesArchive::new(&dir).unwrap();
+        archive
+            .append("czk", &[Transaction::new(1, date, Decimal::ONE, "CZK")])
+            .unwrap();
+        let path = dir.join("czk.jsonl");
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"transaction_id":2,"da"#).unwrap();
+
+        assert_eq!(archive.last_id("czk").unwrap(), Some(1));
+        let added = archive
+            .append(
+                "czk",
+                &[
+                    Transaction::new(2, date, Decimal::ONE, "CZK"),
+                    Transaction::new(3, date, Decimal::ONE, "CZK"),
+                ],
+            )
+            .unwrap();
+        let ids = archive.archived_ids("czk").unwrap();
+        let lines = fs::read_to_string(&path).unwrap().lines().count();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(added, 2);
+        assert_eq!(ids, HashSet::from([1, 2, 3]));
+        assert_eq!(lines, 3);
+    }
+
+    /// Archive that cannot store anything, as on a full disk.
+    struct FullArchive(PathBuf);
+
+    impl Archive for FullArchive {
+        fn last_id(&mut self, _account: &str) -> Result<Option<i64>, Box<dyn Error>> {
+            Ok(None)
+        }
+
+        fn append(&mut self, _account: &str, _: &[Transaction]) -> Result<usize, Box<dyn Error>> {
+            Err("disk full".into())
+        }
+
+        fn quarantine(
+            &mut self,
+            _account: &str,
+            _: &[RejectedTransaction],
+        ) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+
+        fn lock_path(&self, account: &str) -> PathBuf {
+            self.0.join(format!("{account}.lock"))
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn first_sync_failure_restores_server_cursor() {
+        let date = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let server = MockServer::start(
+            MockFixture::new(token())
+                .with_rate_limit(FIO_REQUEST_INTERVAL)
+                .with_transactions(
+                    (1..=3).map(|id| Transaction::new(id, date, Decimal::ONE, "CZK")),
+                ),
+        )
+        .await
+        .unwrap();
+        let client = paused_client(&server).with_rate_limit(FIO_REQUEST_INTERVAL);
+        let dir = temp_dir("full");
+        fs::create_dir_all(&dir).unwrap();
+
+        let err = sync_account("czk", &client, &mut FullArchive(dir.clone()))
+            .await
+            .unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(err.to_string(), "disk full");
+        // The next run downloads the same transactions again.
+        assert_eq!(server.last_downloaded_id(), None);
+        assert!(server.requests()[1].starts_with("/set-last-date/"));
+    }
+}
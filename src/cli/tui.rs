@@ -0,0 +1,401 @@
+//! Interactive browser for a fetched transaction list.
+//!
+//! Keys: `↑`/`↓` (or `j`/`k`), `PgUp`/`PgDn`, `Home`/`End` move the selection;
+//! `/` edits the filter, written in the [`query`](fioapi::query) language
+//! used by `--filter`. `c`, `a`, `s` and `t` start the edit with a
+//! counterparty name, amount, symbol or type term appended. `Enter` applies
+//! and `Esc` cancels the edit; `x` clears the filter and `q` quits.
+
+use fioapi::query::Filter;
+use fioapi::{AccountInfo, Transaction};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use rust_decimal::Decimal;
+use std::io;
+
+/// Term appended to the query by a shortcut key, e.g. `a` for `amount`.
+fn shortcut_term(key: char) -> Option<&'static str> {
+    match key {
+        'c' => Some("name:"),
+        'a' => Some("amount"),
+        's' => Some("vs="),
+        't' => Some("type:"),
+        _ => None,
+    }
+}
+
+fn counterparty_of(txn: &Transaction) -> Option<String> {
+    match (&txn.account_name, &txn.account_id, &txn.bank_id) {
+        (Some(name), Some(account), Some(bank)) => Some(format!("{name} {account}/{bank}")),
+        (None, Some(account), Some(bank)) => Some(format!("{account}/{bank}")),
+        (name, account, _) => name.clone().or_else(|| account.clone()),
+    }
+}
+
+struct App {
+    info: AccountInfo,
+    transactions: Vec<Transaction>,
+    /// Applied query and the filter parsed from it.
+    query: String,
+    filter: Filter,
+    /// Indices into `transactions` passing the filter.
+    visible: Vec<usize>,
+    table: TableState,
+    /// Draft of the query being edited.
+    editing: Option<String>,
+    /// Why the last draft could not be applied.
+    error: Option<String>,
+}
+
+impl App {
+    fn new(info: AccountInfo, transactions: Vec<Transaction>) -> Self {
+        let mut app = Self {
+            info,
+            transactions,
+            query: String::new(),
+            filter: Filter::Any,
+            visible: Vec::new(),
+            table: TableState::default(),
+            editing: None,
+            error: None,
+        };
+        app.apply_filters();
+        app
+    }
+
+    fn apply_filters(&mut self) {
+        self.visible = (0..self.transactions.len())
+            .filter(|&i| self.filter.matches(&self.transactions[i]))
+            .collect();
+        self.table.select((!self.visible.is_empty()).then_some(0));
+    }
+
+    fn selected(&self) -> Option<&Transaction> {
+        self.table
+            .selected()
+            .and_then(|i| self.visible.get(i))
+            .map(|&i| &self.transactions[i])
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        if self.visible.is_empty() {
+            return;
+        }
+        let last = self.visible.len() - 1;
+        let current = self.table.selected().unwrap_or(0);
+        self.table
+            .select(Some(current.saturating_add_signed(delta).min(last)));
+    }
+
+    /// Handle a key press; returns `false` when the app should quit.
+    fn on_key(&mut self, code: KeyCode) -> bool {
+        if let Some(draft) = &mut self.editing {
+            match code {
+                KeyCode::Enter => match Filter::parse(draft) {
+                    Ok(filter) => {
+                        self.query = std::mem::take(draft).trim().to_string();
+                        self.filter = filter;
+                        self.editing = None;
+                        self.error = None;
+                        self.apply_filters();
+                    }
+                    // Keep editing so the typo can be fixed.
+                    Err(e) => self.error = Some(e.to_string()),
+                },
+                KeyCode::Esc => {
+                    self.editing = None;
+                    self.error = None;
+                }
+                KeyCode::Backspace => {
+                    draft.pop();
+                }
+                KeyCode::Char(c) => draft.push(c),
+                _ => {}
+            }
+            return true;
+        }
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::PageDown => self.move_by(20),
+            KeyCode::PageUp => self.move_by(-20),
+            KeyCode::Home => self.move_by(isize::MIN),
+            KeyCode::End => self.move_by(isize::MAX),
+            KeyCode::Char('x') => {
+                self.query.clear();
+                self.filter = Filter::Any;
+                self.apply_filters();
+            }
+            KeyCode::Char('/') => self.editing = Some(self.query.clone()),
+            KeyCode::Char(c) => {
+                let Some(term) = shortcut_term(c) else {
+                    return true;
+                };
+                let draft = if self.query.is_empty() {
+                    term.to_string()
+                } else {
+                    format!("{} {}", self.query, term)
+                };
+                self.editing = Some(draft);
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(4),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [list, detail] =
+            Layout::horizontal([Constraint::Percentage(62), Constraint::Percentage(38)])
+                .areas(body);
+
+        frame.render_widget(self.account_header(), header);
+        self.draw_list(frame, list);
+        frame.render_widget(self.detail_pane(), detail);
+        frame.render_widget(self.status_line(), footer);
+    }
+
+    fn account_header(&self) -> Paragraph<'_> {
+        let info = &self.info;
+        let text = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".to_string());
+        let money = |v: Option<Decimal>| v.map_or_else(|| "-".to_string(), |v| v.to_string());
+        let date =
+            |v: Option<chrono::NaiveDate>| v.map_or_else(|| "-".to_string(), |v| v.to_string());
+        let account = match (&info.account_id, &info.bank_id) {
+            (Some(account), Some(bank)) => format!("{account}/{bank}"),
+            _ => text(&info.account_id),
+        };
+        let lines = vec![
+            Line::from(format!(
+                "{}  IBAN {}  BIC {}  {}",
+                account,
+                text(&info.iban),
+                text(&info.bic),
+                text(&info.currency)
+            )),
+            Line::from(format!(
+                "{} – {}  opening {}  closing {}",
+                date(info.date_start),
+                date(info.date_end),
+                money(info.opening_balance),
+                money(info.closing_balance)
+            )),
+        ];
+        Paragraph::new(lines).block(Block::bordered().title(" Account "))
+    }
+
+    fn draw_list(&mut self, frame: &mut Frame, area: Rect) {
+        let rows: Vec<Row> = self
+            .visible
+            .iter()
+            .map(|&i| {
+                let t = &self.transactions[i];
+                let color = if t.amount.is_sign_negative() {
+                    Color::Red
+                } else {
+                    Color::Green
+                };
+                Row::new(vec![
+                    Span::raw(t.date.to_string()),
+                    Span::styled(format!("{:>12}", t.amount), Style::new().fg(color)),
+                    Span::raw(t.currency.clone()),
+                    Span::raw(t.vs.map(|v| v.to_string()).unwrap_or_default()),
+                    Span::raw(counterparty_of(t).unwrap_or_default()),
+                ])
+            })
+            .collect();
+        let title = format!(
+            " Transactions {}/{} ",
+            self.visible.len(),
+            self.transactions.len()
+        );
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(10),
+                Constraint::Length(12),
+                Constraint::Length(3),
+                Constraint::Length(10),
+                Constraint::Min(10),
+            ],
+        )
+        .header(
+            Row::new(["DATE", "      AMOUNT", "CUR", "VS", "COUNTERPARTY"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+        .block(Block::bordered().title(title));
+        frame.render_stateful_widget(table, area, &mut self.table);
+    }
+
+    fn detail_pane(&self) -> Paragraph<'_> {
+        let block = Block::bordered().title(" Detail ");
+        let Some(txn) = self.selected() else {
+            return Paragraph::new("No transaction matches the filters").block(block);
+        };
+        // Serialize to show every column, including ones added to the model later.
+        let fields = serde_json::to_value(txn)
+            .ok()
+            .and_then(|v| v.as_object().cloned())
+            .unwrap_or_default();
+        let lines: Vec<Line> = fields
+            .iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(name, value)| {
+                let value = match value {
+                    serde_json::Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                Line::from(vec![
+                    Span::styled(format!("{name}: "), Style::new().fg(Color::Cyan)),
+                    Span::raw(value),
+                ])
+            })
+            .collect();
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(block)
+    }
+
+    fn status_line(&self) -> Line<'_> {
+        if let Some(draft) = &self.editing {
+            let hint = match &self.error {
+                Some(error) => error.clone(),
+                None => "Enter apply, Esc cancel".to_string(),
+            };
+            return Line::from(format!("Filter: {draft}▏ ({hint})"));
+        }
+        let filter = if self.query.is_empty() {
+            "no filter"
+        } else {
+            &self.query
+        };
+        Line::from(format!(
+            "{filter} | / filter  c/a/s/t add term  x clear  ↑↓ move  q quit"
+        ))
+    }
+}
+
+/// Run the browser until the user quits, restoring the terminal afterwards.
+pub fn browse(info: AccountInfo, transactions: Vec<Transaction>) -> io::Result<()> {
+    let mut app = App::new(info, transactions);
+    ratatui::run(|terminal| event_loop(terminal, &mut app))
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && !app.on_key(key.code)
+        {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    fn txn(id: i64, amount: i64, vs: Option<i64>, name: &str, kind: &str) -> Transaction {
+        let date = chrono::NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        Transaction {
+            account_name: Some(name.to_string()),
+            vs,
+            transaction_type: Some(kind.to_string()),
+            ..Transaction::new(id, date, Decimal::from(amount), "CZK")
+        }
+    }
+
+    #[test]
+    fn filters_and_renders_transactions() {
+        let info: AccountInfo = serde_json::from_value(serde_json::json!({
+            "accountId": "2000000018",
+            "bankId": "2010",
+            "closingBalance": 4200,
+            "dateStart": "2025-03-01+0100",
+            "dateEnd": "2025-03-31+0100",
+        }))
+        .unwrap();
+        let mut app = App::new(
+            info,
+            vec![
+                txn(
+                    1,
+                    1500,
+                    Some(2025001),
+                    "Alza.cz a.s.",
+                    "Příjem převodem uvnitř banky",
+                ),
+                txn(2, -300, None, "Lidl", "Platba kartou"),
+                txn(3, 250, Some(77), "Novák Jan", "Bezhotovostní příjem"),
+            ],
+        );
+        for key in "a>0".chars().map(KeyCode::Char).chain([KeyCode::Enter]) {
+            app.on_key(key);
+        }
+        assert_eq!(app.visible, [0, 2]);
+        for key in "sxx".chars().map(KeyCode::Char) {
+            app.on_key(key);
+        }
+        app.on_key(KeyCode::Backspace);
+        app.on_key(KeyCode::Backspace);
+        for key in "77".chars().map(KeyCode::Char).chain([KeyCode::Enter]) {
+            app.on_key(key);
+        }
+        assert_eq!(app.visible, [2]);
+        assert_eq!(app.query, "amount>0 vs=77");
+        assert_eq!(app.selected().unwrap().transaction_id, 3);
+
+        let mut terminal = Terminal::new(TestBackend::new(120, 20)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("2000000018/2010"));
+        assert!(screen.contains("closing 4200"));
+        assert!(screen.contains("Novák Jan"));
+        assert!(screen.contains("Transactions 1/3"));
+        assert!(!screen.contains("Lidl"));
+
+        app.on_key(KeyCode::Char('x'));
+        assert_eq!(app.visible.len(), 3);
+        for key in "cnovák".chars().map(KeyCode::Char).chain([KeyCode::Enter]) {
+            app.on_key(key);
+        }
+        assert_eq!(app.visible, [2]);
+
+        // An invalid query keeps the editor open and the old filter applied.
+        app.on_key(KeyCode::Char('/'));
+        for key in " amount>abc"
+            .chars()
+            .map(KeyCode::Char)
+            .chain([KeyCode::Enter])
+        {
+            app.on_key(key);
+        }
+        assert!(app.error.is_some());
+        assert_eq!(app.editing.as_deref(), Some("name:novák amount>abc"));
+        assert_eq!(app.visible, [2]);
+        app.on_key(KeyCode::Esc);
+        assert_eq!(app.query, "name:novák");
+        assert!(!app.on_key(KeyCode::Char('q')));
+    }
+}
@@ -1,13 +1,32 @@
+use crate::cassette::{Cassette, CassetteMode};
+#[cfg(feature = "reqwest")]
+use crate::credentials::TokenSource;
 use crate::error::{ApiError, FioError};
-use crate::models::{AccountInfo, Transaction, parse_account_info, parse_transactions};
+use crate::merchant::{MerchantReportFmt, MerchantTransaction, parse_merchant_transactions};
+use crate::models::{
+    AccountInfo, LenientTransactions, Statement, Transaction, parse_account_info, parse_statement,
+    parse_transactions, parse_transactions_lenient,
+};
+use crate::payments::{ImportResponse, PaymentBatch};
+#[cfg(feature = "rate-limit")]
+use crate::rate_limit::RateLimiter;
+#[cfg(feature = "reqwest")]
+use crate::token::FioToken;
+use crate::token::{ApiToken, ReadToken, TokenScope, WriteToken};
+#[cfg(feature = "reqwest")]
+use crate::transport::ReqwestTransport;
+use crate::transport::{HttpResponse, Transport};
 use chrono::NaiveDate;
-use log::{debug, info};
-use reqwest::{Client as HttpClient, Response, StatusCode};
+use http::StatusCode;
+use log::{debug, info, warn};
 use std::fmt;
-use std::time::Duration;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 const BASE_URL: &str = "https://fioapi.fio.cz/v1/rest";
-const TOKEN_LENGTH: usize = 64;
+const MULTIPART_BOUNDARY: &str = "----fioapi-import-boundary";
+const EMPTY_IMPORT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Import xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:noNamespaceSchemaLocation="http://www.fio.cz/schema/importIB.xsd"><Orders></Orders></Import>"#;
 
 #[derive(Debug, Clone, Copy)]
 pub enum TransactionReportFmt {
@@ -65,6 +84,27 @@ impl fmt::Display for AccountStatementFmt {
     }
 }
 
+/// Format of a payment order batch submitted through the import endpoint.
+#[derive(Debug, Clone, Copy)]
+pub enum ImportFmt {
+    Abo,
+    Xml,
+    Pain001,
+    Pain008,
+}
+
+impl fmt::Display for ImportFmt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v = match self {
+            ImportFmt::Abo => "abo",
+            ImportFmt::Xml => "xml",
+            ImportFmt::Pain001 => "pain001.xml",
+            ImportFmt::Pain008 => "pain008.xml",
+        };
+        f.write_str(v)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum StatementData {
     Text(String),
@@ -77,34 +117,91 @@ pub struct LastStatementInfo {
     pub statement_id: i32,
 }
 
+/// Fio API client.
+///
+/// The token type decides which endpoints are available: every client can
+/// read account data, while payment import requires a `Client<WriteToken>`.
 #[derive(Debug, Clone)]
-pub struct Client {
-    token: String,
-    http: HttpClient,
+pub struct Client<T: ApiToken = ReadToken> {
+    token: T,
+    http: Arc<dyn Transport>,
     base_url: String,
+    cassette: Option<Arc<CassetteMode>>,
+    #[cfg(feature = "rate-limit")]
+    rate_limiter: Option<Arc<RateLimiter>>,
 }
 
 impl Client {
-    /// Create a new client with the default base URL.
+    /// Create a new read-only client with the default base URL.
+    #[cfg(feature = "reqwest")]
     pub fn new(token: impl Into<String>) -> Result<Self, FioError> {
-        let token = token.into();
-        if token.len() != TOKEN_LENGTH {
-            return Err(FioError::InvalidTokenLength {
-                expected: TOKEN_LENGTH,
-                actual: token.len(),
-            });
+        Self::from_token(ReadToken::new(token)?)
+    }
+
+    /// Create a new read-only client that sends requests through a custom transport.
+    pub fn from_transport(
+        token: impl Into<String>,
+        transport: impl Transport + 'static,
+    ) -> Result<Self, FioError> {
+        Ok(Self::from_token_and_transport(
+            ReadToken::new(token)?,
+            transport,
+        ))
+    }
+
+    /// Probe the token and return a write-capable client if it can submit payments.
+    ///
+    /// Returns [`ApiError::Authorization`] when the token is read-only. The
+    /// probe uses up the token's 30 s request slot; see
+    /// [`Client::probe_token_scope`].
+    pub async fn into_write(self) -> Result<Client<WriteToken>, FioError> {
+        match self.probe_token_scope().await? {
+            TokenScope::ReadWrite => Ok(Client {
+                token: WriteToken::from(self.token.token().clone()),
+                http: self.http,
+                base_url: self.base_url,
+                cassette: self.cassette,
+                #[cfg(feature = "rate-limit")]
+                rate_limiter: self.rate_limiter,
+            }),
+            TokenScope::Read => Err(FioError::Api(ApiError::Authorization)),
         }
+    }
+}
 
-        let http = HttpClient::builder()
-            .timeout(Duration::from_secs(10))
-            .build()?;
+impl<T: ApiToken> Client<T> {
+    /// Create a new client for a typed token with the default base URL.
+    #[cfg(feature = "reqwest")]
+    pub fn from_token(token: T) -> Result<Self, FioError> {
+        Ok(Self::from_token_and_transport(
+            token,
+            ReqwestTransport::new()?,
+        ))
+    }
+
+    /// Create a new client with a token loaded from `source`.
+    #[cfg(feature = "reqwest")]
+    pub fn from_credentials(source: &TokenSource) -> Result<Self, FioError>
+    where
+        T: From<FioToken>,
+    {
+        Self::from_token(T::from(source.load()?))
+    }
 
-        info!("Initialized Fio API client with default base URL");
-        Ok(Self {
+    /// Create a new client for a typed token and a custom transport.
+    pub fn from_token_and_transport(token: T, transport: impl Transport + 'static) -> Self {
+        info!(
+            "Initialized Fio API client with default base URL ({} token)",
+            T::SCOPE
+        );
+        Self {
             token,
-            http,
+            http: Arc::new(transport),
             base_url: BASE_URL.to_string(),
-        })
+            cassette: None,
+            #[cfg(feature = "rate-limit")]
+            rate_limiter: None,
+        }
     }
 
     /// Override the base URL (useful for tests or proxies).
@@ -114,6 +211,44 @@ impl Client {
         self
     }
 
+    /// Space requests at least `interval` apart instead of failing with 409.
+    ///
+    /// Use [`FIO_REQUEST_INTERVAL`](crate::rate_limit::FIO_REQUEST_INTERVAL)
+    /// for the limit enforced by Fio. Clones of the client share the limiter.
+    #[cfg(feature = "rate-limit")]
+    pub fn with_rate_limit(mut self, interval: std::time::Duration) -> Self {
+        info!("Rate limiting Fio API requests to one per {:?}", interval);
+        self.rate_limiter = Some(Arc::new(RateLimiter::new(interval)));
+        self
+    }
+
+    /// Record every request and response into a cassette file at `path`.
+    ///
+    /// The token is redacted from recorded paths and bodies, and account
+    /// numbers and counterparty names are masked in JSON bodies, so the
+    /// file can later be replayed with [`Client::with_replay`] without
+    /// exposing the account. Failing to write the cassette is logged and
+    /// does not fail the request.
+    pub fn with_recording(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        info!("Recording Fio API interactions to {}", path.display());
+        self.cassette = Some(Arc::new(CassetteMode::record(path)));
+        self
+    }
+
+    /// Answer requests from a previously recorded cassette instead of the network.
+    ///
+    /// Requests must arrive in the recorded order; any other request fails
+    /// with [`FioError::ReplayMismatch`].
+    pub fn with_replay(mut self, cassette: Cassette) -> Self {
+        info!(
+            "Replaying {} recorded Fio API interactions",
+            cassette.interactions.len()
+        );
+        self.cassette = Some(Arc::new(CassetteMode::replay(cassette)));
+        self
+    }
+
     /// Fetch transaction report for the given period in the requested format.
     pub async fn fetch_transaction_report_for_period(
         &self,
@@ -129,7 +264,7 @@ impl Client {
         }
         let path = format!(
             "/periods/{}/{}/{}/transactions.{}",
-            self.token,
+            self.secret(),
             date_from.format("%Y-%m-%d"),
             date_to.format("%Y-%m-%d"),
             fmt
@@ -141,6 +276,34 @@ impl Client {
         self.get_text(path).await
     }
 
+    /// Fetch card payments accepted by the merchant's POS terminals and
+    /// payment gateway in the given period.
+    pub async fn fetch_merchant_transactions_for_period(
+        &self,
+        date_from: NaiveDate,
+        date_to: NaiveDate,
+        fmt: MerchantReportFmt,
+    ) -> Result<String, FioError> {
+        if date_from > date_to {
+            return Err(FioError::InvalidDateRange {
+                start: date_from,
+                end: date_to,
+            });
+        }
+        let path = format!(
+            "/merchant/{}/{}/{}/transactions.{}",
+            self.secret(),
+            date_from.format("%Y-%m-%d"),
+            date_to.format("%Y-%m-%d"),
+            fmt
+        );
+        debug!(
+            "Fetching merchant transactions for period {} to {} as {}",
+            date_from, date_to, fmt
+        );
+        self.get_text(path).await
+    }
+
     /// Fetch account statement identified by year and statement id.
     pub async fn fetch_account_statement(
         &self,
@@ -155,7 +318,10 @@ impl Client {
         }
         let path = format!(
             "/by-id/{}/{}/{}/transactions.{}",
-            self.token, year, statement_id, fmt
+            self.secret(),
+            year,
+            statement_id,
+            fmt
         );
         debug!(
             "Fetching account statement year={} id={} as {}",
@@ -172,14 +338,14 @@ impl Client {
         &self,
         fmt: TransactionReportFmt,
     ) -> Result<String, FioError> {
-        let path = format!("/last/{}/transactions.{}", self.token, fmt);
+        let path = format!("/last/{}/transactions.{}", self.secret(), fmt);
         debug!("Fetching transaction report since last download as {}", fmt);
         self.get_text(path).await
     }
 
     /// Retrieve metadata about the last available account statement.
     pub async fn fetch_last_account_statement_info(&self) -> Result<LastStatementInfo, FioError> {
-        let path = format!("/lastStatement/{}/statement", self.token);
+        let path = format!("/lastStatement/{}/statement", self.secret());
         debug!("Fetching last account statement metadata");
         let body = self.get_text(path).await?;
         let mut parts = body.split(',');
@@ -204,7 +370,7 @@ impl Client {
                 "transaction_id must be a positive integer",
             ));
         }
-        let path = format!("/set-last-id/{}/{}/", self.token, transaction_id);
+        let path = format!("/set-last-id/{}/{}/", self.secret(), transaction_id);
         info!(
             "Updating last downloaded transaction id to {}",
             transaction_id
@@ -219,7 +385,7 @@ impl Client {
     ) -> Result<(), FioError> {
         let path = format!(
             "/set-last-date/{}/{}/",
-            self.token,
+            self.secret(),
             download_date.format("%Y-%m-%d")
         );
         info!(
@@ -229,6 +395,51 @@ impl Client {
         self.get_void(path).await
     }
 
+    /// Discover whether the token can submit payment orders.
+    ///
+    /// Posts an import batch without any orders, which creates nothing. Only
+    /// a `responseImport` with error code 0 counts as write access; 422 or
+    /// any other error code means a read-only token.
+    ///
+    /// Fio's API documentation does not describe the answer to an empty
+    /// batch, and no recorded answer was available when this was written,
+    /// so error code 0 for a write token is an assumption. If it does not
+    /// hold, the probe errs towards [`TokenScope::Read`] and never reports
+    /// write access that the token lacks; the unexpected code is logged as a
+    /// warning.
+    ///
+    /// The probe is a real request and uses the token's slot: Fio allows one
+    /// request per token every 30 s, so the next call with the same token
+    /// fails with [`ApiError::TimeLimit`] unless the client has a rate
+    /// limiter (see `Client::with_rate_limit`).
+    pub async fn probe_token_scope(&self) -> Result<TokenScope, FioError> {
+        debug!("Probing token scope");
+        let result = self
+            .post_import(EMPTY_IMPORT.as_bytes().to_vec(), ImportFmt::Xml)
+            .await;
+        match result {
+            Ok(body) => {
+                let response = ImportResponse::parse(&body)?;
+                if response.error_code == 0 {
+                    Ok(TokenScope::ReadWrite)
+                } else {
+                    warn!(
+                        "Import probe rejected with error code {} ({})",
+                        response.error_code, response.status
+                    );
+                    Ok(TokenScope::Read)
+                }
+            }
+            Err(FioError::Api(ApiError::Authorization)) => Ok(TokenScope::Read),
+            // The token was accepted by the URL check, so a 500 here is a
+            // server failure rather than a bad token.
+            Err(FioError::Api(ApiError::InvalidToken)) => Err(FioError::Api(
+                ApiError::UnexpectedStatus(StatusCode::INTERNAL_SERVER_ERROR),
+            )),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Parse account info from a JSON string returned by Fio API.
     pub fn parse_account_info(&self, data: &str) -> Result<AccountInfo, FioError> {
         parse_account_info(data)
@@ -239,18 +450,30 @@ impl Client {
         parse_transactions(data)
     }
 
+    /// Parse account info and transactions from a JSON string returned by Fio API.
+    pub fn parse_statement(&self, data: &str) -> Result<Statement, FioError> {
+        parse_statement(data)
+    }
+
+    /// Parse transactions, keeping malformed entries aside instead of failing.
+    pub fn parse_transactions_lenient(&self, data: &str) -> Result<LenientTransactions, FioError> {
+        parse_transactions_lenient(data)
+    }
+
+    /// Parse a JSON merchant report returned by Fio API.
+    pub fn parse_merchant_transactions(
+        &self,
+        data: &str,
+    ) -> Result<Vec<MerchantTransaction>, FioError> {
+        parse_merchant_transactions(data)
+    }
+
     async fn get_text(&self, path: String) -> Result<String, FioError> {
-        let response = self.get(path).await?;
-        response.text().await.map_err(FioError::from)
+        Ok(decode_text(self.get(path).await?))
     }
 
     async fn get_binary(&self, path: String) -> Result<Vec<u8>, FioError> {
-        let response = self.get(path).await?;
-        response
-            .bytes()
-            .await
-            .map(|b| b.to_vec())
-            .map_err(FioError::from)
+        self.get(path).await
     }
 
     async fn get_void(&self, path: String) -> Result<(), FioError> {
@@ -258,14 +481,85 @@ impl Client {
         Ok(())
     }
 
-    async fn get(&self, path: String) -> Result<Response, FioError> {
+    fn secret(&self) -> &str {
+        self.token.token().expose_secret()
+    }
+
+    async fn get(&self, path: String) -> Result<Vec<u8>, FioError> {
+        self.send("GET", path, None).await
+    }
+
+    async fn post_import(&self, document: Vec<u8>, fmt: ImportFmt) -> Result<String, FioError> {
+        let mut body = Vec::new();
+        for (name, value) in [("type", fmt.to_string()), ("token", self.secret().into())] {
+            body.extend_from_slice(
+                format!(
+                    "--{MULTIPART_BOUNDARY}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{value}\r\n"
+                )
+                .as_bytes(),
+            );
+        }
+        body.extend_from_slice(
+            format!(
+                "--{MULTIPART_BOUNDARY}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"import.{fmt}\"\r\nContent-Type: application/octet-stream\r\n\r\n"
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(&document);
+        body.extend_from_slice(format!("\r\n--{MULTIPART_BOUNDARY}--\r\n").as_bytes());
+
+        let content_type = format!("multipart/form-data; boundary={MULTIPART_BOUNDARY}");
+        let response = self
+            .send("POST", "/import/".to_string(), Some((&content_type, body)))
+            .await?;
+        Ok(decode_text(response))
+    }
+
+    async fn send(
+        &self,
+        method: &str,
+        path: String,
+        body: Option<(&str, Vec<u8>)>,
+    ) -> Result<Vec<u8>, FioError> {
         let url = format!("{}{}", self.base_url, path);
-        let redacted_path = path.replace(&self.token, "<token>");
-        debug!("GET request to {}{}", self.base_url, redacted_path);
-        let response = self.http.get(url).send().await?;
-        debug!("Received status {}", response.status());
-        self.handle_status(response.status())?;
-        Ok(response)
+        let redacted_path = self.token.token().redact(&path);
+        debug!("{} request to {}{}", method, self.base_url, redacted_path);
+        let (status, body) = match self.cassette.as_deref() {
+            Some(cassette) if cassette.is_replay() => {
+                let (status, body) = cassette.next(method, &redacted_path)?;
+                let status = StatusCode::from_u16(status).map_err(|_| FioError::InvalidResponse)?;
+                (status, body)
+            }
+            cassette => {
+                #[cfg(feature = "rate-limit")]
+                if let Some(limiter) = &self.rate_limiter {
+                    limiter.acquire().await;
+                }
+                let response = match body {
+                    Some((content_type, body)) => self.http.post(&url, content_type, body).await,
+                    None => self.http.get(&url).await,
+                };
+                let HttpResponse { status, body } =
+                    response.map_err(|e| e.redact(self.token.token()))?;
+                if let Some(cassette) = cassette {
+                    // The request already happened; a cassette problem must
+                    // not report e.g. a sent payment as failed.
+                    if let Err(e) = cassette.store(
+                        method,
+                        &redacted_path,
+                        status.as_u16(),
+                        &body,
+                        self.token.token(),
+                    ) {
+                        warn!("Cannot record {} {}: {}", method, redacted_path, e);
+                    }
+                }
+                (status, body)
+            }
+        };
+        debug!("Received status {}", status);
+        self.handle_status(status)?;
+        Ok(body)
     }
 
     fn handle_status(&self, status: StatusCode) -> Result<(), FioError> {
@@ -283,3 +577,58 @@ impl Client {
         Err(FioError::Api(api_error))
     }
 }
+
+/// Decode a text response.
+///
+/// JSON and XML come as UTF-8; the legacy formats (GPC, ABO and some HTML
+/// and CSV exports) use windows-1250, which is the fallback for bodies that
+/// are not valid UTF-8.
+fn decode_text(body: Vec<u8>) -> String {
+    match String::from_utf8(body) {
+        Ok(text) => text,
+        Err(e) => encoding_rs::WINDOWS_1250
+            .decode_without_bom_handling(e.as_bytes())
+            .0
+            .into_owned(),
+    }
+}
+
+impl Client<WriteToken> {
+    /// A read-only client for the same token, sharing the transport,
+    /// cassette and rate limiter.
+    pub fn to_read(&self) -> Client {
+        Client {
+            token: ReadToken::from(self.token.clone()),
+            http: self.http.clone(),
+            base_url: self.base_url.clone(),
+            cassette: self.cassette.clone(),
+            #[cfg(feature = "rate-limit")]
+            rate_limiter: self.rate_limiter.clone(),
+        }
+    }
+
+    /// Submit a batch of payment orders through the import endpoint.
+    ///
+    /// Returns the XML response describing the outcome of each order.
+    pub async fn import_payment_orders(
+        &self,
+        document: impl Into<Vec<u8>>,
+        fmt: ImportFmt,
+    ) -> Result<String, FioError> {
+        info!("Importing payment orders as {}", fmt);
+        self.post_import(document.into(), fmt).await
+    }
+
+    /// Submit a batch as an XML import and parse Fio's per-order results.
+    ///
+    /// The batch is not validated here; call [`PaymentBatch::validate`] first.
+    pub async fn submit_payment_batch(
+        &self,
+        batch: &PaymentBatch,
+    ) -> Result<ImportResponse, FioError> {
+        let response = self
+            .import_payment_orders(batch.to_xml(), ImportFmt::Xml)
+            .await?;
+        ImportResponse::parse(&response)
+    }
+}
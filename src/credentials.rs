@@ -0,0 +1,435 @@
+//! Loading API tokens without keeping them in `.env` files.
+//!
+//! A [`TokenSource`] describes where a token lives: an environment variable,
+//! a file readable only by its owner, a systemd credential, or (with the
+//! `keystore` feature) an entry in a passphrase-encrypted [`Keystore`] file.
+//! Sources deserialize from configuration, e.g. `{ file = "/etc/fio/czk" }`.
+
+use crate::error::FioError;
+use crate::token::FioToken;
+use log::debug;
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+#[cfg(feature = "keystore")]
+pub use keystore::Keystore;
+
+/// Environment variable systemd sets to the directory holding service credentials.
+pub const SYSTEMD_CREDENTIALS_DIR: &str = "CREDENTIALS_DIRECTORY";
+
+/// Where to load a token from.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenSource {
+    /// Name of an environment variable holding the token.
+    Env(String),
+    /// File containing the token; must not be readable by group or others.
+    File(PathBuf),
+    /// Credential name passed with `LoadCredential=` in a systemd unit.
+    Systemd(String),
+    /// Entry in an encrypted keystore file.
+    #[cfg(feature = "keystore")]
+    Keystore {
+        path: PathBuf,
+        alias: String,
+        /// Environment variable holding the passphrase.
+        #[serde(default = "keystore::default_passphrase_env")]
+        passphrase_env: String,
+    },
+}
+
+impl TokenSource {
+    /// Read the token from this source.
+    pub fn load(&self) -> Result<FioToken, FioError> {
+        match self {
+            TokenSource::Env(name) => {
+                debug!("Loading token from environment variable {}", name);
+                let value = env::var(name)
+                    .map(Zeroizing::new)
+                    .map_err(|e| credential_error(format!("env {}", name), e))?;
+                FioToken::new(value.trim())
+            }
+            TokenSource::File(path) => load_file(path),
+            TokenSource::Systemd(name) => {
+                let dir = env::var_os(SYSTEMD_CREDENTIALS_DIR).ok_or_else(|| {
+                    credential_error(
+                        format!("systemd credential {}", name),
+                        format!("{} is not set", SYSTEMD_CREDENTIALS_DIR),
+                    )
+                })?;
+                load_file(&Path::new(&dir).join(name))
+            }
+            #[cfg(feature = "keystore")]
+            TokenSource::Keystore {
+                path,
+                alias,
+                passphrase_env,
+            } => {
+                let passphrase = env::var(passphrase_env)
+                    .map(Zeroizing::new)
+                    .map_err(|e| credential_error(format!("env {}", passphrase_env), e))?;
+                Keystore::open(path, &passphrase)?.get(alias)
+            }
+        }
+    }
+}
+
+fn credential_error(origin: impl Into<String>, reason: impl ToString) -> FioError {
+    FioError::Credentials {
+        origin: origin.into(),
+        reason: reason.to_string(),
+    }
+}
+
+fn load_file(path: &Path) -> Result<FioToken, FioError> {
+    debug!("Loading token from {}", path.display());
+    let origin = || format!("file {}", path.display());
+    let contents = read_private(path).map_err(|reason| credential_error(origin(), reason))?;
+    FioToken::new(contents.trim())
+}
+
+/// Read a file after checking that only its owner can access it.
+///
+/// The check runs on the opened handle, so the file cannot be swapped
+/// between checking and reading.
+fn read_private(path: &Path) -> Result<Zeroizing<String>, String> {
+    use std::io::Read;
+
+    let mut file = fs::File::open(path).map_err(|e| e.to_string())?;
+    check_permissions(&file)?;
+    let mut contents = Zeroizing::new(String::new());
+    file.read_to_string(&mut contents)
+        .map_err(|e| e.to_string())?;
+    Ok(contents)
+}
+
+#[cfg(unix)]
+fn check_permissions(file: &fs::File) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = file
+        .metadata()
+        .map_err(|e| e.to_string())?
+        .permissions()
+        .mode();
+    if mode & 0o077 != 0 {
+        return Err(format!(
+            "permissions {:o} allow access by group or others; use chmod 600",
+            mode & 0o777
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_permissions(_file: &fs::File) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(feature = "keystore")]
+mod keystore {
+    use super::credential_error;
+    use crate::error::FioError;
+    use crate::token::FioToken;
+    use argon2::Argon2;
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+    use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+    use log::debug;
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use zeroize::Zeroizing;
+
+    const VERSION: u32 = 1;
+    const SALT_LEN: usize = 16;
+    const NONCE_LEN: usize = 12;
+
+    pub(super) fn default_passphrase_env() -> String {
+        "FIOAPI_KEYSTORE_PASSPHRASE".to_string()
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct KeystoreFile {
+        version: u32,
+        salt: String,
+        entries: BTreeMap<String, Entry>,
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    struct Entry {
+        nonce: String,
+        ciphertext: String,
+    }
+
+    /// Passphrase-encrypted file mapping aliases to tokens.
+    ///
+    /// The key is derived from the passphrase with Argon2id and every entry
+    /// is sealed with ChaCha20-Poly1305, bound to its alias. The format is
+    /// plain JSON and does not depend on any OS keyring.
+    pub struct Keystore {
+        path: PathBuf,
+        salt: Vec<u8>,
+        key: Zeroizing<[u8; 32]>,
+        entries: BTreeMap<String, Entry>,
+    }
+
+    impl std::fmt::Debug for Keystore {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("Keystore")
+                .field("path", &self.path)
+                .field("aliases", &self.entries.keys().collect::<Vec<_>>())
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl Keystore {
+        /// Start a new, empty keystore; nothing is written until [`Keystore::save`].
+        pub fn create(path: impl Into<PathBuf>, passphrase: &str) -> Result<Self, FioError> {
+            let path = path.into();
+            let mut salt = vec![0u8; SALT_LEN];
+            getrandom::fill(&mut salt).map_err(|e| error(&path, e))?;
+            let key = derive_key(&path, passphrase, &salt)?;
+            Ok(Self {
+                path,
+                salt,
+                key,
+                entries: BTreeMap::new(),
+            })
+        }
+
+        /// Open an existing keystore.
+        ///
+        /// The passphrase is only verified when an entry is decrypted.
+        pub fn open(path: impl AsRef<Path>, passphrase: &str) -> Result<Self, FioError> {
+            let path = path.as_ref().to_path_buf();
+            let data = super::read_private(&path).map_err(|reason| error(&path, reason))?;
+            let file: KeystoreFile = serde_json::from_str(&data).map_err(|e| error(&path, e))?;
+            if file.version != VERSION {
+                return Err(error(
+                    &path,
+                    format!("unsupported keystore version {}", file.version),
+                ));
+            }
+            let salt = BASE64.decode(&file.salt).map_err(|e| error(&path, e))?;
+            let key = derive_key(&path, passphrase, &salt)?;
+            debug!(
+                "Opened keystore {} with {} entries",
+                path.display(),
+                file.entries.len()
+            );
+            Ok(Self {
+                path,
+                salt,
+                key,
+                entries: file.entries,
+            })
+        }
+
+        pub fn aliases(&self) -> impl Iterator<Item = &str> {
+            self.entries.keys().map(String::as_str)
+        }
+
+        /// Decrypt the token stored under `alias`.
+        pub fn get(&self, alias: &str) -> Result<FioToken, FioError> {
+            let entry = self
+                .entries
+                .get(alias)
+                .ok_or_else(|| error(&self.path, format!("no entry named {}", alias)))?;
+            let nonce = BASE64
+                .decode(&entry.nonce)
+                .map_err(|e| error(&self.path, e))?;
+            let ciphertext = BASE64
+                .decode(&entry.ciphertext)
+                .map_err(|e| error(&self.path, e))?;
+            if nonce.len() != NONCE_LEN {
+                return Err(error(&self.path, "corrupted entry nonce"));
+            }
+            let plaintext = self
+                .cipher()
+                .decrypt(
+                    Nonce::from_slice(&nonce),
+                    Payload {
+                        msg: &ciphertext,
+                        aad: alias.as_bytes(),
+                    },
+                )
+                .map(Zeroizing::new)
+                .map_err(|_| error(&self.path, "wrong passphrase or corrupted entry"))?;
+            let token = String::from_utf8(plaintext.to_vec())
+                .map(Zeroizing::new)
+                .map_err(|_| error(&self.path, "entry is not valid UTF-8"))?;
+            FioToken::new(token.as_str())
+        }
+
+        /// Encrypt and store `token` under `alias`, replacing any previous entry.
+        pub fn insert(
+            &mut self,
+            alias: impl Into<String>,
+            token: &FioToken,
+        ) -> Result<(), FioError> {
+            let alias = alias.into();
+            let mut nonce = [0u8; NONCE_LEN];
+            getrandom::fill(&mut nonce).map_err(|e| error(&self.path, e))?;
+            let ciphertext = self
+                .cipher()
+                .encrypt(
+                    Nonce::from_slice(&nonce),
+                    Payload {
+                        msg: token.expose_secret().as_bytes(),
+                        aad: alias.as_bytes(),
+                    },
+                )
+                .map_err(|_| error(&self.path, "encryption failed"))?;
+            self.entries.insert(
+                alias,
+                Entry {
+                    nonce: BASE64.encode(nonce),
+                    ciphertext: BASE64.encode(ciphertext),
+                },
+            );
+            Ok(())
+        }
+
+        pub fn remove(&mut self, alias: &str) -> bool {
+            self.entries.remove(alias).is_some()
+        }
+
+        /// Write the keystore to its path, readable by the owner only.
+        pub fn save(&self) -> Result<(), FioError> {
+            let file = KeystoreFile {
+                version: VERSION,
+                salt: BASE64.encode(&self.salt),
+                entries: self.entries.clone(),
+            };
+            let data = serde_json::to_string_pretty(&file).map_err(|e| error(&self.path, e))?;
+            write_private(&self.path, data.as_bytes()).map_err(|e| error(&self.path, e))
+        }
+
+        fn cipher(&self) -> ChaCha20Poly1305 {
+            ChaCha20Poly1305::new(Key::from_slice(self.key.as_slice()))
+        }
+    }
+
+    fn derive_key(
+        path: &Path,
+        passphrase: &str,
+        salt: &[u8],
+    ) -> Result<Zeroizing<[u8; 32]>, FioError> {
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, key.as_mut_slice())
+            .map_err(|e| error(path, e))?;
+        Ok(key)
+    }
+
+    #[cfg(unix)]
+    fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+        use std::io::Write;
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        // `mode` only applies when the file is created; tighten an existing
+        // file before the new contents are written.
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        file.write_all(data)?;
+        file.sync_all()
+    }
+
+    #[cfg(not(unix))]
+    fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+        fs::write(path, data)
+    }
+
+    fn error(path: &Path, reason: impl ToString) -> FioError {
+        credential_error(format!("keystore {}", path.display()), reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("fioapi-{}-{}", name, std::process::id()))
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_source_checks_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_path("token");
+        fs::write(&path, format!("{}\n", "f".repeat(64))).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        let source = TokenSource::File(path.clone());
+        assert!(matches!(source.load(), Err(FioError::Credentials { .. })));
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        let token = source.load().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(token.expose_secret(), "f".repeat(64));
+    }
+
+    #[cfg(all(unix, feature = "keystore"))]
+    #[test]
+    fn keystore_save_tightens_existing_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_path("keystore-perm.json");
+        fs::write(&path, "{}").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        Keystore::create(&path, "correct horse")
+            .unwrap()
+            .save()
+            .unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn sources_deserialize_from_config() {
+        let source: TokenSource = serde_json::from_str(r#"{"systemd": "fio-czk"}"#).unwrap();
+        assert_eq!(source, TokenSource::Systemd("fio-czk".into()));
+        let source: TokenSource = serde_json::from_str(r#"{"env": "FIO_CZK"}"#).unwrap();
+        assert!(matches!(source.load(), Err(FioError::Credentials { .. })));
+    }
+
+    #[cfg(feature = "keystore")]
+    #[test]
+    fn keystore_round_trip() {
+        let path = temp_path("keystore.json");
+        let token = FioToken::new("k".repeat(64)).unwrap();
+        let mut store = Keystore::create(&path, "correct horse").unwrap();
+        store.insert("czk", &token).unwrap();
+        store.save().unwrap();
+
+        let store = Keystore::open(&path, "correct horse").unwrap();
+        assert_eq!(store.aliases().collect::<Vec<_>>(), ["czk"]);
+        assert_eq!(
+            store.get("czk").unwrap().expose_secret(),
+            token.expose_secret()
+        );
+        assert!(
+            !fs::read_to_string(&path)
+                .unwrap()
+                .contains(token.expose_secret())
+        );
+
+        let wrong = Keystore::open(&path, "wrong").unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(wrong.get("czk").is_err());
+        assert!(store.get("eur").is_err());
+    }
+}
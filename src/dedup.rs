@@ -0,0 +1,352 @@
+//! Merging of transactions seen in several fetches.
+//!
+//! Period fetches, since-last fetches and statements overlap, so the same
+//! `transaction_id` is often ingested more than once. A [`Deduplicator`]
+//! keeps one canonical [`Transaction`] per id. Differences in editable fields
+//! (by default only `comment`, which users can change in internet banking)
+//! are taken over and recorded as [`FieldChange`]s. Differences anywhere
+//! else keep the first value and are reported as conflicts.
+//!
+//! Card payments may first be seen as provisional entries added with
+//! [`Deduplicator::add_pending`]. When the settled booking arrives, with the
+//! same id or as a new card payment of a similar amount shortly afterwards,
+//! it replaces the pending entry and a [`Settlement`] is recorded.
+
+use crate::models::{Transaction, TransactionKind};
+use chrono::Days;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// A field whose value differs between two sightings of a transaction.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub transaction_id: i64,
+    pub field: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+/// A pending card entry replaced by its settled booking.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Settlement {
+    pub pending_id: i64,
+    pub settled_id: i64,
+    pub pending_amount: Decimal,
+    pub settled_amount: Decimal,
+}
+
+/// What happened to one added transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeOutcome {
+    /// First sighting of this id.
+    New,
+    /// Identical to the stored transaction.
+    Duplicate,
+    /// Editable fields were updated.
+    Updated,
+    /// Other fields differ; the stored values were kept.
+    Conflict,
+    /// Replaced a pending entry.
+    Settled,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    txn: Transaction,
+    pending: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Deduplicator {
+    entries: BTreeMap<i64, Entry>,
+    editable: Vec<String>,
+    settlement_days: u64,
+    settlement_tolerance: Decimal,
+    edits: Vec<FieldChange>,
+    conflicts: Vec<FieldChange>,
+    settlements: Vec<Settlement>,
+}
+
+impl Default for Deduplicator {
+    fn default() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            editable: vec!["comment".to_string()],
+            settlement_days: 7,
+            settlement_tolerance: Decimal::new(5, 2),
+            edits: Vec::new(),
+            conflicts: Vec::new(),
+            settlements: Vec::new(),
+        }
+    }
+}
+
+impl Deduplicator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fields (by their serialized name) that may change between sightings.
+    pub fn with_editable_fields<S: Into<String>>(
+        mut self,
+        fields: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.editable = fields.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// How a settled card payment with a new id is paired with a pending
+    /// entry: booked at most `days` later and differing by at most the
+    /// fraction `tolerance` of the pending amount (defaults: 7 days, 5 %).
+    pub fn with_settlement_window(mut self, days: u64, tolerance: Decimal) -> Self {
+        self.settlement_days = days;
+        self.settlement_tolerance = tolerance;
+        self
+    }
+
+    /// Add a booked transaction.
+    pub fn add(&mut self, txn: Transaction) -> MergeOutcome {
+        if let Some(entry) = self.entries.get(&txn.transaction_id) {
+            if entry.pending {
+                self.settle(txn.transaction_id, txn);
+                return MergeOutcome::Settled;
+            }
+            return self.merge(txn);
+        }
+        if let Some(pending_id) = self.find_pending(&txn) {
+            self.settle(pending_id, txn);
+            return MergeOutcome::Settled;
+        }
+        self.entries.insert(
+            txn.transaction_id,
+            Entry {
+                txn,
+                pending: false,
+            },
+        );
+        MergeOutcome::New
+    }
+
+    /// Add a provisional entry that a later booking may replace.
+    pub fn add_pending(&mut self, txn: Transaction) -> MergeOutcome {
+        match self.entries.get(&txn.transaction_id) {
+            Some(entry) if !entry.pending => MergeOutcome::Duplicate,
+            Some(_) => self.merge(txn),
+            None => {
+                self.entries
+                    .insert(txn.transaction_id, Entry { txn, pending: true });
+                MergeOutcome::New
+            }
+        }
+    }
+
+    pub fn extend(&mut self, txns: impl IntoIterator<Item = Transaction>) {
+        for txn in txns {
+            self.add(txn);
+        }
+    }
+
+    /// Canonical transactions, pending ones included, ordered by id.
+    pub fn transactions(&self) -> impl Iterator<Item = &Transaction> {
+        self.entries.values().map(|e| &e.txn)
+    }
+
+    /// Pending entries that have not been settled yet.
+    pub fn pending(&self) -> impl Iterator<Item = &Transaction> {
+        self.entries.values().filter(|e| e.pending).map(|e| &e.txn)
+    }
+
+    pub fn into_transactions(self) -> Vec<Transaction> {
+        self.entries.into_values().map(|e| e.txn).collect()
+    }
+
+    /// Accepted changes of editable fields, in the order they were seen.
+    pub fn edits(&self) -> &[FieldChange] {
+        &self.edits
+    }
+
+    /// Differences in other fields; `before` is the value that was kept.
+    pub fn conflicts(&self) -> &[FieldChange] {
+        &self.conflicts
+    }
+
+    pub fn settlements(&self) -> &[Settlement] {
+        &self.settlements
+    }
+
+    fn merge(&mut self, txn: Transaction) -> MergeOutcome {
+        let id = txn.transaction_id;
+        let entry = self.entries.get_mut(&id).expect("merge of a known id");
+        let (Value::Object(mut stored), Value::Object(incoming)) =
+            (to_value(&entry.txn), to_value(&txn))
+        else {
+            unreachable!("transactions serialize to objects");
+        };
+        let mut outcome = MergeOutcome::Duplicate;
+        for (field, after) in incoming {
+            let before = stored.get(&field).cloned().unwrap_or(Value::Null);
+            if before == after || same_number(&before, &after) {
+                continue;
+            }
+            let change = FieldChange {
+                transaction_id: id,
+                field: field.clone(),
+                before,
+                after: after.clone(),
+            };
+            if self.editable.contains(&field) {
+                stored.insert(field, after);
+                self.edits.push(change);
+                if outcome == MergeOutcome::Duplicate {
+                    outcome = MergeOutcome::Updated;
+                }
+            } else {
+                self.conflicts.push(change);
+                outcome = MergeOutcome::Conflict;
+            }
+        }
+        if outcome == MergeOutcome::Updated {
+            entry.txn = serde_json::from_value(Value::Object(stored))
+                .expect("edited transaction deserializes");
+        }
+        outcome
+    }
+
+    fn find_pending(&self, txn: &Transaction) -> Option<i64> {
+        if txn.kind() != TransactionKind::CardPayment {
+            return None;
+        }
+        self.entries
+            .values()
+            .filter(|e| e.pending && e.txn.kind() == TransactionKind::CardPayment)
+            .map(|e| &e.txn)
+            .find(|p| {
+                p.currency == txn.currency
+                    && p.date <= txn.date
+                    && txn.date <= p.date + Days::new(self.settlement_days)
+                    && (txn.amount - p.amount).abs() <= p.amount.abs() * self.settlement_tolerance
+                    && same_if_known(&p.user_identification, &txn.user_identification)
+            })
+            .map(|p| p.transaction_id)
+    }
+
+    fn settle(&mut self, pending_id: i64, txn: Transaction) {
+        let pending = self
+            .entries
+            .remove(&pending_id)
+            .expect("settled entry exists");
+        self.settlements.push(Settlement {
+            pending_id,
+            settled_id: txn.transaction_id,
+            pending_amount: pending.txn.amount,
+            settled_amount: txn.amount,
+        });
+        self.entries.insert(
+            txn.transaction_id,
+            Entry {
+                txn,
+                pending: false,
+            },
+        );
+    }
+}
+
+fn to_value(txn: &Transaction) -> Value {
+    serde_json::to_value(txn).expect("transaction serializes")
+}
+
+/// `"100"` and `"100.00"` are the same amount.
+fn same_number(a: &Value, b: &Value) -> bool {
+    match (a.as_str(), b.as_str()) {
+        (Some(a), Some(b)) => matches!(
+            (a.parse::<Decimal>(), b.parse::<Decimal>()),
+            (Ok(a), Ok(b)) if a == b
+        ),
+        _ => false,
+    }
+}
+
+fn same_if_known(a: &Option<String>, b: &Option<String>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a == b,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::txn;
+    use serde_json::json;
+
+    #[test]
+    fn merges_sightings_and_reports_changes() {
+        let mut dedup = Deduplicator::new();
+        let original = txn(1, "2025-05-02", "100", json!({"comment": null, "vs": 7}));
+        let reformatted = txn(1, "2025-05-02", "100.00", json!({"comment": null, "vs": 7}));
+        assert_eq!(dedup.add(original.clone()), MergeOutcome::New);
+        assert_eq!(dedup.add(original), MergeOutcome::Duplicate);
+        assert_eq!(dedup.add(reformatted), MergeOutcome::Duplicate);
+
+        let edited = txn(1, "2025-05-02", "100", json!({"comment": "rent", "vs": 7}));
+        assert_eq!(dedup.add(edited), MergeOutcome::Updated);
+        let tampered = txn(1, "2025-05-02", "110", json!({"comment": "rent", "vs": 7}));
+        assert_eq!(dedup.add(tampered), MergeOutcome::Conflict);
+
+        let merged: Vec<_> = dedup.transactions().collect();
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].comment.as_deref(), Some("rent"));
+        assert_eq!(merged[0].amount, Decimal::from(100));
+        assert_eq!(dedup.edits()[0].field, "comment");
+        assert_eq!(dedup.conflicts()[0].field, "amount");
+        assert_eq!(dedup.conflicts()[0].after, json!("110"));
+    }
+
+    #[test]
+    fn settles_pending_card_payments() {
+        let card = |id, date, amount| {
+            txn(
+                id,
+                date,
+                amount,
+                json!({"transaction_type": "Platba kartou", "user_identification": "Nákup: SHOP"}),
+            )
+        };
+        let mut dedup = Deduplicator::new();
+        dedup.add_pending(card(10, "2025-05-01", "-250.00"));
+        dedup.add_pending(card(11, "2025-05-01", "-80.00"));
+        dedup.add_pending(card(12, "2025-05-01", "-40.00"));
+
+        // Settled under a new id with an FX difference.
+        assert_eq!(
+            dedup.add(card(20, "2025-05-03", "-252.10")),
+            MergeOutcome::Settled
+        );
+        // Settled under the same id.
+        assert_eq!(
+            dedup.add(card(11, "2025-05-02", "-80.00")),
+            MergeOutcome::Settled
+        );
+        // Too far off in amount to be the same payment.
+        assert_eq!(
+            dedup.add(card(21, "2025-05-03", "-60.00")),
+            MergeOutcome::New
+        );
+
+        let ids: Vec<_> = dedup.transactions().map(|t| t.transaction_id).collect();
+        assert_eq!(ids, [11, 12, 20, 21]);
+        let pending: Vec<_> = dedup.pending().map(|t| t.transaction_id).collect();
+        assert_eq!(pending, [12]);
+        assert_eq!(
+            dedup.settlements()[0],
+            Settlement {
+                pending_id: 10,
+                settled_id: 20,
+                pending_amount: Decimal::new(-25000, 2),
+                settled_amount: Decimal::new(-25210, 2),
+            }
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use crate::transport::TransportError;
+use http::StatusCode;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -15,16 +17,45 @@ pub enum FioError {
     InvalidParameter(&'static str),
 
     #[error("http error: {0}")]
-    Http(#[from] reqwest::Error),
+    Http(#[from] TransportError),
 
     #[error("unexpected http status: {0}")]
-    Status(reqwest::StatusCode),
+    Status(StatusCode),
 
     #[error("invalid or unexpected response format")]
     InvalidResponse,
 
+    /// Reason a row was set aside by lenient parsing.
+    #[error("malformed transaction: {0}")]
+    MalformedTransaction(String),
+
     #[error("api rejected request: {0}")]
     Api(#[from] ApiError),
+
+    #[error("cannot load token from {origin}: {reason}")]
+    Credentials { origin: String, reason: String },
+
+    #[error("cassette error: {0}")]
+    Cassette(String),
+
+    #[error("replayed request does not match cassette: expected {expected}, got {actual}")]
+    ReplayMismatch { expected: String, actual: String },
+
+    #[error("invalid query: {0}")]
+    InvalidQuery(String),
+
+    #[error("invalid categorization rules: {0}")]
+    InvalidRules(String),
+
+    #[error("invalid exchange rate data: {0}")]
+    InvalidRates(String),
+
+    #[error("no exchange rate {from}/{to} for {date}")]
+    MissingRate {
+        from: String,
+        to: String,
+        date: chrono::NaiveDate,
+    },
 }
 
 #[derive(Debug, Error)]
@@ -45,5 +76,5 @@ pub enum ApiError {
     InvalidToken,
 
     #[error("unexpected status {0}")]
-    UnexpectedStatus(reqwest::StatusCode),
+    UnexpectedStatus(StatusCode),
 }
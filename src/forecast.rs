@@ -0,0 +1,409 @@
+//! Recurring payment detection and balance forecasting.
+//!
+//! [`detect_recurring`] groups historical transactions by counterparty,
+//! variable symbol, currency and direction. A group becomes a
+//! [`RecurringSeries`] when enough of its payments have a similar amount and
+//! follow each other at a regular [`Cadence`]. [`forecast`] then projects
+//! those series forward from a known balance, typically
+//! [`AccountInfo::closing_balance`], into a daily balance curve.
+//!
+//! [`AccountInfo::closing_balance`]: crate::models::AccountInfo::closing_balance
+
+use crate::models::Transaction;
+use chrono::{Days, Months, NaiveDate};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Interval between payments of a series.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Cadence {
+    Weekly,
+    Biweekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl Cadence {
+    /// Cadence whose usual gap, allowing for weekends and short months,
+    /// contains `days`.
+    fn from_gap(days: i64) -> Option<Self> {
+        match days {
+            5..=9 => Some(Cadence::Weekly),
+            12..=16 => Some(Cadence::Biweekly),
+            26..=35 => Some(Cadence::Monthly),
+            80..=100 => Some(Cadence::Quarterly),
+            350..=380 => Some(Cadence::Yearly),
+            _ => None,
+        }
+    }
+
+    /// The `n`-th date after `anchor`; months keep the anchor's day where possible.
+    pub fn step(self, anchor: NaiveDate, n: u32) -> NaiveDate {
+        match self {
+            Cadence::Weekly => anchor + Days::new(7 * u64::from(n)),
+            Cadence::Biweekly => anchor + Days::new(14 * u64::from(n)),
+            Cadence::Monthly => anchor + Months::new(n),
+            Cadence::Quarterly => anchor + Months::new(3 * n),
+            Cadence::Yearly => anchor + Months::new(12 * n),
+        }
+    }
+}
+
+impl fmt::Display for Cadence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Cadence::Weekly => "weekly",
+            Cadence::Biweekly => "biweekly",
+            Cadence::Monthly => "monthly",
+            Cadence::Quarterly => "quarterly",
+            Cadence::Yearly => "yearly",
+        })
+    }
+}
+
+/// Thresholds for [`detect_recurring`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DetectionRules {
+    /// Payments needed before a group counts as recurring.
+    pub min_occurrences: usize,
+    /// Allowed deviation from the median amount as a fraction, e.g. `0.1`.
+    pub amount_tolerance: Decimal,
+}
+
+impl Default for DetectionRules {
+    fn default() -> Self {
+        Self {
+            min_occurrences: 3,
+            amount_tolerance: Decimal::new(1, 1),
+        }
+    }
+}
+
+/// Payments to or from one counterparty that repeat at a regular interval.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RecurringSeries {
+    /// Counterparty account, name or identification.
+    pub counterparty: String,
+    pub vs: Option<i64>,
+    pub currency: String,
+    pub cadence: Cadence,
+    /// Amount of the latest payment, negative for debits.
+    pub amount: Decimal,
+    pub last_date: NaiveDate,
+    /// Payments that make up the series, oldest first.
+    pub transaction_ids: Vec<i64>,
+}
+
+impl RecurringSeries {
+    /// Expected payment dates in `(after, until]`.
+    ///
+    /// A series whose next payment is more than one interval overdue at
+    /// `after` is considered ended and yields nothing.
+    pub fn dates_between(&self, after: NaiveDate, until: NaiveDate) -> Vec<NaiveDate> {
+        if self.cadence.step(self.last_date, 2) <= after {
+            return Vec::new();
+        }
+        (1..)
+            .map(|n| self.cadence.step(self.last_date, n))
+            .skip_while(|&date| date <= after)
+            .take_while(|&date| date <= until)
+            .collect()
+    }
+}
+
+/// Find recurring series in `transactions`, ordered by counterparty.
+pub fn detect_recurring(
+    transactions: &[Transaction],
+    rules: &DetectionRules,
+) -> Vec<RecurringSeries> {
+    let mut groups: BTreeMap<(String, Option<i64>, String, bool), Vec<&Transaction>> =
+        BTreeMap::new();
+    for txn in transactions {
+        if let Some(counterparty) = counterparty(txn) {
+            let key = (
+                counterparty,
+                txn.vs,
+                txn.currency.to_uppercase(),
+                txn.amount.is_sign_negative(),
+            );
+            groups.entry(key).or_default().push(txn);
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter_map(|((counterparty, vs, currency, _), mut txns)| {
+            let median = median(txns.iter().map(|t| t.amount).collect());
+            let tolerance = median.abs() * rules.amount_tolerance;
+            txns.retain(|t| (t.amount - median).abs() <= tolerance);
+            if txns.len() < rules.min_occurrences.max(2) {
+                return None;
+            }
+            txns.sort_by_key(|t| (t.date, t.transaction_id));
+            let mut cadences = txns
+                .windows(2)
+                .map(|pair| Cadence::from_gap((pair[1].date - pair[0].date).num_days()));
+            let cadence = cadences.next()??;
+            if !cadences.all(|c| c == Some(cadence)) {
+                return None;
+            }
+            let last = txns[txns.len() - 1];
+            Some(RecurringSeries {
+                counterparty,
+                vs,
+                currency,
+                cadence,
+                amount: last.amount,
+                last_date: last.date,
+                transaction_ids: txns.iter().map(|t| t.transaction_id).collect(),
+            })
+        })
+        .collect()
+}
+
+fn counterparty(txn: &Transaction) -> Option<String> {
+    match (&txn.account_id, &txn.bank_id) {
+        (Some(account), Some(bank)) => Some(format!("{account}/{bank}")),
+        (Some(account), None) => Some(account.clone()),
+        _ => txn
+            .account_name
+            .clone()
+            .or_else(|| txn.user_identification.clone()),
+    }
+}
+
+fn median(mut amounts: Vec<Decimal>) -> Decimal {
+    amounts.sort();
+    let mid = amounts.len() / 2;
+    if amounts.len().is_multiple_of(2) {
+        (amounts[mid - 1] + amounts[mid]) / Decimal::TWO
+    } else {
+        amounts[mid]
+    }
+}
+
+/// One expected payment of a series.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProjectedPayment {
+    pub date: NaiveDate,
+    pub amount: Decimal,
+    /// Index into the series passed to [`forecast`].
+    pub series: usize,
+}
+
+/// Expected balance at the end of one day.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ForecastPoint {
+    pub date: NaiveDate,
+    pub inflow: Decimal,
+    /// Positive sum of the day's debits.
+    pub outflow: Decimal,
+    pub balance: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Forecast {
+    pub start: NaiveDate,
+    pub opening_balance: Decimal,
+    /// Projected payments by date.
+    pub payments: Vec<ProjectedPayment>,
+    /// One point per day after `start`.
+    pub points: Vec<ForecastPoint>,
+}
+
+impl Forecast {
+    /// The first day with the lowest projected balance.
+    pub fn lowest(&self) -> Option<&ForecastPoint> {
+        self.points
+            .iter()
+            .reduce(|low, p| if p.balance < low.balance { p } else { low })
+    }
+
+    pub fn closing_balance(&self) -> Decimal {
+        self.points
+            .last()
+            .map_or(self.opening_balance, |p| p.balance)
+    }
+}
+
+/// Project `series` over the `days` following `start`, beginning with
+/// `balance` at the end of `start`.
+pub fn forecast(
+    series: &[RecurringSeries],
+    start: NaiveDate,
+    balance: Decimal,
+    days: u64,
+) -> Forecast {
+    let end = start + Days::new(days);
+    let mut payments: Vec<ProjectedPayment> = series
+        .iter()
+        .enumerate()
+        .flat_map(|(i, s)| {
+            s.dates_between(start, end)
+                .into_iter()
+                .map(move |date| ProjectedPayment {
+                    date,
+                    amount: s.amount,
+                    series: i,
+                })
+        })
+        .collect();
+    payments.sort_by_key(|p| (p.date, p.series));
+
+    let mut running = balance;
+    let mut due = payments.iter().peekable();
+    let points = (1..=days)
+        .map(|offset| {
+            let date = start + Days::new(offset);
+            let (mut inflow, mut outflow) = (Decimal::ZERO, Decimal::ZERO);
+            while let Some(payment) = due.next_if(|p| p.date == date) {
+                if payment.amount.is_sign_negative() {
+                    outflow -= payment.amount;
+                } else {
+                    inflow += payment.amount;
+                }
+            }
+            running += inflow - outflow;
+            ForecastPoint {
+                date,
+                inflow,
+                outflow,
+                balance: running,
+            }
+        })
+        .collect();
+    Forecast {
+        start,
+        opening_balance: balance,
+        payments,
+        points,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::txn;
+    use serde_json::json;
+
+    fn payment(id: i64, date: &str, amount: &str, account: &str, vs: Option<i64>) -> Transaction {
+        txn(
+            id,
+            date,
+            amount,
+            json!({"account_id": account, "bank_id": "0800", "vs": vs}),
+        )
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn detects_series_and_projects_balance() {
+        let history = [
+            // Rent, paid around the 1st; one extra payment to the landlord.
+            payment(1, "2025-01-31", "-15000", "111", Some(7)),
+            payment(2, "2025-03-03", "-15000", "111", Some(7)),
+            payment(3, "2025-03-31", "-15000", "111", Some(7)),
+            payment(4, "2025-03-15", "-2400", "111", Some(7)),
+            // Weekly pocket money with a slightly changing amount.
+            payment(5, "2025-03-14", "-500", "222", None),
+            payment(6, "2025-03-21", "-520", "222", None),
+            payment(7, "2025-03-28", "-520", "222", None),
+            // Salary, but only twice.
+            payment(8, "2025-02-15", "40000", "333", None),
+            payment(9, "2025-03-15", "40000", "333", None),
+            // Irregular payments to the same account.
+            payment(10, "2025-01-02", "-300", "444", None),
+            payment(11, "2025-01-20", "-300", "444", None),
+            payment(12, "2025-03-25", "-300", "444", None),
+        ];
+        let series = detect_recurring(&history, &DetectionRules::default());
+        let found: Vec<_> = series
+            .iter()
+            .map(|s| {
+                (
+                    s.counterparty.as_str(),
+                    s.cadence,
+                    s.transaction_ids.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("111/0800", Cadence::Monthly, vec![1, 2, 3]),
+                ("222/0800", Cadence::Weekly, vec![5, 6, 7]),
+            ]
+        );
+        assert_eq!(
+            series[0].dates_between(date("2025-03-31"), date("2025-05-31")),
+            [date("2025-04-30"), date("2025-05-31")]
+        );
+        assert!(
+            series[1]
+                .dates_between(date("2025-04-20"), date("2025-05-31"))
+                .is_empty()
+        );
+
+        let result = forecast(&series, date("2025-03-31"), Decimal::from(20000), 30);
+        assert_eq!(result.points.len(), 30);
+        // Four weekly payments of 520 and the rent on April 30.
+        assert_eq!(result.payments.len(), 5);
+        assert_eq!(
+            result.closing_balance(),
+            Decimal::from(20000 - 4 * 520 - 15000)
+        );
+        let lowest = result.lowest().unwrap();
+        assert_eq!(lowest.date, date("2025-04-30"));
+        assert_eq!(lowest.outflow, Decimal::from(15000));
+    }
+
+    #[test]
+    fn edge_cases() {
+        assert!(detect_recurring(&[], &DetectionRules::default()).is_empty());
+        let empty = forecast(&[], date("2025-03-31"), Decimal::from(100), 0);
+        assert!(empty.points.is_empty());
+        assert_eq!(empty.lowest(), None);
+        assert_eq!(empty.closing_balance(), Decimal::from(100));
+
+        // The same counterparty in two currencies forms two series.
+        let history = [
+            payment(1, "2025-01-10", "-10", "111", None),
+            payment(2, "2025-02-10", "-10", "111", None),
+            txn(
+                3,
+                "2025-01-20",
+                "-5",
+                json!({"account_id": "111", "bank_id": "0800", "currency": "EUR"}),
+            ),
+            txn(
+                4,
+                "2025-02-20",
+                "-5",
+                json!({"account_id": "111", "bank_id": "0800", "currency": "EUR"}),
+            ),
+        ];
+        assert!(detect_recurring(&history, &DetectionRules::default()).is_empty());
+        let rules = DetectionRules {
+            min_occurrences: 2,
+            ..DetectionRules::default()
+        };
+        let series = detect_recurring(&history, &rules);
+        let currencies: Vec<_> = series.iter().map(|s| s.currency.as_str()).collect();
+        assert_eq!(currencies, ["CZK", "EUR"]);
+
+        // Equal lowest balances: the first day is reported.
+        let daily = forecast(&series, date("2025-02-28"), Decimal::from(50), 31);
+        let lowest = daily.lowest().unwrap();
+        assert_eq!(lowest.date, date("2025-03-20"));
+        assert_eq!(lowest.balance, Decimal::from(35));
+        assert_eq!(daily.closing_balance(), Decimal::from(35));
+    }
+}
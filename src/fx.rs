@@ -0,0 +1,423 @@
+//! Currency conversion for multi-currency accounts.
+//!
+//! A [`RateProvider`] supplies exchange rates for a date. [`CnbRates`] reads
+//! the rate files published by the Czech National Bank (ČNB), so conversions
+//! work offline from files downloaded once. Both the daily file
+//! (`denni_kurz.txt`) and the yearly one (`rok.txt`) are understood:
+//!
+//! ```text
+//! 17.10.2025 #201
+//! země|měna|množství|kód|kurz
+//! EMU|euro|1|EUR|24,360
+//! Japonsko|jen|100|JPY|13,866
+//! ```
+//!
+//! ČNB publishes on working days only; a date without its own rates uses the
+//! latest earlier ones, as the rates stay valid until the next publication.
+//! Rates older than [`DEFAULT_MAX_RATE_AGE`] count as missing, so a stale or
+//! incomplete rate file is not silently used for later dates.
+//! Converted amounts are rounded to two decimal places.
+
+use crate::error::FioError;
+use crate::models::{AccountInfo, OriginalAmount, Transaction};
+use chrono::{NaiveDate, TimeDelta};
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// Source of exchange rates.
+pub trait RateProvider {
+    /// Units of `to` for one unit of `from` on `date`.
+    fn rate(&self, from: &str, to: &str, date: NaiveDate) -> Result<Decimal, FioError>;
+}
+
+impl<P: RateProvider + ?Sized> RateProvider for &P {
+    fn rate(&self, from: &str, to: &str, date: NaiveDate) -> Result<Decimal, FioError> {
+        (**self).rate(from, to, date)
+    }
+}
+
+/// How far back [`CnbRates`] looks for the latest publication by default.
+/// Covers weekends and the longest runs of Czech public holidays.
+pub const DEFAULT_MAX_RATE_AGE: TimeDelta = TimeDelta::days(7);
+
+/// ČNB rates: CZK per unit of each currency, by publication date.
+#[derive(Debug, Clone)]
+pub struct CnbRates {
+    days: BTreeMap<NaiveDate, HashMap<String, Decimal>>,
+    max_age: TimeDelta,
+}
+
+impl Default for CnbRates {
+    fn default() -> Self {
+        Self {
+            days: BTreeMap::new(),
+            max_age: DEFAULT_MAX_RATE_AGE,
+        }
+    }
+}
+
+impl CnbRates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept rates published up to `max_age` before the requested date.
+    pub fn with_max_age(mut self, max_age: TimeDelta) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Read one daily or yearly rate file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, FioError> {
+        let mut rates = Self::new();
+        rates.add_file(path)?;
+        Ok(rates)
+    }
+
+    pub fn add_file(&mut self, path: impl AsRef<Path>) -> Result<(), FioError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| FioError::InvalidRates(format!("{}: {e}", path.display())))?;
+        self.add(&text)
+    }
+
+    /// Add the rates of a daily or yearly file; later additions win.
+    pub fn add(&mut self, text: &str) -> Result<(), FioError> {
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+        let first = lines
+            .next()
+            .ok_or_else(|| FioError::InvalidRates("empty file".into()))?;
+        if first.starts_with("Datum") {
+            self.add_yearly(first, lines)
+        } else {
+            self.add_daily(first, lines)
+        }
+    }
+
+    /// Dates with published rates, oldest first.
+    pub fn dates(&self) -> impl Iterator<Item = NaiveDate> + '_ {
+        self.days.keys().copied()
+    }
+
+    fn add_daily<'a>(
+        &mut self,
+        first: &str,
+        lines: impl Iterator<Item = &'a str>,
+    ) -> Result<(), FioError> {
+        let date = parse_date(first.split_whitespace().next().unwrap_or_default())?;
+        let mut day = HashMap::new();
+        for line in lines.skip(1) {
+            let fields: Vec<&str> = line.split('|').collect();
+            let [_, _, amount, code, rate] = fields[..] else {
+                return Err(FioError::InvalidRates(format!("unexpected line `{line}`")));
+            };
+            day.insert(code.to_string(), per_unit(rate, amount)?);
+        }
+        self.days.insert(date, day);
+        Ok(())
+    }
+
+    fn add_yearly<'a>(
+        &mut self,
+        header: &str,
+        lines: impl Iterator<Item = &'a str>,
+    ) -> Result<(), FioError> {
+        let mut columns = yearly_columns(header)?;
+        for line in lines {
+            // The currency set may change during the year; a new header follows.
+            if line.starts_with("Datum") {
+                columns = yearly_columns(line)?;
+                continue;
+            }
+            let mut fields = line.split('|');
+            let date = parse_date(fields.next().unwrap_or_default())?;
+            let day = self.days.entry(date).or_default();
+            for ((code, amount), rate) in columns.iter().zip(fields) {
+                day.insert(code.clone(), per_unit(rate, amount)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// CZK per unit of `currency` valid on `date`.
+    fn czk(&self, currency: &str, date: NaiveDate) -> Option<Decimal> {
+        if currency.eq_ignore_ascii_case("CZK") {
+            return Some(Decimal::ONE);
+        }
+        let (published, day) = self.days.range(..=date).next_back()?;
+        if date - *published > self.max_age {
+            return None;
+        }
+        day.get(&currency.to_ascii_uppercase()).copied()
+    }
+}
+
+impl RateProvider for CnbRates {
+    fn rate(&self, from: &str, to: &str, date: NaiveDate) -> Result<Decimal, FioError> {
+        if from.eq_ignore_ascii_case(to) {
+            return Ok(Decimal::ONE);
+        }
+        match (self.czk(from, date), self.czk(to, date)) {
+            (Some(from), Some(to)) => Ok(from / to),
+            _ => Err(FioError::MissingRate {
+                from: from.to_string(),
+                to: to.to_string(),
+                date,
+            }),
+        }
+    }
+}
+
+fn parse_date(raw: &str) -> Result<NaiveDate, FioError> {
+    NaiveDate::parse_from_str(raw, "%d.%m.%Y")
+        .map_err(|_| FioError::InvalidRates(format!("invalid date `{raw}`")))
+}
+
+fn per_unit(rate: &str, amount: &str) -> Result<Decimal, FioError> {
+    let invalid = || FioError::InvalidRates(format!("invalid rate `{rate}` per `{amount}`"));
+    let rate: Decimal = rate.replace(',', ".").parse().map_err(|_| invalid())?;
+    let amount: Decimal = amount.parse().map_err(|_| invalid())?;
+    if amount.is_zero() {
+        return Err(invalid());
+    }
+    Ok(rate / amount)
+}
+
+/// `Datum|1 AUD|100 JPY|...` as `(code, amount)` pairs.
+fn yearly_columns(header: &str) -> Result<Vec<(String, String)>, FioError> {
+    header
+        .split('|')
+        .skip(1)
+        .map(|column| match column.split_once(' ') {
+            Some((amount, code)) => Ok((code.to_string(), amount.to_string())),
+            None => Err(FioError::InvalidRates(format!(
+                "unexpected column `{column}`"
+            ))),
+        })
+        .collect()
+}
+
+/// Convert `amount` from `from` to `to` at the rate of `date`.
+pub fn convert(
+    provider: &impl RateProvider,
+    amount: Decimal,
+    from: &str,
+    to: &str,
+    date: NaiveDate,
+) -> Result<Decimal, FioError> {
+    let rate = provider.rate(from, to, date)?;
+    Ok((amount * rate).round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero))
+}
+
+/// Copies of `transactions` with amounts in `currency`, each converted on its
+/// booking date.
+///
+/// `original_amount` is cleared, as it describes the payment relative to the
+/// booking currency that no longer applies.
+pub fn convert_transactions(
+    provider: &impl RateProvider,
+    transactions: &[Transaction],
+    currency: &str,
+) -> Result<Vec<Transaction>, FioError> {
+    transactions
+        .iter()
+        .map(|txn| {
+            let amount = convert(provider, txn.amount, &txn.currency, currency, txn.date)?;
+            Ok(Transaction {
+                amount,
+                currency: currency.to_string(),
+                original_amount: None,
+                ..txn.clone()
+            })
+        })
+        .collect()
+}
+
+/// Copy of `info` with the opening balance converted on `date_start` and the
+/// closing one on `date_end`.
+pub fn convert_balances(
+    provider: &impl RateProvider,
+    info: &AccountInfo,
+    currency: &str,
+) -> Result<AccountInfo, FioError> {
+    let from = info
+        .currency
+        .as_deref()
+        .ok_or(FioError::InvalidParameter("account currency unknown"))?;
+    let at = |balance: Option<Decimal>, date: Option<NaiveDate>| match (balance, date) {
+        (Some(balance), Some(date)) => convert(provider, balance, from, currency, date).map(Some),
+        (Some(_), None) => Err(FioError::InvalidParameter("balance date unknown")),
+        (None, _) => Ok(None),
+    };
+    Ok(AccountInfo {
+        opening_balance: at(info.opening_balance, info.date_start)?,
+        closing_balance: at(info.closing_balance, info.date_end)?,
+        currency: Some(currency.to_string()),
+        ..info.clone()
+    })
+}
+
+/// What a foreign card payment cost compared with the reference rate.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FxBreakdown {
+    pub original: OriginalAmount,
+    /// Booked amount without sign.
+    pub booked: Decimal,
+    /// Booked units per original unit, see [`Transaction::effective_rate`].
+    pub effective_rate: Decimal,
+    pub reference_rate: Decimal,
+    /// The original amount converted at the reference rate.
+    pub reference_amount: Decimal,
+    /// `booked - reference_amount`; positive when the payment cost more.
+    pub fee: Decimal,
+}
+
+/// Compare a foreign payment with the provider's rate on its booking date.
+///
+/// Returns `None` for transactions without an [`OriginalAmount`].
+pub fn fx_breakdown(
+    provider: &impl RateProvider,
+    txn: &Transaction,
+) -> Result<Option<FxBreakdown>, FioError> {
+    let (Some(original), Some(effective_rate)) =
+        (txn.original_amount.clone(), txn.effective_rate())
+    else {
+        return Ok(None);
+    };
+    let reference_rate = provider.rate(&original.currency, &txn.currency, txn.date)?;
+    let reference_amount = convert(
+        provider,
+        original.amount.abs(),
+        &original.currency,
+        &txn.currency,
+        txn.date,
+    )?;
+    let booked = txn.amount.abs();
+    Ok(Some(FxBreakdown {
+        original,
+        booked,
+        effective_rate,
+        reference_rate,
+        reference_amount,
+        fee: booked - reference_amount,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::txn;
+    use serde_json::json;
+
+    const DAILY: &str = "\
+17.10.2025 #201
+země|měna|množství|kód|kurz
+EMU|euro|1|EUR|24,360
+Japonsko|jen|100|JPY|13,866
+USA|dolar|1|USD|20,875
+";
+
+    const YEARLY: &str = "\
+Datum|1 EUR|1 USD
+15.10.2025|24,400|20,900
+16.10.2025|24,380|20,850
+";
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn converts_with_daily_and_yearly_rates() {
+        let mut rates = CnbRates::new();
+        rates.add(YEARLY).unwrap();
+        rates.add(DAILY).unwrap();
+        assert_eq!(rates.dates().count(), 3);
+
+        let friday = date("2025-10-17");
+        assert_eq!(
+            rates.rate("JPY", "CZK", friday).unwrap(),
+            Decimal::new(13866, 5)
+        );
+        // Saturday uses Friday's rates.
+        assert_eq!(
+            convert(&rates, Decimal::from(100), "EUR", "USD", date("2025-10-18")).unwrap(),
+            Decimal::new(11669, 2)
+        );
+        assert!(matches!(
+            rates.rate("EUR", "CZK", date("2025-10-14")),
+            Err(FioError::MissingRate { .. })
+        ));
+        assert!(matches!(
+            rates.rate("GBP", "CZK", friday),
+            Err(FioError::MissingRate { .. })
+        ));
+        // A week without publications is too long a gap.
+        assert!(matches!(
+            rates.rate("EUR", "CZK", date("2025-10-25")),
+            Err(FioError::MissingRate { .. })
+        ));
+        let lenient = rates.clone().with_max_age(TimeDelta::days(30));
+        assert!(lenient.rate("EUR", "CZK", date("2025-10-25")).is_ok());
+
+        let transactions = [
+            txn(
+                1,
+                "2025-10-15",
+                "-10.00",
+                json!({"currency": "EUR", "original_amount": "10.00 EUR"}),
+            ),
+            txn(2, "2025-10-16", "250", json!({})),
+        ];
+        let converted = convert_transactions(&rates, &transactions, "CZK").unwrap();
+        let amounts: Vec<_> = converted.iter().map(|t| t.amount).collect();
+        assert_eq!(amounts, [Decimal::new(-24400, 2), Decimal::from(250)]);
+        assert!(converted.iter().all(|t| t.currency == "CZK"));
+        assert!(transactions[0].original_amount.is_some());
+        assert!(converted[0].original_amount.is_none());
+
+        let info: AccountInfo = serde_json::from_value(json!({
+            "currency": "USD",
+            "openingBalance": 100,
+            "closingBalance": 200,
+            "dateStart": "2025-10-16",
+            "dateEnd": "2025-10-17",
+        }))
+        .unwrap();
+        let info = convert_balances(&rates, &info, "CZK").unwrap();
+        assert_eq!(info.opening_balance, Some(Decimal::new(208500, 2)));
+        assert_eq!(info.closing_balance, Some(Decimal::new(417500, 2)));
+    }
+
+    #[test]
+    fn breaks_down_foreign_card_payment() {
+        let rates = {
+            let mut rates = CnbRates::new();
+            rates.add(DAILY).unwrap();
+            rates
+        };
+        let txn = txn(
+            1,
+            "2025-10-18",
+            "-312.40",
+            json!({
+                "specification": "12.50 EUR",
+                "original_amount": "12.50 EUR",
+            }),
+        );
+        let breakdown = fx_breakdown(&rates, &txn).unwrap().unwrap();
+        assert_eq!(breakdown.effective_rate, Decimal::new(24992, 3));
+        assert_eq!(breakdown.reference_amount, Decimal::new(30450, 2));
+        assert_eq!(breakdown.fee, Decimal::new(790, 2));
+
+        let domestic = Transaction {
+            specification: None,
+            original_amount: None,
+            ..txn
+        };
+        assert_eq!(fx_breakdown(&rates, &domestic).unwrap(), None);
+    }
+}
@@ -1,13 +1,52 @@
 //! Rust client for the Fio banka REST API.
-//! Provides a small synchronous interface and helpers for parsing JSON
-//! statements into typed domain models.
+//! Provides an async client, a blocking wrapper behind the `blocking`
+//! feature and helpers for parsing JSON statements into typed domain models.
+//!
+//! The core does not depend on an async runtime. The default `reqwest`
+//! feature provides `ReqwestTransport`; without it, supply a
+//! [`Transport`] for the runtime of your choice.
 
+pub mod balance;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod card;
+pub mod cassette;
+pub mod categorize;
 pub mod client;
+pub mod credentials;
+pub mod dedup;
 pub mod error;
+pub mod forecast;
+pub mod fx;
+pub mod merchant;
+#[cfg(feature = "mock")]
+pub mod mock;
 pub mod models;
+pub mod payments;
+pub mod query;
+#[cfg(feature = "rate-limit")]
+pub mod rate_limit;
+pub mod reconcile;
+pub mod registry;
+pub mod reports;
+#[cfg(test)]
+mod test_util;
+pub mod token;
+pub mod transport;
 
+pub use cassette::Cassette;
 pub use client::{
-    AccountStatementFmt, Client, LastStatementInfo, StatementData, TransactionReportFmt,
+    AccountStatementFmt, Client, ImportFmt, LastStatementInfo, StatementData, TransactionReportFmt,
 };
+pub use credentials::TokenSource;
 pub use error::{ApiError, FioError};
-pub use models::{AccountInfo, Transaction};
+pub use models::{
+    AccountInfo, LenientTransactions, OriginalAmount, RejectedTransaction, Statement, Transaction,
+    TransactionKind,
+};
+pub use payments::{ImportResponse, PaymentBatch, PaymentOrder};
+pub use registry::AccountRegistry;
+pub use token::{ApiToken, FioToken, ReadToken, TokenScope, WriteToken};
+#[cfg(feature = "reqwest")]
+pub use transport::ReqwestTransport;
+pub use transport::{HttpResponse, MemoryTransport, Transport, TransportError};
@@ -1 +1,19 @@
-fn main() {}
+//! `fioapi` command-line client for the Fio banka REST API.
+
+mod cli;
+
+use std::process::ExitCode;
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    dotenvy::dotenv().ok();
+    env_logger::init();
+
+    match cli::run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::from(cli::exit_code(err.as_ref()))
+        }
+    }
+}
@@ -0,0 +1,335 @@
+//! Card acquiring: transactions of a merchant's POS terminals and gateways.
+//!
+//! Fio reports card payments accepted by a merchant separately from the
+//! account movements, through `/merchant/{token}/{from}/{to}/transactions.{fmt}`
+//! (see [`Client::fetch_merchant_transactions_for_period`]). Each entry shows
+//! the gross amount paid by the cardholder, Fio's fee and the net amount
+//! that is later settled to the account. [`settlements`] sums entries per
+//! settlement day, which is what appears as a single credit on the account.
+//!
+//! The JSON report has the same layout as `accountStatement`: every entry
+//! in `transactionList.transaction` is an object of `columnN` cells. The
+//! shared columns keep their account statement meaning (22 id, 0 date,
+//! 1 amount, 14 currency); the POS-specific ones are assumed to be
+//!
+//! | column | content                  |
+//! |--------|--------------------------|
+//! | 30     | terminal id              |
+//! | 31     | card brand               |
+//! | 32     | masked card number       |
+//! | 33     | authorization code       |
+//! | 34     | settlement date          |
+//! | 35     | fee                      |
+//!
+//! This mapping is inferred, not taken from Fio's published API
+//! documentation, and has not been checked against a real merchant report;
+//! the tests use synthetic reports. Parsing is therefore lenient: only the
+//! shared columns are required, and every column not listed above is kept
+//! in [`MerchantTransaction::other_columns`] so nothing is lost if the
+//! mapping turns out to be wrong. The fee is stored as a positive cost
+//! whichever sign the report uses.
+//!
+//! [`Client::fetch_merchant_transactions_for_period`]: crate::Client::fetch_merchant_transactions_for_period
+
+use crate::error::FioError;
+use crate::models::{ColumnValue, optional_string, parse_date, parse_i64_value};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy)]
+pub enum MerchantReportFmt {
+    Csv,
+    Json,
+    Xml,
+}
+
+impl fmt::Display for MerchantReportFmt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v = match self {
+            MerchantReportFmt::Csv => "csv",
+            MerchantReportFmt::Json => "json",
+            MerchantReportFmt::Xml => "xml",
+        };
+        f.write_str(v)
+    }
+}
+
+/// One card payment accepted by a terminal.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MerchantTransaction {
+    pub transaction_id: i64,
+    pub terminal_id: Option<String>,
+    /// Card scheme, e.g. `VISA` or `MASTERCARD`.
+    pub card_brand: Option<String>,
+    /// Masked card number.
+    pub card_number: Option<String>,
+    pub authorization_code: Option<String>,
+    pub transaction_date: NaiveDate,
+    /// Day the net amount is credited; `None` while not yet settled.
+    pub settlement_date: Option<NaiveDate>,
+    /// Amount paid by the cardholder; negative for refunds.
+    pub gross_amount: Decimal,
+    /// Fee charged by Fio, always positive.
+    pub fee: Decimal,
+    /// `gross_amount - fee`, the amount credited to the account.
+    pub net_amount: Decimal,
+    pub currency: String,
+    /// Non-empty cells of columns not mapped above, keyed by `columnN`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub other_columns: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MerchantResponse {
+    #[serde(rename = "accountStatement")]
+    statement: MerchantStatement,
+}
+
+#[derive(Debug, Deserialize)]
+struct MerchantStatement {
+    #[serde(rename = "transactionList")]
+    transaction_list: MerchantTransactionList,
+}
+
+#[derive(Debug, Deserialize)]
+struct MerchantTransactionList {
+    #[serde(default)]
+    transaction: Vec<RawMerchantTransaction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawMerchantTransaction {
+    #[serde(rename = "column22")]
+    transaction_id: ColumnValue<Value>,
+
+    #[serde(rename = "column0")]
+    transaction_date: ColumnValue<String>,
+
+    #[serde(rename = "column1")]
+    gross_amount: ColumnValue<Decimal>,
+
+    #[serde(rename = "column14")]
+    currency: ColumnValue<String>,
+
+    #[serde(rename = "column30")]
+    terminal_id: Option<ColumnValue<Value>>,
+
+    #[serde(rename = "column31")]
+    card_brand: Option<ColumnValue<Value>>,
+
+    #[serde(rename = "column32")]
+    card_number: Option<ColumnValue<Value>>,
+
+    #[serde(rename = "column33")]
+    authorization_code: Option<ColumnValue<Value>>,
+
+    #[serde(rename = "column34")]
+    settlement_date: Option<ColumnValue<String>>,
+
+    #[serde(rename = "column35")]
+    fee: Option<ColumnValue<Decimal>>,
+
+    #[serde(flatten)]
+    other_columns: BTreeMap<String, Value>,
+}
+
+impl TryFrom<RawMerchantTransaction> for MerchantTransaction {
+    type Error = FioError;
+
+    fn try_from(raw: RawMerchantTransaction) -> Result<Self, Self::Error> {
+        let transaction_id =
+            parse_i64_value(&raw.transaction_id.value).ok_or(FioError::InvalidResponse)?;
+        let transaction_date =
+            parse_date(&raw.transaction_date.value).ok_or(FioError::InvalidResponse)?;
+        let settlement_date = match raw.settlement_date {
+            None => None,
+            Some(date) => Some(parse_date(&date.value).ok_or(FioError::InvalidResponse)?),
+        };
+        let gross_amount = raw.gross_amount.value;
+        let fee = raw.fee.map_or(Decimal::ZERO, |fee| fee.value.abs());
+        let mut other_columns = raw.other_columns;
+        other_columns.retain(|_, cell| !cell.is_null());
+        Ok(MerchantTransaction {
+            transaction_id,
+            terminal_id: optional_string(&raw.terminal_id),
+            card_brand: optional_string(&raw.card_brand),
+            card_number: optional_string(&raw.card_number),
+            authorization_code: optional_string(&raw.authorization_code),
+            transaction_date,
+            settlement_date,
+            gross_amount,
+            fee,
+            net_amount: gross_amount - fee,
+            currency: raw.currency.value,
+            other_columns,
+        })
+    }
+}
+
+/// Parse a JSON merchant report.
+pub fn parse_merchant_transactions(data: &str) -> Result<Vec<MerchantTransaction>, FioError> {
+    let response: MerchantResponse =
+        serde_json::from_str(data).map_err(|_| FioError::InvalidResponse)?;
+    response
+        .statement
+        .transaction_list
+        .transaction
+        .into_iter()
+        .map(MerchantTransaction::try_from)
+        .collect()
+}
+
+/// Entries settled to the account on one day in one currency.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SettlementBatch {
+    pub settlement_date: NaiveDate,
+    pub currency: String,
+    pub count: usize,
+    pub gross_amount: Decimal,
+    pub fee: Decimal,
+    /// Expected credit on the account.
+    pub net_amount: Decimal,
+}
+
+/// Sum settled entries per settlement date and currency, oldest first.
+///
+/// Entries without a settlement date are left out.
+pub fn settlements(transactions: &[MerchantTransaction]) -> Vec<SettlementBatch> {
+    let mut batches: BTreeMap<(NaiveDate, &str), SettlementBatch> = BTreeMap::new();
+    for txn in transactions {
+        let Some(date) = txn.settlement_date else {
+            continue;
+        };
+        let batch = batches
+            .entry((date, &txn.currency))
+            .or_insert_with(|| SettlementBatch {
+                settlement_date: date,
+                currency: txn.currency.clone(),
+                count: 0,
+                gross_amount: Decimal::ZERO,
+                fee: Decimal::ZERO,
+                net_amount: Decimal::ZERO,
+            });
+        batch.count += 1;
+        batch.gross_amount += txn.gross_amount;
+        batch.fee += txn.fee;
+        batch.net_amount += txn.net_amount;
+    }
+    batches.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Client, MemoryTransport};
+    use http::StatusCode;
+    use serde_json::json;
+
+    // The reports below are synthetic, written from the column table in the
+    // module docs; no report captured from Fio is available.
+    #[tokio::test]
+    async fn fetches_and_parses_merchant_report() {
+        let cell = |id: u32, value: serde_json::Value| {
+            (format!("column{id}"), json!({ "value": value, "id": id }))
+        };
+        let row = |cells: Vec<(String, serde_json::Value)>| {
+            serde_json::Value::Object(cells.into_iter().collect())
+        };
+        let report = json!({
+            "accountStatement": {
+                "info": {},
+                "transactionList": {
+                    "transaction": [
+                        row(vec![
+                            cell(22, json!(501)),
+                            cell(0, json!("2025-04-01+0200")),
+                            cell(1, json!(1000)),
+                            cell(14, json!("CZK")),
+                            cell(30, json!("T0001")),
+                            cell(31, json!("VISA")),
+                            cell(32, json!("416598******1234")),
+                            cell(34, json!("2025-04-02+0200")),
+                            cell(35, json!(11.5)),
+                        ]),
+                        row(vec![
+                            cell(22, json!(502)),
+                            cell(0, json!("2025-04-01+0200")),
+                            cell(1, json!(200)),
+                            cell(14, json!("CZK")),
+                            cell(30, json!("T0001")),
+                            cell(31, json!("MASTERCARD")),
+                            cell(34, json!("2025-04-02+0200")),
+                            cell(35, json!(2.3)),
+                        ]),
+                        row(vec![
+                            cell(22, json!(503)),
+                            cell(0, json!("2025-04-02+0200")),
+                            cell(1, json!(50)),
+                            cell(14, json!("CZK")),
+                            cell(30, json!(7)),
+                        ]),
+                        row(vec![
+                            cell(22, json!(504)),
+                            cell(0, json!("2025-04-01+0200")),
+                            cell(1, json!(100)),
+                            cell(14, json!("CZK")),
+                            cell(34, json!("2025-04-03+0200")),
+                            cell(35, json!(-1.2)),
+                            cell(36, json!("E-SHOP")),
+                            ("column37".to_string(), json!(null)),
+                        ]),
+                    ]
+                }
+            }
+        });
+        let token = "p".repeat(64);
+        let transport =
+            MemoryTransport::new().on_get("/merchant/", StatusCode::OK, report.to_string());
+        let client = Client::from_transport(&token, transport)
+            .unwrap()
+            .with_base_url("http://fio.test");
+        let from = NaiveDate::from_ymd_opt(2025, 4, 1).unwrap();
+        let to = NaiveDate::from_ymd_opt(2025, 4, 2).unwrap();
+        let payload = client
+            .fetch_merchant_transactions_for_period(from, to, MerchantReportFmt::Json)
+            .await
+            .unwrap();
+        let transactions = client.parse_merchant_transactions(&payload).unwrap();
+        assert_eq!(transactions.len(), 4);
+        assert_eq!(transactions[1].transaction_id, 502);
+        assert_eq!(transactions[1].net_amount, Decimal::new(19770, 2));
+        assert_eq!(transactions[2].terminal_id.as_deref(), Some("7"));
+        assert_eq!(transactions[2].settlement_date, None);
+        let gateway = &transactions[3];
+        assert_eq!(gateway.terminal_id, None);
+        assert_eq!(gateway.fee, Decimal::new(12, 1));
+        assert_eq!(gateway.net_amount, Decimal::new(988, 1));
+        assert_eq!(
+            gateway.other_columns.keys().collect::<Vec<_>>(),
+            ["column36"]
+        );
+        assert_eq!(gateway.other_columns["column36"]["value"], "E-SHOP");
+
+        assert_eq!(
+            settlements(&transactions)[0],
+            SettlementBatch {
+                settlement_date: to,
+                currency: "CZK".to_string(),
+                count: 2,
+                gross_amount: Decimal::from(1200),
+                fee: Decimal::new(1380, 2),
+                net_amount: Decimal::new(118620, 2),
+            }
+        );
+        assert!(
+            client
+                .fetch_merchant_transactions_for_period(to, from, MerchantReportFmt::Json)
+                .await
+                .is_err()
+        );
+    }
+}
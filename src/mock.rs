@@ -0,0 +1,925 @@
+//! In-process mock of the Fio REST API for integration tests.
+//!
+//! [`MockServer`] listens on a random local port and implements every path
+//! used by [`Client`](crate::Client). Point a client at it with
+//! [`Client::with_base_url`](crate::Client::with_base_url):
+//!
+//! ```no_run
+//! # #[cfg(feature = "reqwest")]
+//! # async fn demo() -> Result<(), Box<dyn std::error::Error>> {
+//! use fioapi::mock::{MockFixture, MockServer};
+//! use fioapi::{Client, TransactionReportFmt};
+//!
+//! let token = "a".repeat(64);
+//! let server = MockServer::start(MockFixture::new(&token)).await?;
+//! let client = Client::new(token)?.with_base_url(server.base_url());
+//! let payload = client
+//!     .fetch_transaction_report_since_last_download(TransactionReportFmt::Json)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::merchant::MerchantTransaction;
+use crate::models::Transaction;
+use crate::token::TokenScope;
+use chrono::NaiveDate;
+use log::debug;
+use rust_decimal::Decimal;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// Minimum spacing between two requests with the same token enforced by Fio.
+pub const DEFAULT_RATE_LIMIT: Duration = Duration::from_secs(30);
+
+const MAX_REQUEST_SIZE: usize = 1024 * 1024;
+
+/// REST endpoints served by the mock, used to target injected errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MockEndpoint {
+    Periods,
+    Merchant,
+    ById,
+    Last,
+    LastStatement,
+    SetLastId,
+    SetLastDate,
+    Import,
+}
+
+impl MockEndpoint {
+    fn from_segment(segment: &str) -> Option<Self> {
+        match segment {
+            "periods" => Some(MockEndpoint::Periods),
+            "merchant" => Some(MockEndpoint::Merchant),
+            "by-id" => Some(MockEndpoint::ById),
+            "last" => Some(MockEndpoint::Last),
+            "lastStatement" => Some(MockEndpoint::LastStatement),
+            "set-last-id" => Some(MockEndpoint::SetLastId),
+            "set-last-date" => Some(MockEndpoint::SetLastDate),
+            "import" => Some(MockEndpoint::Import),
+            _ => None,
+        }
+    }
+}
+
+/// Account metadata reported in the `info` block of served statements.
+#[derive(Debug, Clone)]
+pub struct MockAccount {
+    pub account_id: String,
+    pub bank_id: String,
+    pub currency: String,
+    pub iban: String,
+    pub bic: String,
+    pub opening_balance: Decimal,
+}
+
+impl Default for MockAccount {
+    fn default() -> Self {
+        Self {
+            account_id: "2000000000".to_string(),
+            bank_id: "2010".to_string(),
+            currency: "CZK".to_string(),
+            iban: "CZ1020100000002000000000".to_string(),
+            bic: "FIOBCZPPXXX".to_string(),
+            opening_balance: Decimal::ZERO,
+        }
+    }
+}
+
+/// Seed data served by a [`MockServer`].
+#[derive(Debug, Clone)]
+pub struct MockFixture {
+    token: String,
+    account: MockAccount,
+    transactions: Vec<Transaction>,
+    statements: BTreeMap<(i32, i64), Vec<Transaction>>,
+    merchant_transactions: Vec<MerchantTransaction>,
+    last_downloaded_id: Option<i64>,
+    rate_limit: Duration,
+    scope: TokenScope,
+}
+
+impl MockFixture {
+    /// Create an empty fixture accepting the given token.
+    pub fn new(token: impl Into<String>) -> Self {
+        Self {
+            token: token.into(),
+            account: MockAccount::default(),
+            transactions: Vec::new(),
+            statements: BTreeMap::new(),
+            merchant_transactions: Vec::new(),
+            last_downloaded_id: None,
+            rate_limit: DEFAULT_RATE_LIMIT,
+            scope: TokenScope::Read,
+        }
+    }
+
+    pub fn with_account(mut self, account: MockAccount) -> Self {
+        self.account = account;
+        self
+    }
+
+    /// Add a transaction to the account history.
+    pub fn with_transaction(mut self, transaction: Transaction) -> Self {
+        self.transactions.push(transaction);
+        self
+    }
+
+    pub fn with_transactions(
+        mut self,
+        transactions: impl IntoIterator<Item = Transaction>,
+    ) -> Self {
+        self.transactions.extend(transactions);
+        self
+    }
+
+    /// Register an official statement served from `/by-id`.
+    pub fn with_statement(
+        mut self,
+        year: i32,
+        statement_id: i64,
+        transactions: impl IntoIterator<Item = Transaction>,
+    ) -> Self {
+        self.statements
+            .insert((year, statement_id), transactions.into_iter().collect());
+        self
+    }
+
+    /// Card payments served from `/merchant`.
+    pub fn with_merchant_transactions(
+        mut self,
+        transactions: impl IntoIterator<Item = MerchantTransaction>,
+    ) -> Self {
+        self.merchant_transactions.extend(transactions);
+        self
+    }
+
+    /// Initial position of the "last downloaded transaction" cursor.
+    pub fn with_last_downloaded_id(mut self, transaction_id: i64) -> Self {
+        self.last_downloaded_id = Some(transaction_id);
+        self
+    }
+
+    /// Capability of the fixture token; read-only tokens get 422 from `/import`.
+    pub fn with_token_scope(mut self, scope: TokenScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Override the per-token request spacing; `Duration::ZERO` disables it.
+    pub fn with_rate_limit(mut self, rate_limit: Duration) -> Self {
+        self.rate_limit = rate_limit;
+        self
+    }
+}
+
+#[derive(Debug)]
+struct MockState {
+    fixture: MockFixture,
+    last_request: Option<Instant>,
+    injected: Vec<(Option<MockEndpoint>, u16)>,
+    requests: Vec<String>,
+    imports: Vec<String>,
+}
+
+/// A running mock server; shuts down when dropped.
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Bind to a random local port and start serving the fixture.
+    pub async fn start(fixture: MockFixture) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(MockState {
+            fixture,
+            last_request: None,
+            injected: Vec::new(),
+            requests: Vec::new(),
+            imports: Vec::new(),
+        }));
+        let task = tokio::spawn(accept_loop(listener, Arc::clone(&state)));
+        debug!("Mock Fio API listening on {}", addr);
+        Ok(Self { addr, state, task })
+    }
+
+    /// Base URL to pass to [`Client::with_base_url`](crate::Client::with_base_url).
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Answer the next request with the given status code.
+    pub fn inject_error(&self, status: u16) {
+        self.lock().injected.push((None, status));
+    }
+
+    /// Answer the next request to `endpoint` with the given status code.
+    pub fn inject_error_on(&self, endpoint: MockEndpoint, status: u16) {
+        self.lock().injected.push((Some(endpoint), status));
+    }
+
+    /// Current position of the "last downloaded transaction" cursor.
+    pub fn last_downloaded_id(&self) -> Option<i64> {
+        self.lock().fixture.last_downloaded_id
+    }
+
+    /// Forget the time of the previous request so the next one is not throttled.
+    pub fn reset_rate_limit(&self) {
+        self.lock().last_request = None;
+    }
+
+    /// Payment order documents accepted by `/import` so far.
+    pub fn imports(&self) -> Vec<String> {
+        self.lock().imports.clone()
+    }
+
+    /// Paths of all requests received so far, with the token redacted.
+    pub fn requests(&self) -> Vec<String> {
+        self.lock().requests.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn accept_loop(listener: TcpListener, state: Arc<Mutex<MockState>>) {
+    loop {
+        let Ok((stream, _)) = listener.accept().await else {
+            continue;
+        };
+        let state = Arc::clone(&state);
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, state).await {
+                debug!("Mock connection failed: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, state: Arc<Mutex<MockState>>) -> io::Result<()> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if buf.len() > MAX_REQUEST_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "request too large",
+            ));
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).into_owned();
+    let content_length = head
+        .lines()
+        .filter_map(|l| l.split_once(':'))
+        .find(|(k, _)| k.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, v)| v.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    while buf.len() < header_end + content_length.min(MAX_REQUEST_SIZE) {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+
+    let response = {
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+        state.respond(&method, &path, &buf[header_end..])
+    };
+    stream.write_all(&response.encode()).await?;
+    stream.shutdown().await
+}
+
+struct MockResponse {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl MockResponse {
+    fn ok(content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: 200,
+            content_type,
+            body: body.into(),
+        }
+    }
+
+    fn status(status: u16) -> Self {
+        Self {
+            status,
+            content_type: "text/plain",
+            body: Vec::new(),
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut out = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status,
+            reason_phrase(self.status),
+            self.content_type,
+            self.body.len()
+        )
+        .into_bytes();
+        out.extend_from_slice(&self.body);
+        out
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        500 => "Internal Server Error",
+        _ => "Unknown",
+    }
+}
+
+impl MockState {
+    fn respond(&mut self, method: &str, path: &str, body: &[u8]) -> MockResponse {
+        let segments: Vec<&str> = path
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect();
+        let endpoint = segments.first().and_then(|s| MockEndpoint::from_segment(s));
+        let body = String::from_utf8_lossy(body);
+        let token = match endpoint {
+            Some(MockEndpoint::Import) => multipart_field(&body, "token").unwrap_or_default(),
+            _ => segments.get(1).copied().unwrap_or_default(),
+        };
+        self.requests
+            .push(path.replace(&self.fixture.token, "<token>"));
+
+        if let Some(pos) = self
+            .injected
+            .iter()
+            .position(|(target, _)| target.is_none() || *target == endpoint)
+        {
+            let (_, status) = self.injected.remove(pos);
+            return MockResponse::status(status);
+        }
+        let Some(endpoint) = endpoint else {
+            return MockResponse::status(404);
+        };
+        let expected_method = match endpoint {
+            MockEndpoint::Import => "POST",
+            _ => "GET",
+        };
+        if method != expected_method {
+            return MockResponse::status(405);
+        }
+        if token != self.fixture.token {
+            return MockResponse::status(500);
+        }
+        let now = Instant::now();
+        if let Some(last) = self.last_request
+            && now.duration_since(last) < self.fixture.rate_limit
+        {
+            return MockResponse::status(409);
+        }
+        self.last_request = Some(now);
+
+        let args = segments.get(2..).unwrap_or_default();
+        match endpoint {
+            MockEndpoint::Periods => self.periods(args),
+            MockEndpoint::Merchant => self.merchant(args),
+            MockEndpoint::ById => self.by_id(args),
+            MockEndpoint::Last => self.last(args),
+            MockEndpoint::LastStatement => self.last_statement(),
+            MockEndpoint::SetLastId => self.set_last_id(args),
+            MockEndpoint::SetLastDate => self.set_last_date(args),
+            MockEndpoint::Import => self.import(&body),
+        }
+    }
+
+    fn import(&mut self, body: &str) -> MockResponse {
+        if !self.fixture.scope.can_write() {
+            return MockResponse::status(422);
+        }
+        let Some(document) = multipart_field(body, "file") else {
+            return MockResponse::status(404);
+        };
+        let orders = [
+            "<DomesticTransaction>",
+            "<T2Transaction>",
+            "<ForeignTransaction>",
+        ]
+        .iter()
+        .map(|tag| document.matches(tag).count())
+        .sum::<usize>();
+        self.imports.push(document.to_string());
+        let details: String = (1..=orders)
+            .map(|id| {
+                format!(
+                    r#"<detail id="{id}"><messages><message status="ok" errorCode="0">OK</message></messages></detail>"#
+                )
+            })
+            .collect();
+        let instruction = self.imports.len();
+        MockResponse::ok(
+            "application/xml",
+            format!(
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<responseImport><result><errorCode>0</errorCode><idInstruction>{instruction}</idInstruction><status>ok</status></result><ordersDetails>{details}</ordersDetails></responseImport>"#
+            ),
+        )
+    }
+
+    fn periods(&self, args: &[&str]) -> MockResponse {
+        let (Some(from), Some(to), Some(file)) = (
+            args.first().and_then(|s| parse_date(s)),
+            args.get(1).and_then(|s| parse_date(s)),
+            args.get(2),
+        ) else {
+            return MockResponse::status(404);
+        };
+        let txns: Vec<&Transaction> = self
+            .fixture
+            .transactions
+            .iter()
+            .filter(|t| t.date >= from && t.date <= to)
+            .collect();
+        self.report(file, &txns, Some(from), Some(to), None)
+    }
+
+    fn merchant(&self, args: &[&str]) -> MockResponse {
+        let (Some(from), Some(to), Some(fmt)) = (
+            args.first().and_then(|s| parse_date(s)),
+            args.get(1).and_then(|s| parse_date(s)),
+            args.get(2).and_then(|s| s.strip_prefix("transactions.")),
+        ) else {
+            return MockResponse::status(404);
+        };
+        let txns: Vec<&MerchantTransaction> = self
+            .fixture
+            .merchant_transactions
+            .iter()
+            .filter(|t| t.transaction_date >= from && t.transaction_date <= to)
+            .collect();
+        match fmt {
+            "json" => {
+                let body = json!({
+                    "accountStatement": {
+                        "info": {
+                            "accountId": self.fixture.account.account_id,
+                            "bankId": self.fixture.account.bank_id,
+                            "dateStart": fio_date(from),
+                            "dateEnd": fio_date(to),
+                        },
+                        "transactionList": {
+                            "transaction": txns.iter().map(|t| merchant_json(t)).collect::<Vec<_>>(),
+                        }
+                    }
+                });
+                MockResponse::ok("application/json", body.to_string())
+            }
+            other => MockResponse::ok(
+                "text/plain",
+                format!(
+                    "mock {} merchant report with {} transactions\n",
+                    other,
+                    txns.len()
+                ),
+            ),
+        }
+    }
+
+    fn by_id(&self, args: &[&str]) -> MockResponse {
+        let (Some(year), Some(id), Some(file)) = (
+            args.first().and_then(|s| s.parse::<i32>().ok()),
+            args.get(1).and_then(|s| s.parse::<i64>().ok()),
+            args.get(2),
+        ) else {
+            return MockResponse::status(404);
+        };
+        let Some(txns) = self.fixture.statements.get(&(year, id)) else {
+            return MockResponse::status(404);
+        };
+        let txns: Vec<&Transaction> = txns.iter().collect();
+        self.report(file, &txns, None, None, Some((year, id)))
+    }
+
+    fn last(&mut self, args: &[&str]) -> MockResponse {
+        let Some(file) = args.first() else {
+            return MockResponse::status(404);
+        };
+        let cursor = self.fixture.last_downloaded_id;
+        let txns: Vec<&Transaction> = self
+            .fixture
+            .transactions
+            .iter()
+            .filter(|t| cursor.is_none_or(|c| t.transaction_id > c))
+            .collect();
+        let response = self.report(file, &txns, None, None, None);
+        if let Some(max) = txns.iter().map(|t| t.transaction_id).max() {
+            self.fixture.last_downloaded_id = Some(max);
+        }
+        response
+    }
+
+    fn last_statement(&self) -> MockResponse {
+        let (year, id) = self
+            .fixture
+            .statements
+            .keys()
+            .next_back()
+            .copied()
+            .unwrap_or((0, 0));
+        MockResponse::ok("text/plain", format!("{},{}", year, id))
+    }
+
+    fn set_last_id(&mut self, args: &[&str]) -> MockResponse {
+        let Some(id) = args.first().and_then(|s| s.parse::<i64>().ok()) else {
+            return MockResponse::status(404);
+        };
+        self.fixture.last_downloaded_id = Some(id);
+        MockResponse::ok("text/plain", "")
+    }
+
+    fn set_last_date(&mut self, args: &[&str]) -> MockResponse {
+        let Some(date) = args.first().and_then(|s| parse_date(s)) else {
+            return MockResponse::status(404);
+        };
+        // The next download starts with the transactions from `date`.
+        self.fixture.last_downloaded_id = self
+            .fixture
+            .transactions
+            .iter()
+            .filter(|t| t.date < date)
+            .map(|t| t.transaction_id)
+            .max();
+        MockResponse::ok("text/plain", "")
+    }
+
+    fn report(
+        &self,
+        file: &str,
+        txns: &[&Transaction],
+        date_start: Option<NaiveDate>,
+        date_end: Option<NaiveDate>,
+        statement: Option<(i32, i64)>,
+    ) -> MockResponse {
+        let Some(fmt) = file.strip_prefix("transactions.") else {
+            return MockResponse::status(404);
+        };
+        match fmt {
+            "json" => {
+                let body =
+                    statement_json(&self.fixture.account, txns, date_start, date_end, statement);
+                MockResponse::ok("application/json", body.to_string())
+            }
+            "pdf" => MockResponse::ok("application/pdf", b"%PDF-1.4\n% fioapi mock\n".to_vec()),
+            other => MockResponse::ok(
+                "text/plain",
+                format!("mock {} report with {} transactions\n", other, txns.len()),
+            ),
+        }
+    }
+}
+
+/// Extract a field from a `multipart/form-data` body.
+fn multipart_field<'a>(body: &'a str, name: &str) -> Option<&'a str> {
+    let marker = format!("name=\"{}\"", name);
+    let start = body.find(&marker)?;
+    let content = start + body[start..].find("\r\n\r\n")? + 4;
+    let end = content + body[content..].find("\r\n--")?;
+    Some(&body[content..end])
+}
+
+fn parse_date(raw: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(raw, "%Y-%m-%d").ok()
+}
+
+fn fio_date(date: NaiveDate) -> String {
+    format!("{}+0100", date.format("%Y-%m-%d"))
+}
+
+fn column<T: Into<Value>>(id: u32, name: &str, value: Option<T>) -> (String, Value) {
+    let value = match value {
+        Some(v) => json!({ "value": v.into(), "name": name, "id": id }),
+        None => Value::Null,
+    };
+    (format!("column{}", id), value)
+}
+
+fn transaction_json(txn: &Transaction) -> Value {
+    let columns = [
+        column(22, "ID pohybu", Some(txn.transaction_id)),
+        column(0, "Datum", Some(fio_date(txn.date))),
+        column(1, "Objem", Some(txn.amount.to_string())),
+        column(14, "Měna", Some(txn.currency.clone())),
+        column(2, "Protiúčet", txn.account_id.clone()),
+        column(10, "Název protiúčtu", txn.account_name.clone()),
+        column(3, "Kód banky", txn.bank_id.clone()),
+        column(12, "Název banky", txn.bank_name.clone()),
+        column(4, "KS", txn.ks.map(|v| format!("{:04}", v))),
+        column(5, "VS", txn.vs.map(|v| v.to_string())),
+        column(6, "SS", txn.ss.map(|v| v.to_string())),
+        column(
+            7,
+            "Uživatelská identifikace",
+            txn.user_identification.clone(),
+        ),
+        column(16, "Zpráva pro příjemce", txn.message_from_sender.clone()),
+        column(8, "Typ", txn.transaction_type.clone()),
+        column(9, "Provedl", txn.executor.clone()),
+        column(18, "Upřesnění", txn.specification.clone()),
+        column(25, "Komentář", txn.comment.clone()),
+        column(26, "BIC", txn.bic.clone()),
+        column(17, "ID pokynu", txn.order_id),
+        column(27, "Reference plátce", txn.payer_reference.clone()),
+    ];
+    Value::Object(columns.into_iter().collect())
+}
+
+fn merchant_json(txn: &MerchantTransaction) -> Value {
+    let columns = [
+        column(22, "ID pohybu", Some(txn.transaction_id)),
+        column(0, "Datum", Some(fio_date(txn.transaction_date))),
+        column(1, "Objem", Some(txn.gross_amount.to_string())),
+        column(14, "Měna", Some(txn.currency.clone())),
+        column(30, "Terminál", txn.terminal_id.clone()),
+        column(31, "Typ karty", txn.card_brand.clone()),
+        column(32, "Číslo karty", txn.card_number.clone()),
+        column(33, "Autorizační kód", txn.authorization_code.clone()),
+        column(34, "Datum zúčtování", txn.settlement_date.map(fio_date)),
+        column(35, "Poplatek", Some(txn.fee.to_string())),
+    ];
+    let other = txn.other_columns.clone();
+    Value::Object(columns.into_iter().chain(other).collect())
+}
+
+fn statement_json(
+    account: &MockAccount,
+    txns: &[&Transaction],
+    date_start: Option<NaiveDate>,
+    date_end: Option<NaiveDate>,
+    statement: Option<(i32, i64)>,
+) -> Value {
+    let total: Decimal = txns.iter().map(|t| t.amount).sum();
+    let date_start = date_start.or_else(|| txns.iter().map(|t| t.date).min());
+    let date_end = date_end.or_else(|| txns.iter().map(|t| t.date).max());
+    json!({
+        "accountStatement": {
+            "info": {
+                "accountId": account.account_id,
+                "bankId": account.bank_id,
+                "currency": account.currency,
+                "iban": account.iban,
+                "bic": account.bic,
+                "openingBalance": account.opening_balance.to_string(),
+                "closingBalance": (account.opening_balance + total).to_string(),
+                "dateStart": date_start.map(fio_date),
+                "dateEnd": date_end.map(fio_date),
+                "yearList": statement.map(|(year, _)| year),
+                "idList": statement.map(|(_, id)| id),
+                "idFrom": txns.iter().map(|t| t.transaction_id).min(),
+                "idTo": txns.iter().map(|t| t.transaction_id).max(),
+                "idLastDownload": Value::Null,
+            },
+            "transactionList": {
+                "transaction": txns.iter().map(|t| transaction_json(t)).collect::<Vec<_>>(),
+            }
+        }
+    })
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod tests {
+    use super::*;
+    use crate::{ApiError, Client, FioError, TransactionReportFmt};
+    use std::str::FromStr;
+
+    fn token() -> String {
+        "t".repeat(64)
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    fn fixture() -> MockFixture {
+        MockFixture::new(token())
+            .with_rate_limit(Duration::ZERO)
+            .with_transaction(Transaction::new(1, date(1), Decimal::from(100), "CZK"))
+            .with_transaction(Transaction::new(
+                2,
+                date(2),
+                Decimal::from_str("-20.5").unwrap(),
+                "CZK",
+            ))
+            .with_transaction(Transaction::new(3, date(5), Decimal::from(7), "CZK"))
+    }
+
+    async fn start(fixture: MockFixture) -> (MockServer, Client) {
+        let server = MockServer::start(fixture).await.unwrap();
+        let client = Client::new(token())
+            .unwrap()
+            .with_base_url(server.base_url());
+        (server, client)
+    }
+
+    #[tokio::test]
+    async fn serves_period_reports() {
+        let (_server, client) = start(fixture()).await;
+        let payload = client
+            .fetch_transaction_report_for_period(date(2), date(5), TransactionReportFmt::Json)
+            .await
+            .unwrap();
+        let info = client.parse_account_info(&payload).unwrap();
+        let txns = client.parse_transactions(&payload).unwrap();
+        assert_eq!(
+            txns.iter().map(|t| t.transaction_id).collect::<Vec<_>>(),
+            [2, 3]
+        );
+        assert_eq!(
+            info.closing_balance,
+            Some(Decimal::from_str("-13.5").unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn simulates_download_cursor() {
+        let (server, client) = start(fixture().with_last_downloaded_id(1)).await;
+        let fmt = TransactionReportFmt::Json;
+        let payload = client
+            .fetch_transaction_report_since_last_download(fmt)
+            .await
+            .unwrap();
+        assert_eq!(client.parse_transactions(&payload).unwrap().len(), 2);
+        assert_eq!(server.last_downloaded_id(), Some(3));
+
+        let payload = client
+            .fetch_transaction_report_since_last_download(fmt)
+            .await
+            .unwrap();
+        assert!(client.parse_transactions(&payload).unwrap().is_empty());
+
+        client
+            .set_last_unsuccessful_download_date(date(2))
+            .await
+            .unwrap();
+        assert_eq!(server.last_downloaded_id(), Some(1));
+        client.set_last_downloaded_transaction_id(2).await.unwrap();
+        let payload = client
+            .fetch_transaction_report_since_last_download(fmt)
+            .await
+            .unwrap();
+        let txns = client.parse_transactions(&payload).unwrap();
+        assert_eq!(txns[0].transaction_id, 3);
+    }
+
+    #[tokio::test]
+    async fn serves_merchant_reports() {
+        let payment = |transaction_id, day| MerchantTransaction {
+            transaction_id,
+            terminal_id: Some("T0001".to_string()),
+            card_brand: Some("VISA".to_string()),
+            card_number: None,
+            authorization_code: None,
+            transaction_date: date(day),
+            settlement_date: Some(date(day + 1)),
+            gross_amount: Decimal::from(100),
+            fee: Decimal::from_str("1.5").unwrap(),
+            net_amount: Decimal::from_str("98.5").unwrap(),
+            currency: "CZK".to_string(),
+            other_columns: Default::default(),
+        };
+        let fixture = fixture().with_merchant_transactions([payment(7, 1), payment(8, 4)]);
+        let (_server, client) = start(fixture).await;
+        let payload = client
+            .fetch_merchant_transactions_for_period(
+                date(1),
+                date(2),
+                crate::merchant::MerchantReportFmt::Json,
+            )
+            .await
+            .unwrap();
+        let txns = client.parse_merchant_transactions(&payload).unwrap();
+        assert_eq!(txns, [payment(7, 1)]);
+    }
+
+    #[tokio::test]
+    async fn serves_statements() {
+        let fixture = fixture()
+            .with_statement(2024, 1, [Transaction::new(1, date(1), Decimal::ONE, "CZK")])
+            .with_statement(2024, 2, []);
+        let (_server, client) = start(fixture).await;
+        let info = client.fetch_last_account_statement_info().await.unwrap();
+        assert_eq!((info.year, info.statement_id), (2024, 2));
+        let err = client
+            .fetch_account_statement(2024, 3, crate::AccountStatementFmt::Json)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FioError::Api(ApiError::InvalidRequest)));
+    }
+
+    #[tokio::test]
+    async fn enforces_rate_limit() {
+        let (server, client) = start(fixture().with_rate_limit(DEFAULT_RATE_LIMIT)).await;
+        client.fetch_last_account_statement_info().await.unwrap();
+        let err = client
+            .fetch_last_account_statement_info()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FioError::Api(ApiError::TimeLimit)));
+        server.reset_rate_limit();
+        client.fetch_last_account_statement_info().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_tokens() {
+        let server = MockServer::start(fixture()).await.unwrap();
+        let client = Client::new("x".repeat(64))
+            .unwrap()
+            .with_base_url(server.base_url());
+        let err = client
+            .fetch_last_account_statement_info()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FioError::Api(ApiError::InvalidToken)));
+        assert_eq!(
+            server.requests(),
+            ["/lastStatement/".to_string() + &"x".repeat(64) + "/statement"]
+        );
+    }
+
+    #[tokio::test]
+    async fn probes_token_scope_and_imports() {
+        let (server, client) = start(fixture()).await;
+        assert_eq!(client.probe_token_scope().await.unwrap(), TokenScope::Read);
+        assert!(client.clone().into_write().await.is_err());
+
+        let (server_rw, client) = start(fixture().with_token_scope(TokenScope::ReadWrite)).await;
+        let client = client.into_write().await.unwrap();
+        let response = client
+            .import_payment_orders(
+                "<Import><Orders><DomesticTransaction></DomesticTransaction></Orders></Import>",
+                crate::ImportFmt::Xml,
+            )
+            .await
+            .unwrap();
+        assert!(response.contains(r#"<detail id="1">"#));
+        assert_eq!(server_rw.imports().len(), 2);
+        assert!(server.imports().is_empty());
+    }
+
+    #[tokio::test]
+    async fn injects_errors() {
+        let (server, client) = start(fixture()).await;
+        server.inject_error_on(MockEndpoint::SetLastId, 422);
+        server.inject_error(413);
+        let err = client
+            .fetch_last_account_statement_info()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FioError::Api(ApiError::TooManyItems)));
+        let err = client
+            .set_last_downloaded_transaction_id(1)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FioError::Api(ApiError::Authorization)));
+        client.set_last_downloaded_transaction_id(1).await.unwrap();
+    }
+}
@@ -1,12 +1,15 @@
+use crate::card::CardPaymentDetails;
 use crate::error::FioError;
 use chrono::NaiveDate;
 use log::debug;
 use rust_decimal::Decimal;
 use serde::de::Error as DeError;
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AccountInfo {
     #[serde(rename = "accountId")]
     pub account_id: Option<String>,
@@ -35,7 +38,7 @@ pub struct AccountInfo {
     pub id_last_download: Option<i64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Transaction {
     pub transaction_id: i64,
     pub date: NaiveDate,
@@ -57,6 +60,239 @@ pub struct Transaction {
     pub bic: Option<String>,
     pub order_id: Option<i64>,
     pub payer_reference: Option<String>,
+    /// Amount in the original currency of a foreign payment, read from
+    /// `specification`. `None` when absent or in the booking currency.
+    #[serde(default)]
+    pub original_amount: Option<OriginalAmount>,
+}
+
+impl Transaction {
+    /// A transaction with only the required columns set.
+    pub fn new(
+        transaction_id: i64,
+        date: NaiveDate,
+        amount: Decimal,
+        currency: impl Into<String>,
+    ) -> Self {
+        Self {
+            transaction_id,
+            date,
+            amount,
+            currency: currency.into(),
+            account_id: None,
+            account_name: None,
+            bank_id: None,
+            bank_name: None,
+            ks: None,
+            vs: None,
+            ss: None,
+            user_identification: None,
+            message_from_sender: None,
+            transaction_type: None,
+            executor: None,
+            specification: None,
+            comment: None,
+            bic: None,
+            order_id: None,
+            payer_reference: None,
+            original_amount: None,
+        }
+    }
+
+    /// Category derived from the Czech `transaction_type` text.
+    pub fn kind(&self) -> TransactionKind {
+        self.transaction_type
+            .as_deref()
+            .map_or(TransactionKind::Other, TransactionKind::from_type)
+    }
+
+    /// Merchant, card and purchase details of a card payment.
+    pub fn card_details(&self) -> Option<CardPaymentDetails> {
+        CardPaymentDetails::from_transaction(self)
+    }
+
+    /// Booking currency units paid per unit of the original currency,
+    /// rounded to six decimal places.
+    pub fn effective_rate(&self) -> Option<Decimal> {
+        let original = self.original_amount.as_ref()?;
+        if original.amount.is_zero() {
+            return None;
+        }
+        Some((self.amount.abs() / original.amount.abs()).round_dp(6))
+    }
+}
+
+/// Amount and currency of a foreign payment before conversion.
+///
+/// Serialized as text such as `12.50 EUR`, so it fits into a CSV cell.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct OriginalAmount {
+    /// As stated by Fio, usually without sign.
+    pub amount: Decimal,
+    pub currency: String,
+}
+
+impl FromStr for OriginalAmount {
+    type Err = FioError;
+
+    /// Parse `12.50 EUR`; `EUR 12.50`, decimal commas and digit grouping
+    /// such as `1 234,50 EUR` or `1.234,50 EUR` are accepted as well.
+    ///
+    /// When both `,` and `.` occur, the last one is the decimal separator.
+    /// A separator that repeats (`1.234.567 HUF`) groups digits, and so does
+    /// a single one followed by exactly three digits after a non-zero
+    /// integer part of up to three digits (`1,234 EUR` is 1234, while
+    /// `0.125 EUR` and `12,5 EUR` keep their decimals).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || FioError::InvalidParameter("invalid original amount");
+        let s = s.trim();
+        let is_code = |c: &str| c.len() == 3 && c.chars().all(|c| c.is_ascii_uppercase());
+        let (number, currency) = match (s.get(..3), s.get(s.len().saturating_sub(3)..)) {
+            (_, Some(code)) if is_code(code) => (&s[..s.len() - 3], code),
+            (Some(code), _) if is_code(code) => (&s[3..], code),
+            _ => return Err(invalid()),
+        };
+        let mut number: String = number
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '\'')
+            .collect();
+        if let Some(decimal) = decimal_separator(&number) {
+            let (int, frac) = number.split_at(decimal);
+            number = format!("{}.{}", int.replace([',', '.'], ""), &frac[1..]);
+        } else {
+            number.retain(|c| c != ',' && c != '.');
+        }
+        Ok(OriginalAmount {
+            amount: number.parse().map_err(|_| invalid())?,
+            currency: currency.to_string(),
+        })
+    }
+}
+
+/// Byte position of the decimal separator in `number`, if it has one.
+fn decimal_separator(number: &str) -> Option<usize> {
+    let last = number.rfind([',', '.'])?;
+    let separator = number[last..].chars().next()?;
+    let other = if separator == ',' { '.' } else { ',' };
+    if number.contains(other) {
+        return Some(last);
+    }
+    if number.matches(separator).count() > 1 {
+        return None;
+    }
+    let (int, frac) = (&number[..last], &number[last + 1..]);
+    let digits = int.trim_start_matches(['-', '+']);
+    let grouping = frac.len() == 3
+        && frac.chars().all(|c| c.is_ascii_digit())
+        && (1..=3).contains(&digits.len())
+        && digits != "0";
+    (!grouping).then_some(last)
+}
+
+impl TryFrom<String> for OriginalAmount {
+    type Error = FioError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<OriginalAmount> for String {
+    fn from(value: OriginalAmount) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for OriginalAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.amount, self.currency)
+    }
+}
+
+/// Coarse category of a transaction, independent of Fio's wording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TransactionKind {
+    IncomingTransfer,
+    OutgoingTransfer,
+    CardPayment,
+    CashDeposit,
+    CashWithdrawal,
+    DirectDebit,
+    Interest,
+    Fee,
+    Tax,
+    Other,
+}
+
+impl TransactionKind {
+    /// Classify a `transaction_type` value such as "Platba kartou".
+    pub fn from_type(value: &str) -> Self {
+        let value = value.to_lowercase();
+        let has = |needle: &str| value.contains(needle);
+        if has("poplatek") {
+            TransactionKind::Fee
+        } else if has("daň") || has("daně") || has("dane") {
+            TransactionKind::Tax
+        } else if has("úrok") || has("urok") {
+            TransactionKind::Interest
+        } else if has("kartou") || has("karta") {
+            TransactionKind::CardPayment
+        } else if has("inkaso") {
+            TransactionKind::DirectDebit
+        } else if has("vklad") {
+            TransactionKind::CashDeposit
+        } else if has("výběr") || has("vyber") {
+            TransactionKind::CashWithdrawal
+        } else if has("příjem") || has("prijem") || has("příchozí") {
+            TransactionKind::IncomingTransfer
+        } else if has("platba") || has("odchozí") || has("převod") {
+            TransactionKind::OutgoingTransfer
+        } else {
+            TransactionKind::Other
+        }
+    }
+}
+
+impl fmt::Display for TransactionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v = match self {
+            TransactionKind::IncomingTransfer => "incoming-transfer",
+            TransactionKind::OutgoingTransfer => "outgoing-transfer",
+            TransactionKind::CardPayment => "card-payment",
+            TransactionKind::CashDeposit => "cash-deposit",
+            TransactionKind::CashWithdrawal => "cash-withdrawal",
+            TransactionKind::DirectDebit => "direct-debit",
+            TransactionKind::Interest => "interest",
+            TransactionKind::Fee => "fee",
+            TransactionKind::Tax => "tax",
+            TransactionKind::Other => "other",
+        };
+        f.write_str(v)
+    }
+}
+
+impl FromStr for TransactionKind {
+    type Err = FioError;
+
+    /// Parse the kebab-case name; `card`, `incoming`, `outgoing`, `deposit`
+    /// and `withdrawal` are accepted as short forms.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "incoming-transfer" | "incoming" => TransactionKind::IncomingTransfer,
+            "outgoing-transfer" | "outgoing" => TransactionKind::OutgoingTransfer,
+            "card-payment" | "card" => TransactionKind::CardPayment,
+            "cash-deposit" | "deposit" => TransactionKind::CashDeposit,
+            "cash-withdrawal" | "withdrawal" => TransactionKind::CashWithdrawal,
+            "direct-debit" => TransactionKind::DirectDebit,
+            "interest" => TransactionKind::Interest,
+            "fee" => TransactionKind::Fee,
+            "tax" => TransactionKind::Tax,
+            "other" => TransactionKind::Other,
+            _ => return Err(FioError::InvalidParameter("unknown transaction kind")),
+        })
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -70,14 +306,76 @@ impl FioResponse {
         &self.account_statement.info
     }
 
+    pub fn statement(&self) -> Result<Statement, FioError> {
+        Ok(Statement {
+            info: self.account_statement.info.clone(),
+            transactions: self.transactions()?,
+        })
+    }
+
+    /// Convert all transactions, failing with [`FioError::InvalidResponse`]
+    /// on the first malformed row. The reason is logged at debug level;
+    /// [`Self::transactions_lenient`] reports it per row.
     pub fn transactions(&self) -> Result<Vec<Transaction>, FioError> {
         self.account_statement
             .transaction_list
             .transaction
             .iter()
-            .map(Transaction::try_from)
+            .map(|raw| {
+                transaction_from_value(raw).map_err(|e| {
+                    debug!("Rejecting statement: {}", e);
+                    FioError::InvalidResponse
+                })
+            })
             .collect()
     }
+
+    /// Convert transactions one by one, collecting rejected rows instead of
+    /// failing the whole statement on the first malformed entry.
+    pub fn transactions_lenient(&self) -> LenientTransactions {
+        let mut parsed = LenientTransactions::default();
+        for (index, raw) in self
+            .account_statement
+            .transaction_list
+            .transaction
+            .iter()
+            .enumerate()
+        {
+            match transaction_from_value(raw) {
+                Ok(txn) => parsed.transactions.push(txn),
+                Err(error) => parsed.rejected.push(RejectedTransaction {
+                    index,
+                    raw: raw.clone(),
+                    error,
+                }),
+            }
+        }
+        parsed
+    }
+}
+
+/// Account info and transactions of one statement or period fetch.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Statement {
+    pub info: AccountInfo,
+    pub transactions: Vec<Transaction>,
+}
+
+/// Result of lenient transaction parsing.
+#[derive(Debug, Default)]
+pub struct LenientTransactions {
+    pub transactions: Vec<Transaction>,
+    pub rejected: Vec<RejectedTransaction>,
+}
+
+/// A transaction entry that could not be converted into a [`Transaction`].
+#[derive(Debug)]
+pub struct RejectedTransaction {
+    /// Position of the entry within `transactionList.transaction`.
+    pub index: usize,
+    /// The entry exactly as returned by the API.
+    pub raw: Value,
+    pub error: FioError,
 }
 
 #[derive(Debug, Deserialize)]
@@ -90,12 +388,12 @@ pub struct AccountStatement {
 
 #[derive(Debug, Deserialize)]
 pub struct TransactionList {
-    pub(crate) transaction: Vec<RawTransaction>,
+    pub(crate) transaction: Vec<Value>,
 }
 
 #[derive(Debug, Deserialize)]
-struct ColumnValue<T> {
-    value: T,
+pub(crate) struct ColumnValue<T> {
+    pub(crate) value: T,
 }
 
 #[derive(Debug, Deserialize)]
@@ -175,7 +473,7 @@ where
     }
 }
 
-fn parse_date(raw: &str) -> Option<NaiveDate> {
+pub(crate) fn parse_date(raw: &str) -> Option<NaiveDate> {
     let prefix = raw.get(0..10)?;
     NaiveDate::parse_from_str(prefix, "%Y-%m-%d").ok()
 }
@@ -189,14 +487,14 @@ fn json_value_to_string(value: &Value) -> Option<String> {
     }
 }
 
-fn optional_string(value: &Option<ColumnValue<Value>>) -> Option<String> {
+pub(crate) fn optional_string(value: &Option<ColumnValue<Value>>) -> Option<String> {
     value
         .as_ref()
         .and_then(|c| json_value_to_string(&c.value))
         .filter(|s| !s.is_empty())
 }
 
-fn parse_i64_value(value: &Value) -> Option<i64> {
+pub(crate) fn parse_i64_value(value: &Value) -> Option<i64> {
     match value {
         Value::Number(n) => n.as_i64(),
         Value::String(s) => s.parse().ok(),
@@ -208,13 +506,30 @@ fn optional_i64(value: &Option<ColumnValue<Value>>) -> Option<i64> {
     value.as_ref().and_then(|c| parse_i64_value(&c.value))
 }
 
+fn transaction_from_value(value: &Value) -> Result<Transaction, FioError> {
+    let raw = RawTransaction::deserialize(value)
+        .map_err(|e| FioError::MalformedTransaction(e.to_string()))?;
+    Transaction::try_from(&raw)
+}
+
 impl TryFrom<&RawTransaction> for Transaction {
     type Error = FioError;
 
     fn try_from(raw: &RawTransaction) -> Result<Self, Self::Error> {
-        let transaction_id =
-            parse_i64_value(&raw.transaction_id.value).ok_or(FioError::InvalidResponse)?;
-        let date = parse_date(&raw.date.value).ok_or(FioError::InvalidResponse)?;
+        let transaction_id = parse_i64_value(&raw.transaction_id.value).ok_or_else(|| {
+            FioError::MalformedTransaction(format!(
+                "invalid transaction id {}",
+                raw.transaction_id.value
+            ))
+        })?;
+        let date = parse_date(&raw.date.value).ok_or_else(|| {
+            FioError::MalformedTransaction(format!("invalid date {:?}", raw.date.value))
+        })?;
+        let specification = optional_string(&raw.specification);
+        let original_amount = specification
+            .as_deref()
+            .and_then(|s| s.parse::<OriginalAmount>().ok())
+            .filter(|o| !o.currency.eq_ignore_ascii_case(&raw.currency.value));
 
         Ok(Transaction {
             transaction_id,
@@ -232,11 +547,12 @@ impl TryFrom<&RawTransaction> for Transaction {
             message_from_sender: optional_string(&raw.remittance_info),
             transaction_type: optional_string(&raw.transaction_type),
             executor: optional_string(&raw.executor),
-            specification: optional_string(&raw.specification),
+            specification,
             comment: optional_string(&raw.comment),
             bic: optional_string(&raw.bic),
             order_id: optional_i64(&raw.order_id),
             payer_reference: optional_string(&raw.payer_reference),
+            original_amount,
         })
     }
 }
@@ -254,6 +570,31 @@ pub fn parse_transactions(data: &str) -> Result<Vec<Transaction>, FioError> {
     Ok(txns)
 }
 
+pub fn parse_statement(data: &str) -> Result<Statement, FioError> {
+    let parsed: FioResponse = serde_json::from_str(data).map_err(|_| FioError::InvalidResponse)?;
+    let statement = parsed.statement()?;
+    debug!(
+        "Parsed statement with {} transactions",
+        statement.transactions.len()
+    );
+    Ok(statement)
+}
+
+/// Parse transactions, returning malformed entries alongside the valid ones.
+///
+/// Only the statement envelope has to be well formed; individual rows that
+/// fail to convert end up in [`LenientTransactions::rejected`].
+pub fn parse_transactions_lenient(data: &str) -> Result<LenientTransactions, FioError> {
+    let parsed: FioResponse = serde_json::from_str(data).map_err(|_| FioError::InvalidResponse)?;
+    let txns = parsed.transactions_lenient();
+    debug!(
+        "Parsed {} transactions, rejected {}",
+        txns.transactions.len(),
+        txns.rejected.len()
+    );
+    Ok(txns)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -331,4 +672,77 @@ mod tests {
         assert_eq!(txn.vs, Some(12345));
         assert_eq!(txn.order_id, Some(77));
     }
+
+    #[test]
+    fn lenient_parsing_collects_rejected_rows() {
+        let mut payload: Value = serde_json::from_str(&sample_payload()).unwrap();
+        let list = payload["accountStatement"]["transactionList"]["transaction"]
+            .as_array_mut()
+            .unwrap();
+        let mut bad = list[0].clone();
+        bad["column1"] = json!({ "value": "not a number" });
+        list.insert(0, bad);
+        let data = payload.to_string();
+
+        assert!(matches!(
+            parse_transactions(&data),
+            Err(FioError::InvalidResponse)
+        ));
+
+        let parsed = parse_transactions_lenient(&data).expect("envelope should parse");
+        assert_eq!(parsed.transactions.len(), 1);
+        assert_eq!(parsed.transactions[0].transaction_id, 10001);
+        assert_eq!(parsed.rejected.len(), 1);
+        assert_eq!(parsed.rejected[0].index, 0);
+        assert_eq!(parsed.rejected[0].raw["column1"]["value"], "not a number");
+        assert!(
+            matches!(&parsed.rejected[0].error, FioError::MalformedTransaction(msg) if msg.contains("not a number")),
+            "{}",
+            parsed.rejected[0].error
+        );
+    }
+
+    #[test]
+    fn parses_original_amount_from_specification() {
+        for (spec, amount, currency) in [
+            ("12.50 EUR", "12.50", "EUR"),
+            ("1 234,50 USD", "1234.50", "USD"),
+            ("1.234,50 EUR", "1234.50", "EUR"),
+            ("1,234.50 EUR", "1234.50", "EUR"),
+            ("1,234 EUR", "1234", "EUR"),
+            ("1.234.567 HUF", "1234567", "HUF"),
+            ("1,234,567.89 USD", "1234567.89", "USD"),
+            ("0.125 EUR", "0.125", "EUR"),
+            ("12,5 EUR", "12.5", "EUR"),
+            ("1234,567 EUR", "1234.567", "EUR"),
+            ("GBP 7", "7", "GBP"),
+        ] {
+            let parsed: OriginalAmount = spec.parse().unwrap();
+            assert_eq!(parsed.amount, Decimal::from_str(amount).unwrap());
+            assert_eq!(parsed.currency, currency);
+        }
+        assert!("12.50".parse::<OriginalAmount>().is_err());
+
+        let parse_with = |spec: &str| {
+            let mut payload: Value = serde_json::from_str(&sample_payload()).unwrap();
+            let txn = &mut payload["accountStatement"]["transactionList"]["transaction"][0];
+            txn["column1"] = json!({ "value": -312.40 });
+            txn["column18"] = json!({ "value": spec });
+            parse_transactions(&payload.to_string()).unwrap().remove(0)
+        };
+        let txn = parse_with("12.50 EUR");
+        assert_eq!(
+            txn.original_amount,
+            Some(OriginalAmount {
+                amount: Decimal::from_str("12.50").unwrap(),
+                currency: "EUR".to_string(),
+            })
+        );
+        assert_eq!(
+            txn.effective_rate(),
+            Some(Decimal::from_str("24.992").unwrap())
+        );
+        assert_eq!(parse_with("312.40 CZK").original_amount, None);
+        assert_eq!(parse_with("platba kartou").original_amount, None);
+    }
 }
@@ -0,0 +1,646 @@
+//! Payment orders for the import endpoint.
+//!
+//! A [`PaymentBatch`] holds domestic, euro (SEPA/T2) and foreign orders in a
+//! flat shape that maps one-to-one onto spreadsheet columns. It is checked
+//! with [`PaymentBatch::validate`], rendered as Fio's XML import document and
+//! submitted with [`Client::submit_payment_batch`](crate::Client::submit_payment_batch),
+//! which parses the reply into an [`ImportResponse`].
+
+use crate::error::FioError;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt::{self, Write};
+use thiserror::Error;
+
+/// Longest message for the recipient accepted by Fio.
+const MAX_MESSAGE_LEN: usize = 140;
+/// Longest internal comment accepted by Fio.
+const MAX_COMMENT_LEN: usize = 255;
+/// Longest remittance information line of euro and foreign orders.
+const MAX_REMITTANCE_LEN: usize = 35;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderKind {
+    /// Czech domestic payment in CZK
+    Domestic,
+    /// SEPA/T2 payment in EUR
+    Euro,
+    /// SWIFT payment in any currency
+    Foreign,
+}
+
+impl fmt::Display for OrderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v = match self {
+            OrderKind::Domestic => "domestic",
+            OrderKind::Euro => "euro",
+            OrderKind::Foreign => "foreign",
+        };
+        f.write_str(v)
+    }
+}
+
+/// Who pays the fees of a foreign payment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ChargeBearer {
+    Our,
+    #[default]
+    Sha,
+    Ben,
+}
+
+impl ChargeBearer {
+    fn code(self) -> &'static str {
+        match self {
+            ChargeBearer::Our => "470501",
+            ChargeBearer::Sha => "470502",
+            ChargeBearer::Ben => "470503",
+        }
+    }
+}
+
+/// One payment order; which fields are required depends on `kind`.
+///
+/// `account_to` is `number/bank` or `prefix-number/bank` for domestic orders
+/// and an IBAN (or, for foreign orders, any account number) otherwise.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PaymentOrder {
+    #[serde(rename = "type")]
+    pub kind: OrderKind,
+    pub account_from: String,
+    pub currency: String,
+    #[serde(deserialize_with = "deserialize_amount")]
+    pub amount: Decimal,
+    pub account_to: String,
+    pub date: NaiveDate,
+    #[serde(default)]
+    pub ks: Option<String>,
+    #[serde(default)]
+    pub vs: Option<String>,
+    #[serde(default)]
+    pub ss: Option<String>,
+    #[serde(default)]
+    pub bic: Option<String>,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub comment: Option<String>,
+    #[serde(default)]
+    pub payment_reason: Option<String>,
+    #[serde(default)]
+    pub payment_type: Option<String>,
+    #[serde(default)]
+    pub benef_name: Option<String>,
+    #[serde(default)]
+    pub benef_street: Option<String>,
+    #[serde(default)]
+    pub benef_city: Option<String>,
+    #[serde(default)]
+    pub benef_country: Option<String>,
+    #[serde(default)]
+    pub charges: Option<ChargeBearer>,
+}
+
+/// Read amounts from their text so `1500.50` keeps its scale even when the
+/// format (CSV, YAML) would otherwise hand it over as a float.
+fn deserialize_amount<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct AmountVisitor;
+
+    impl Visitor<'_> for AmountVisitor {
+        type Value = Decimal;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a decimal amount")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Decimal, E> {
+            v.trim().parse().map_err(E::custom)
+        }
+
+        fn visit_i64<E: de::Error>(self, v: i64) -> Result<Decimal, E> {
+            Ok(Decimal::from(v))
+        }
+
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<Decimal, E> {
+            Ok(Decimal::from(v))
+        }
+
+        fn visit_f64<E: de::Error>(self, v: f64) -> Result<Decimal, E> {
+            self.visit_str(&v.to_string())
+        }
+    }
+
+    deserializer.deserialize_str(AmountVisitor)
+}
+
+/// A problem found in one order of a batch.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("order {}: {field}: {reason}", .index + 1)]
+pub struct OrderIssue {
+    /// Zero-based position of the order in the batch.
+    pub index: usize,
+    pub field: &'static str,
+    pub reason: String,
+}
+
+/// Ordered collection of payment orders submitted as one import.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct PaymentBatch {
+    pub orders: Vec<PaymentOrder>,
+}
+
+impl PaymentBatch {
+    pub fn new(orders: impl IntoIterator<Item = PaymentOrder>) -> Self {
+        Self {
+            orders: orders.into_iter().collect(),
+        }
+    }
+
+    /// Check every order, reporting all problems rather than the first one.
+    ///
+    /// Due dates before `today` are rejected, as Fio would refuse them.
+    pub fn validate(&self, today: NaiveDate) -> Result<(), Vec<OrderIssue>> {
+        let mut issues = Vec::new();
+        if self.orders.is_empty() {
+            issues.push(OrderIssue {
+                index: 0,
+                field: "orders",
+                reason: "batch contains no orders".to_string(),
+            });
+        }
+        for (index, order) in self.orders.iter().enumerate() {
+            let mut issue = |field, reason: &str| {
+                issues.push(OrderIssue {
+                    index,
+                    field,
+                    reason: reason.to_string(),
+                })
+            };
+            order.check(today, &mut issue);
+        }
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+
+    /// Sum of amounts per currency, in order of first appearance.
+    pub fn totals(&self) -> Vec<(String, Decimal)> {
+        let mut totals: Vec<(String, Decimal)> = Vec::new();
+        for order in &self.orders {
+            match totals.iter_mut().find(|(cur, _)| *cur == order.currency) {
+                Some((_, sum)) => *sum += order.amount,
+                None => totals.push((order.currency.clone(), order.amount)),
+            }
+        }
+        totals
+    }
+
+    /// Render the batch as an XML document for [`ImportFmt::Xml`](crate::ImportFmt::Xml).
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<Import xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:noNamespaceSchemaLocation="http://www.fio.cz/schema/importIB.xsd"><Orders>"#,
+        );
+        for order in &self.orders {
+            order.write_xml(&mut xml);
+        }
+        xml.push_str("</Orders></Import>");
+        xml
+    }
+}
+
+impl PaymentOrder {
+    fn check(&self, today: NaiveDate, issue: &mut impl FnMut(&'static str, &str)) {
+        if !is_czech_account(&self.account_from) {
+            issue("account_from", "not a valid account number");
+        }
+        if self.amount <= Decimal::ZERO {
+            issue("amount", "must be positive");
+        } else if self.amount.normalize().scale() > 2 {
+            issue("amount", "has more than two decimal places");
+        }
+        if self.date < today {
+            issue("date", "lies in the past");
+        }
+        if self.currency.len() != 3 || !self.currency.bytes().all(|b| b.is_ascii_uppercase()) {
+            issue("currency", "must be a three-letter ISO code");
+        }
+        check_symbol(&self.ks, 4, "ks", issue);
+        check_symbol(&self.vs, 10, "vs", issue);
+        check_symbol(&self.ss, 10, "ss", issue);
+        check_len(&self.comment, MAX_COMMENT_LEN, "comment", issue);
+        if let Some(bic) = &self.bic
+            && !is_bic(bic)
+        {
+            issue("bic", "not a valid BIC");
+        }
+
+        match self.kind {
+            OrderKind::Domestic => {
+                match self.account_to.split_once('/') {
+                    Some((account, bank)) if is_czech_account(account) && is_bank_code(bank) => {}
+                    Some(_) => issue("account_to", "not a valid account number/bank code"),
+                    None => issue("account_to", "missing /bank code"),
+                }
+                if self.currency != "CZK" {
+                    issue("currency", "domestic orders must be in CZK");
+                }
+                check_len(&self.message, MAX_MESSAGE_LEN, "message", issue);
+            }
+            OrderKind::Euro | OrderKind::Foreign => {
+                let looks_like_iban = self.account_to.len() > 4
+                    && self.account_to.as_bytes()[..2]
+                        .iter()
+                        .all(u8::is_ascii_alphabetic);
+                if (self.kind == OrderKind::Euro || looks_like_iban) && !is_iban(&self.account_to) {
+                    issue("account_to", "not a valid IBAN");
+                }
+                if self.kind == OrderKind::Euro && self.currency != "EUR" {
+                    issue("currency", "euro orders must be in EUR");
+                }
+                if self.kind == OrderKind::Foreign && self.bic.is_none() {
+                    issue("bic", "required for foreign orders");
+                }
+                if self.benef_name.as_deref().is_none_or(str::is_empty) {
+                    issue("benef_name", "required for euro and foreign orders");
+                }
+                if let Some(country) = &self.benef_country
+                    && (country.len() != 2 || !country.bytes().all(|b| b.is_ascii_uppercase()))
+                {
+                    issue("benef_country", "must be a two-letter ISO code");
+                }
+                if self.message.as_ref().is_some_and(|m| {
+                    m.chars().count() > MAX_REMITTANCE_LEN * self.remittance_lines()
+                }) {
+                    issue("message", "too long");
+                }
+            }
+        }
+    }
+
+    fn remittance_lines(&self) -> usize {
+        match self.kind {
+            OrderKind::Foreign => 4,
+            _ => 3,
+        }
+    }
+
+    fn write_xml(&self, xml: &mut String) {
+        let tag = match self.kind {
+            OrderKind::Domestic => "DomesticTransaction",
+            OrderKind::Euro => "T2Transaction",
+            OrderKind::Foreign => "ForeignTransaction",
+        };
+        let _ = write!(xml, "<{tag}>");
+        element(xml, "accountFrom", Some(&self.account_from));
+        element(xml, "currency", Some(&self.currency));
+        element(xml, "amount", Some(&self.amount.to_string()));
+        match self.kind {
+            OrderKind::Domestic => {
+                let (account, bank) = self
+                    .account_to
+                    .split_once('/')
+                    .unwrap_or((&self.account_to, ""));
+                element(xml, "accountTo", Some(account));
+                element(xml, "bankCode", Some(bank));
+                element(xml, "ks", self.ks.as_deref());
+                element(xml, "vs", self.vs.as_deref());
+                element(xml, "ss", self.ss.as_deref());
+                element(xml, "date", Some(&self.date.to_string()));
+                element(xml, "messageForRecipient", self.message.as_deref());
+                element(xml, "comment", self.comment.as_deref());
+                element(xml, "paymentReason", self.payment_reason.as_deref());
+                element(xml, "paymentType", self.payment_type.as_deref());
+            }
+            OrderKind::Euro | OrderKind::Foreign => {
+                element(xml, "accountTo", Some(&self.account_to));
+                if self.kind == OrderKind::Euro {
+                    element(xml, "ks", self.ks.as_deref());
+                    element(xml, "vs", self.vs.as_deref());
+                    element(xml, "ss", self.ss.as_deref());
+                }
+                element(xml, "bic", self.bic.as_deref());
+                element(xml, "date", Some(&self.date.to_string()));
+                element(xml, "comment", self.comment.as_deref());
+                element(xml, "benefName", self.benef_name.as_deref());
+                element(xml, "benefStreet", self.benef_street.as_deref());
+                element(xml, "benefCity", self.benef_city.as_deref());
+                element(xml, "benefCountry", self.benef_country.as_deref());
+                let message: Vec<char> = self.message.as_deref().unwrap_or("").chars().collect();
+                for (i, line) in message.chunks(MAX_REMITTANCE_LEN).enumerate() {
+                    let line: String = line.iter().collect();
+                    element(xml, &format!("remittanceInfo{}", i + 1), Some(&line));
+                }
+                if self.kind == OrderKind::Foreign {
+                    let charges = self.charges.unwrap_or_default();
+                    element(xml, "detailsOfCharges", Some(charges.code()));
+                }
+                element(xml, "paymentReason", self.payment_reason.as_deref());
+                if self.kind == OrderKind::Euro {
+                    element(xml, "paymentType", self.payment_type.as_deref());
+                }
+            }
+        }
+        let _ = write!(xml, "</{tag}>");
+    }
+}
+
+fn element(xml: &mut String, name: &str, value: Option<&str>) {
+    let Some(value) = value.filter(|v| !v.is_empty()) else {
+        return;
+    };
+    let _ = write!(xml, "<{name}>");
+    for c in value.chars() {
+        match c {
+            '&' => xml.push_str("&amp;"),
+            '<' => xml.push_str("&lt;"),
+            '>' => xml.push_str("&gt;"),
+            '"' => xml.push_str("&quot;"),
+            '\'' => xml.push_str("&apos;"),
+            c => xml.push(c),
+        }
+    }
+    let _ = write!(xml, "</{name}>");
+}
+
+fn check_symbol(
+    value: &Option<String>,
+    max_digits: usize,
+    field: &'static str,
+    issue: &mut impl FnMut(&'static str, &str),
+) {
+    if let Some(value) = value
+        && (value.is_empty()
+            || value.len() > max_digits
+            || !value.bytes().all(|b| b.is_ascii_digit()))
+    {
+        issue(field, &format!("must be 1 to {max_digits} digits"));
+    }
+}
+
+fn check_len(
+    value: &Option<String>,
+    max: usize,
+    field: &'static str,
+    issue: &mut impl FnMut(&'static str, &str),
+) {
+    if value.as_ref().is_some_and(|v| v.chars().count() > max) {
+        issue(field, &format!("longer than {max} characters"));
+    }
+}
+
+/// Check a Czech account number `[prefix-]number` including its mod 11 checksums.
+pub fn is_czech_account(account: &str) -> bool {
+    let (prefix, number) = account.split_once('-').unwrap_or(("", account));
+    let weighted = |digits: &str, len: usize| -> Option<u32> {
+        if digits.len() > len || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        const WEIGHTS: [u32; 10] = [6, 3, 7, 9, 10, 5, 8, 4, 2, 1];
+        let padding = 10 - digits.len();
+        Some(
+            digits
+                .bytes()
+                .zip(&WEIGHTS[padding..])
+                .map(|(d, w)| u32::from(d - b'0') * w)
+                .sum(),
+        )
+    };
+    number.len() >= 2
+        && weighted(prefix, 6).is_some_and(|sum| sum % 11 == 0)
+        && weighted(number, 10).is_some_and(|sum| sum % 11 == 0 && sum > 0)
+}
+
+/// Check an IBAN's structure and mod 97 checksum; spaces are not allowed.
+pub fn is_iban(iban: &str) -> bool {
+    let bytes = iban.as_bytes();
+    if !(15..=34).contains(&bytes.len())
+        || !bytes[..2].iter().all(u8::is_ascii_uppercase)
+        || !bytes[2..4].iter().all(u8::is_ascii_digit)
+        || !bytes
+            .iter()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+    {
+        return false;
+    }
+    let remainder = bytes[4..]
+        .iter()
+        .chain(&bytes[..4])
+        .fold(0u32, |acc, &b| match b {
+            b'0'..=b'9' => (acc * 10 + u32::from(b - b'0')) % 97,
+            _ => (acc * 100 + u32::from(b - b'A') + 10) % 97,
+        });
+    remainder == 1
+}
+
+fn is_bic(bic: &str) -> bool {
+    let bytes = bic.as_bytes();
+    (bytes.len() == 8 || bytes.len() == 11)
+        && bytes[..6].iter().all(u8::is_ascii_uppercase)
+        && bytes[6..]
+            .iter()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+}
+
+fn is_bank_code(code: &str) -> bool {
+    code.len() == 4 && code.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Outcome of an import as reported by Fio.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImportResponse {
+    /// 0 when the whole batch was accepted.
+    pub error_code: i32,
+    /// Identifier of the created instruction, if any.
+    pub id_instruction: Option<i64>,
+    /// `ok`, `warning`, `error` or `fatal`.
+    pub status: String,
+    pub orders: Vec<OrderResult>,
+}
+
+/// Messages for one order, identified by its 1-based position in the batch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OrderResult {
+    pub id: usize,
+    pub messages: Vec<OrderMessage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OrderMessage {
+    pub status: String,
+    pub error_code: i32,
+    pub text: String,
+}
+
+impl ImportResponse {
+    /// Parse the `responseImport` document returned by the import endpoint.
+    pub fn parse(xml: &str) -> Result<Self, FioError> {
+        let doc = roxmltree::Document::parse(xml).map_err(|_| FioError::InvalidResponse)?;
+        let root = doc.root_element();
+        if !root.has_tag_name("responseImport") {
+            return Err(FioError::InvalidResponse);
+        }
+        let child = |node: roxmltree::Node<'_, '_>, name: &str| {
+            node.children()
+                .find(|n| n.has_tag_name(name))
+                .and_then(|n| n.text())
+                .map(str::trim)
+                .map(str::to_string)
+        };
+        let result = root
+            .children()
+            .find(|n| n.has_tag_name("result"))
+            .ok_or(FioError::InvalidResponse)?;
+        let error_code = child(result, "errorCode")
+            .and_then(|c| c.parse().ok())
+            .ok_or(FioError::InvalidResponse)?;
+        let id_instruction = child(result, "idInstruction").and_then(|id| id.parse().ok());
+        let status = child(result, "status").ok_or(FioError::InvalidResponse)?;
+
+        let mut orders = Vec::new();
+        for detail in root.descendants().filter(|n| n.has_tag_name("detail")) {
+            let id = detail
+                .attribute("id")
+                .and_then(|id| id.parse().ok())
+                .ok_or(FioError::InvalidResponse)?;
+            let messages = detail
+                .descendants()
+                .filter(|n| n.has_tag_name("message"))
+                .map(|m| OrderMessage {
+                    status: m.attribute("status").unwrap_or_default().to_string(),
+                    error_code: m
+                        .attribute("errorCode")
+                        .and_then(|c| c.parse().ok())
+                        .unwrap_or_default(),
+                    text: m.text().unwrap_or_default().trim().to_string(),
+                })
+                .collect();
+            orders.push(OrderResult { id, messages });
+        }
+        Ok(Self {
+            error_code,
+            id_instruction,
+            status,
+            orders,
+        })
+    }
+
+    /// Whether Fio accepted the batch, possibly with warnings.
+    pub fn is_accepted(&self) -> bool {
+        self.error_code == 0 && matches!(self.status.as_str(), "ok" | "warning")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, day).unwrap()
+    }
+
+    fn domestic() -> PaymentOrder {
+        PaymentOrder {
+            kind: OrderKind::Domestic,
+            account_from: "2000000018".to_string(),
+            currency: "CZK".to_string(),
+            amount: Decimal::new(150050, 2),
+            account_to: "19-2000145399/0800".to_string(),
+            date: date(10),
+            ks: Some("0558".to_string()),
+            vs: Some("2025001".to_string()),
+            ss: None,
+            bic: None,
+            message: Some("Invoice <2025001>".to_string()),
+            comment: None,
+            payment_reason: None,
+            payment_type: Some("431001".to_string()),
+            benef_name: None,
+            benef_street: None,
+            benef_city: None,
+            benef_country: None,
+            charges: None,
+        }
+    }
+
+    #[test]
+    fn validates_checksums_and_fields() {
+        assert!(is_czech_account("19-2000145399"));
+        assert!(!is_czech_account("19-2000145398"));
+        assert!(is_iban("CZ6508000000192000145399"));
+        assert!(!is_iban("CZ6608000000192000145399"));
+
+        let mut euro = domestic();
+        euro.kind = OrderKind::Euro;
+        euro.currency = "EUR".to_string();
+        euro.account_to = "DE89370400440532013000".to_string();
+        euro.benef_name = Some("Muster GmbH".to_string());
+        let batch = PaymentBatch::new([domestic(), euro.clone()]);
+        assert_eq!(batch.validate(date(10)), Ok(()));
+
+        let mut bad = domestic();
+        bad.amount = Decimal::new(1, 3);
+        bad.vs = Some("12a".to_string());
+        bad.account_to = "2000145398/0800".to_string();
+        let issues = PaymentBatch::new([domestic(), bad, euro])
+            .validate(date(11))
+            .unwrap_err();
+        let fields: Vec<_> = issues.iter().map(|i| (i.index, i.field)).collect();
+        assert_eq!(
+            fields,
+            [
+                (0, "date"),
+                (1, "amount"),
+                (1, "date"),
+                (1, "vs"),
+                (1, "account_to"),
+                (2, "date"),
+            ]
+        );
+        assert_eq!(
+            issues[1].to_string(),
+            "order 2: amount: has more than two decimal places"
+        );
+    }
+
+    #[test]
+    fn renders_import_xml() {
+        let xml = PaymentBatch::new([domestic()]).to_xml();
+        assert!(xml.contains(
+            "<DomesticTransaction><accountFrom>2000000018</accountFrom><currency>CZK</currency>\
+             <amount>1500.50</amount><accountTo>19-2000145399</accountTo><bankCode>0800</bankCode>\
+             <ks>0558</ks><vs>2025001</vs><date>2025-03-10</date>\
+             <messageForRecipient>Invoice &lt;2025001&gt;</messageForRecipient>\
+             <paymentType>431001</paymentType></DomesticTransaction>"
+        ));
+        assert!(roxmltree::Document::parse(&xml).is_ok());
+    }
+
+    #[test]
+    fn parses_import_response() {
+        let response = ImportResponse::parse(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<responseImport><result><errorCode>11</errorCode><idInstruction></idInstruction><status>error</status></result>
+<ordersDetails><detail id="1"><messages><message status="error" errorCode="1018">Chybné číslo účtu příjemce.</message></messages></detail></ordersDetails></responseImport>"#,
+        )
+        .unwrap();
+        assert!(!response.is_accepted());
+        assert_eq!(response.id_instruction, None);
+        assert_eq!(response.orders[0].id, 1);
+        assert_eq!(response.orders[0].messages[0].error_code, 1018);
+        assert!(matches!(
+            ImportResponse::parse("<html/>"),
+            Err(FioError::InvalidResponse)
+        ));
+    }
+}
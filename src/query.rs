@@ -0,0 +1,462 @@
+//! Composable transaction filters and a small query language.
+//!
+//! Filters are built in code or parsed from text:
+//!
+//! ```
+//! use fioapi::query::Filter;
+//!
+//! let filter: Filter = "vs=12345 amount>1000 type:card".parse().unwrap();
+//! let debits = Filter::parse("amount<0 or type:fee").unwrap();
+//! # let _ = (filter, debits);
+//! ```
+//!
+//! Terms are `field op value` with `=`/`:` for equality, `<`, `<=`, `>`, `>=`
+//! for dates and amounts, `from..to` ranges (either side may be empty) and
+//! `~` for a regular expression on the message (feature `regex`). Terms are
+//! combined with AND, `or` separates alternatives and `!` negates a term.
+//! A bare word searches the message texts. Values with spaces are quoted:
+//! `message:"rent march"`.
+//!
+//! | field | value |
+//! |---|---|
+//! | `date` | `YYYY-MM-DD` |
+//! | `amount` | decimal, negative for debits |
+//! | `sign` | `credit`/`+` or `debit`/`-` |
+//! | `currency` | ISO code |
+//! | `vs`, `ks`, `ss` | symbol |
+//! | `account` | counterparty `number` or `number/bank` |
+//! | `name` | text in the counterparty account name |
+//! | `message` | text in the message, identification or comment |
+//! | `type` | [`TransactionKind`], e.g. `card`, `incoming`, `fee` |
+
+use crate::error::FioError;
+use crate::models::{Transaction, TransactionKind};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::ops::{Bound, RangeBounds};
+use std::str::FromStr;
+
+/// Direction of the money flow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sign {
+    Credit,
+    Debit,
+}
+
+/// Predicate over [`Transaction`]s.
+#[derive(Debug, Clone)]
+pub enum Filter {
+    /// Matches every transaction.
+    Any,
+    Date(Bound<NaiveDate>, Bound<NaiveDate>),
+    Amount(Bound<Decimal>, Bound<Decimal>),
+    Sign(Sign),
+    Currency(String),
+    Vs(i64),
+    Ks(i64),
+    Ss(i64),
+    /// Counterparty `number` or `number/bank`, compared without case.
+    Counterparty(String),
+    /// Case-insensitive substring of the message, identification or comment.
+    MessageContains(String),
+    /// Case-insensitive substring of the counterparty account name.
+    NameContains(String),
+    #[cfg(feature = "regex")]
+    MessageRegex(regex::Regex),
+    /// Regular expression on the counterparty account name.
+    #[cfg(feature = "regex")]
+    NameRegex(regex::Regex),
+    Kind(TransactionKind),
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    /// Transactions booked within `range`.
+    pub fn date(range: impl RangeBounds<NaiveDate>) -> Self {
+        Filter::Date(range.start_bound().cloned(), range.end_bound().cloned())
+    }
+
+    /// Transactions whose signed amount lies within `range`.
+    pub fn amount(range: impl RangeBounds<Decimal>) -> Self {
+        Filter::Amount(range.start_bound().cloned(), range.end_bound().cloned())
+    }
+
+    #[cfg(feature = "regex")]
+    pub fn message_regex(pattern: &str) -> Result<Self, FioError> {
+        regex::Regex::new(pattern)
+            .map(Filter::MessageRegex)
+            .map_err(|e| FioError::InvalidQuery(e.to_string()))
+    }
+
+    #[cfg(feature = "regex")]
+    pub fn name_regex(pattern: &str) -> Result<Self, FioError> {
+        regex::Regex::new(pattern)
+            .map(Filter::NameRegex)
+            .map_err(|e| FioError::InvalidQuery(e.to_string()))
+    }
+
+    /// Both filters must match.
+    pub fn and(self, other: Filter) -> Self {
+        match self {
+            Filter::Any => other,
+            Filter::And(mut all) => {
+                all.push(other);
+                Filter::And(all)
+            }
+            first => Filter::And(vec![first, other]),
+        }
+    }
+
+    /// Either filter must match.
+    pub fn or(self, other: Filter) -> Self {
+        match self {
+            Filter::Or(mut any) => {
+                any.push(other);
+                Filter::Or(any)
+            }
+            first => Filter::Or(vec![first, other]),
+        }
+    }
+
+    pub fn matches(&self, txn: &Transaction) -> bool {
+        match self {
+            Filter::Any => true,
+            Filter::Date(from, to) => (*from, *to).contains(&txn.date),
+            Filter::Amount(min, max) => (*min, *max).contains(&txn.amount),
+            Filter::Sign(Sign::Credit) => txn.amount > Decimal::ZERO,
+            Filter::Sign(Sign::Debit) => txn.amount < Decimal::ZERO,
+            Filter::Currency(currency) => txn.currency.eq_ignore_ascii_case(currency),
+            Filter::Vs(vs) => txn.vs == Some(*vs),
+            Filter::Ks(ks) => txn.ks == Some(*ks),
+            Filter::Ss(ss) => txn.ss == Some(*ss),
+            Filter::Counterparty(wanted) => {
+                let Some(account) = &txn.account_id else {
+                    return false;
+                };
+                match wanted.split_once('/') {
+                    Some((number, bank)) => {
+                        account.eq_ignore_ascii_case(number) && txn.bank_id.as_deref() == Some(bank)
+                    }
+                    None => account.eq_ignore_ascii_case(wanted),
+                }
+            }
+            Filter::MessageContains(needle) => {
+                let needle = needle.to_lowercase();
+                messages(txn).any(|m| m.to_lowercase().contains(&needle))
+            }
+            Filter::NameContains(needle) => txn
+                .account_name
+                .as_deref()
+                .is_some_and(|n| n.to_lowercase().contains(&needle.to_lowercase())),
+            #[cfg(feature = "regex")]
+            Filter::MessageRegex(re) => messages(txn).any(|m| re.is_match(m)),
+            #[cfg(feature = "regex")]
+            Filter::NameRegex(re) => txn.account_name.as_deref().is_some_and(|n| re.is_match(n)),
+            Filter::Kind(kind) => txn.kind() == *kind,
+            Filter::And(all) => all.iter().all(|f| f.matches(txn)),
+            Filter::Or(any) => any.iter().any(|f| f.matches(txn)),
+            Filter::Not(inner) => !inner.matches(txn),
+        }
+    }
+
+    /// Parse the query language described in the [module docs](self).
+    ///
+    /// An empty query yields [`Filter::Any`].
+    pub fn parse(query: &str) -> Result<Self, FioError> {
+        let mut alternatives = Vec::new();
+        let mut current = Filter::Any;
+        for token in tokenize(query)? {
+            if token.eq_ignore_ascii_case("or") {
+                alternatives.push(std::mem::replace(&mut current, Filter::Any));
+                continue;
+            }
+            current = current.and(parse_term(&token)?);
+        }
+        if alternatives.is_empty() {
+            return Ok(current);
+        }
+        alternatives.push(current);
+        if alternatives.iter().any(|f| matches!(f, Filter::Any)) {
+            return Err(FioError::InvalidQuery(
+                "`or` needs a term on both sides".into(),
+            ));
+        }
+        Ok(Filter::Or(alternatives))
+    }
+}
+
+impl std::ops::Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Filter {
+        Filter::Not(Box::new(self))
+    }
+}
+
+impl FromStr for Filter {
+    type Err = FioError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Filter::parse(s)
+    }
+}
+
+fn messages(txn: &Transaction) -> impl Iterator<Item = &str> {
+    [
+        &txn.message_from_sender,
+        &txn.user_identification,
+        &txn.comment,
+    ]
+    .into_iter()
+    .filter_map(|m| m.as_deref())
+}
+
+/// Split on whitespace outside double quotes; quotes are removed.
+fn tokenize(query: &str) -> Result<Vec<String>, FioError> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if quoted {
+        return Err(FioError::InvalidQuery("unterminated quote".into()));
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Match,
+}
+
+fn parse_term(token: &str) -> Result<Filter, FioError> {
+    if let Some(rest) = token.strip_prefix('!') {
+        return Ok(!parse_term(rest)?);
+    }
+    let field_len = token
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(token.len());
+    let (field, rest) = token.split_at(field_len);
+    let (op, value) = [
+        ("<=", Op::Le),
+        (">=", Op::Ge),
+        ("=", Op::Eq),
+        (":", Op::Eq),
+        ("<", Op::Lt),
+        (">", Op::Gt),
+        ("~", Op::Match),
+    ]
+    .into_iter()
+    .find_map(|(symbol, op)| rest.strip_prefix(symbol).map(|value| (op, value)))
+    .map_or((None, rest), |(op, value)| (Some(op), value));
+    let Some(op) = op.filter(|_| !field.is_empty()) else {
+        // Not a `field op value` term: search the messages.
+        return Ok(Filter::MessageContains(token.to_string()));
+    };
+    let invalid = |what: &str| FioError::InvalidQuery(format!("{what} in `{token}`"));
+    let eq_only = |filter: Filter| match op {
+        Op::Eq => Ok(filter),
+        _ => Err(invalid("only `=` or `:` allowed")),
+    };
+    let symbol = |value: &str| value.parse::<i64>().map_err(|_| invalid("invalid symbol"));
+
+    match field.to_ascii_lowercase().as_str() {
+        "date" | "amount" if op == Op::Match => {
+            Err(invalid("`~` only applies to messages and names"))
+        }
+        "date" => ordered(op, value, |v| {
+            NaiveDate::parse_from_str(v, "%Y-%m-%d").map_err(|_| invalid("invalid date"))
+        })
+        .map(|(from, to)| Filter::Date(from, to)),
+        "amount" => ordered(op, value, |v| {
+            v.parse().map_err(|_| invalid("invalid amount"))
+        })
+        .map(|(min, max)| Filter::Amount(min, max)),
+        "sign" => eq_only(Filter::Sign(match value {
+            "credit" | "+" | "in" => Sign::Credit,
+            "debit" | "-" | "out" => Sign::Debit,
+            _ => return Err(invalid("sign must be credit or debit")),
+        })),
+        "currency" | "cur" => eq_only(Filter::Currency(value.to_string())),
+        "vs" => eq_only(Filter::Vs(symbol(value)?)),
+        "ks" => eq_only(Filter::Ks(symbol(value)?)),
+        "ss" => eq_only(Filter::Ss(symbol(value)?)),
+        "account" | "counterparty" => eq_only(Filter::Counterparty(value.to_string())),
+        "message" | "msg" => match op {
+            Op::Eq => Ok(Filter::MessageContains(value.to_string())),
+            #[cfg(feature = "regex")]
+            Op::Match => Filter::message_regex(value),
+            #[cfg(not(feature = "regex"))]
+            Op::Match => Err(invalid("regular expressions need the `regex` feature")),
+            _ => Err(invalid("use `:` or `~` for messages")),
+        },
+        "name" => match op {
+            Op::Eq => Ok(Filter::NameContains(value.to_string())),
+            #[cfg(feature = "regex")]
+            Op::Match => Filter::name_regex(value),
+            #[cfg(not(feature = "regex"))]
+            Op::Match => Err(invalid("regular expressions need the `regex` feature")),
+            _ => Err(invalid("use `:` or `~` for names")),
+        },
+        "type" | "kind" => eq_only(Filter::Kind(
+            value
+                .parse()
+                .map_err(|_| invalid("unknown transaction type"))?,
+        )),
+        _ => Err(invalid("unknown field")),
+    }
+}
+
+/// Bounds for an ordered field from a comparison or an `a..b` range.
+fn ordered<T: Copy, E>(
+    op: Op,
+    value: &str,
+    parse: impl Fn(&str) -> Result<T, E>,
+) -> Result<(Bound<T>, Bound<T>), E> {
+    if op == Op::Eq
+        && let Some((from, to)) = value.split_once("..")
+    {
+        let bound = |v: &str| -> Result<Bound<T>, E> {
+            Ok(if v.is_empty() {
+                Bound::Unbounded
+            } else {
+                Bound::Included(parse(v)?)
+            })
+        };
+        return Ok((bound(from)?, bound(to)?));
+    }
+    let v = parse(value)?;
+    Ok(match op {
+        Op::Eq | Op::Match => (Bound::Included(v), Bound::Included(v)),
+        Op::Lt => (Bound::Unbounded, Bound::Excluded(v)),
+        Op::Le => (Bound::Unbounded, Bound::Included(v)),
+        Op::Gt => (Bound::Excluded(v), Bound::Unbounded),
+        Op::Ge => (Bound::Included(v), Bound::Unbounded),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::txn;
+    use serde_json::json;
+
+    fn ids(filter: &Filter, txns: &[Transaction]) -> Vec<i64> {
+        txns.iter()
+            .filter(|t| filter.matches(t))
+            .map(|t| t.transaction_id)
+            .collect()
+    }
+
+    #[test]
+    fn parses_and_applies_queries() {
+        let txns = [
+            txn(
+                1,
+                "2025-03-03",
+                "1500",
+                json!({"vs": 12345, "account_id": "19-2000145399", "bank_id": "0800",
+                       "account_name": "Novák Jan",
+                       "transaction_type": "Bezhotovostní příjem", "message_from_sender": "Faktura 12345"}),
+            ),
+            txn(
+                2,
+                "2025-03-05",
+                "-320",
+                json!({"transaction_type": "Platba kartou", "user_identification": "Nákup: LIDL Praha"}),
+            ),
+            txn(
+                3,
+                "2025-03-20",
+                "-49",
+                json!({"transaction_type": "Poplatek - platební karta"}),
+            ),
+        ];
+        let cases = [
+            ("", vec![1, 2, 3]),
+            ("vs=12345 amount>1000", vec![1]),
+            ("type:card", vec![2]),
+            ("sign:debit !type:fee", vec![2]),
+            ("amount:-400..0", vec![2, 3]),
+            ("date<2025-03-05", vec![1]),
+            (
+                "date:2025-03-05.. or account=19-2000145399/0800",
+                vec![1, 2, 3],
+            ),
+            ("account:19-2000145399/0100", vec![]),
+            ("lidl", vec![2]),
+            (r#"message:"nákup: lidl""#, vec![2]),
+            ("name:novák", vec![1]),
+            #[cfg(feature = "regex")]
+            ("message~^Faktura\\s\\d+$", vec![1]),
+        ];
+        for (query, expected) in cases {
+            let filter = Filter::parse(query).unwrap();
+            assert_eq!(ids(&filter, &txns), expected, "{query}");
+        }
+
+        let built = Filter::date(NaiveDate::from_ymd_opt(2025, 3, 4).unwrap()..)
+            .and(Filter::Sign(Sign::Debit))
+            .and(!Filter::Kind(TransactionKind::Fee));
+        assert_eq!(ids(&built, &txns), [2]);
+
+        for bad in [
+            "vs>1",
+            "amount>abc",
+            "type:cheque",
+            "foo=1",
+            "or vs=1",
+            "msg:\"open",
+        ] {
+            assert!(
+                matches!(Filter::parse(bad), Err(FioError::InvalidQuery(_))),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn classifies_transaction_types() {
+        let cases = [
+            (
+                "Příjem převodem uvnitř banky",
+                TransactionKind::IncomingTransfer,
+            ),
+            ("Bezhotovostní platba", TransactionKind::OutgoingTransfer),
+            ("Platba kartou", TransactionKind::CardPayment),
+            ("Výběr z bankomatu", TransactionKind::CashWithdrawal),
+            ("Vklad pokladnou", TransactionKind::CashDeposit),
+            ("Inkaso", TransactionKind::DirectDebit),
+            ("Připsaný úrok", TransactionKind::Interest),
+            ("Odvod daně z úroků", TransactionKind::Tax),
+            ("Poplatek - platební karta", TransactionKind::Fee),
+            (
+                "Okamžitá příchozí platba",
+                TransactionKind::IncomingTransfer,
+            ),
+        ];
+        for (value, kind) in cases {
+            assert_eq!(TransactionKind::from_type(value), kind, "{value}");
+            assert_eq!(kind.to_string().parse::<TransactionKind>().unwrap(), kind);
+        }
+    }
+}
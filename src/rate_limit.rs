@@ -0,0 +1,69 @@
+//! Client-side pacing of requests.
+//!
+//! Fio rejects a request with 409 when the same token was used less than
+//! 30 seconds earlier. A [`RateLimiter`] attached with
+//! [`Client::with_rate_limit`](crate::Client::with_rate_limit) delays
+//! requests instead, and is shared by all clones of that client.
+
+use log::debug;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// Minimum spacing between requests with one token required by Fio.
+pub const FIO_REQUEST_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Ensures a minimum interval between consecutive requests.
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,
+    last: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: Mutex::new(None),
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Wait until a request may be sent and reserve the slot for it.
+    pub async fn acquire(&self) {
+        let mut last = self.last.lock().await;
+        if let Some(previous) = *last {
+            let ready = previous + self.interval;
+            if ready > Instant::now() {
+                debug!(
+                    "Rate limit: waiting {:?}",
+                    ready.saturating_duration_since(Instant::now())
+                );
+                tokio::time::sleep_until(ready).await;
+            }
+        }
+        *last = Some(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn spaces_requests() {
+        let limiter = RateLimiter::new(FIO_REQUEST_INTERVAL);
+        let start = Instant::now();
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), FIO_REQUEST_INTERVAL);
+        tokio::time::advance(Duration::from_secs(45)).await;
+        let resumed = Instant::now();
+        limiter.acquire().await;
+        assert_eq!(resumed.elapsed(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,367 @@
+//! Matching of incoming payments to expected receivables.
+//!
+//! Payments are paired with receivables by variable symbol and currency.
+//! Several receivables may share a VS (e.g. monthly invoices of one
+//! customer); payments then settle them in due-date order. Each payment is
+//! assigned whole, and a payment arriving for an already settled receivable
+//! is reported as a duplicate.
+
+use crate::models::Transaction;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// An invoice or other amount expected to arrive.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Receivable {
+    pub id: String,
+    pub vs: i64,
+    pub amount: Decimal,
+    pub currency: String,
+    pub due_date: NaiveDate,
+}
+
+/// How strictly paid amounts are compared with expected ones.
+///
+/// The effective tolerance is the larger of the absolute and the relative
+/// one, e.g. `absolute_tolerance = 1` absorbs rounding to whole crowns.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Rules {
+    pub absolute_tolerance: Decimal,
+    /// Fraction of the expected amount, e.g. `0.01` for 1 %.
+    pub relative_tolerance: Decimal,
+    /// Match payments without VS to the only open receivable with that amount.
+    pub match_amount_without_vs: bool,
+}
+
+impl Rules {
+    fn tolerance(&self, expected: Decimal) -> Decimal {
+        self.absolute_tolerance
+            .max(expected.abs() * self.relative_tolerance)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum MatchStatus {
+    /// Paid within tolerance by one or more payments.
+    Matched,
+    PartiallyPaid {
+        missing: Decimal,
+    },
+    Overpaid {
+        excess: Decimal,
+    },
+    /// Settled, but further payments arrived afterwards.
+    DuplicatePayment {
+        duplicates: Vec<i64>,
+    },
+    Unpaid,
+}
+
+/// Outcome for one receivable.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReceivableMatch {
+    pub receivable: Receivable,
+    pub status: MatchStatus,
+    /// Sum of all assigned payments, duplicates included.
+    pub paid: Decimal,
+    /// Date of the payment that settled the receivable.
+    pub settled_on: Option<NaiveDate>,
+    pub payments: Vec<Transaction>,
+}
+
+impl ReceivableMatch {
+    /// Days between the due date and settlement; negative when paid early.
+    pub fn days_late(&self) -> Option<i64> {
+        self.settled_on
+            .map(|date| (date - self.receivable.due_date).num_days())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Reconciliation {
+    /// One entry per receivable, in input order.
+    pub receivables: Vec<ReceivableMatch>,
+    /// Incoming payments that belong to no receivable.
+    pub unmatched: Vec<Transaction>,
+}
+
+/// Match incoming payments (positive amounts) to `receivables`.
+///
+/// Outgoing transactions are ignored.
+pub fn reconcile(
+    receivables: &[Receivable],
+    transactions: &[Transaction],
+    rules: &Rules,
+) -> Reconciliation {
+    let mut states: Vec<State> = receivables.iter().map(|_| State::default()).collect();
+    // Receivables per (VS, currency), earliest due date first.
+    let mut by_key: HashMap<(i64, String), Vec<usize>> = HashMap::new();
+    for (i, r) in receivables.iter().enumerate() {
+        by_key
+            .entry((r.vs, r.currency.to_uppercase()))
+            .or_default()
+            .push(i);
+    }
+    for group in by_key.values_mut() {
+        group.sort_by_key(|&i| (receivables[i].due_date, i));
+    }
+
+    let mut payments: Vec<&Transaction> = transactions
+        .iter()
+        .filter(|t| t.amount > Decimal::ZERO)
+        .collect();
+    payments.sort_by_key(|t| (t.date, t.transaction_id));
+
+    let mut unmatched = Vec::new();
+    for payment in payments {
+        let currency = payment.currency.to_uppercase();
+        let target = match payment.vs {
+            Some(vs) => by_key.get(&(vs, currency)).map(|group| {
+                // The first open receivable, else the last one as a duplicate.
+                group
+                    .iter()
+                    .copied()
+                    .find(|&i| states[i].settled_on.is_none())
+                    .unwrap_or(group[group.len() - 1])
+            }),
+            None if rules.match_amount_without_vs => {
+                let mut open = receivables.iter().enumerate().filter(|(i, r)| {
+                    states[*i].payments.is_empty()
+                        && r.currency.eq_ignore_ascii_case(&currency)
+                        && (r.amount - payment.amount).abs() <= rules.tolerance(r.amount)
+                });
+                match (open.next(), open.next()) {
+                    (Some((i, _)), None) => Some(i),
+                    _ => None,
+                }
+            }
+            None => None,
+        };
+        match target {
+            Some(i) => states[i].assign(payment, &receivables[i], rules),
+            None => unmatched.push(payment.clone()),
+        }
+    }
+
+    let receivables = receivables
+        .iter()
+        .zip(states)
+        .map(|(receivable, state)| state.finish(receivable, rules))
+        .collect();
+    Reconciliation {
+        receivables,
+        unmatched,
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    paid: Decimal,
+    settled_on: Option<NaiveDate>,
+    payments: Vec<Transaction>,
+    duplicates: Vec<i64>,
+}
+
+impl State {
+    fn assign(&mut self, payment: &Transaction, receivable: &Receivable, rules: &Rules) {
+        if self.settled_on.is_some() {
+            self.duplicates.push(payment.transaction_id);
+        } else if self.paid + payment.amount
+            >= receivable.amount - rules.tolerance(receivable.amount)
+        {
+            self.settled_on = Some(payment.date);
+        }
+        self.paid += payment.amount;
+        self.payments.push(payment.clone());
+    }
+
+    fn finish(self, receivable: &Receivable, rules: &Rules) -> ReceivableMatch {
+        let tolerance = rules.tolerance(receivable.amount);
+        let settled_amount = self.paid
+            - self
+                .payments
+                .iter()
+                .filter(|p| self.duplicates.contains(&p.transaction_id))
+                .map(|p| p.amount)
+                .sum::<Decimal>();
+        let status = if self.payments.is_empty() {
+            MatchStatus::Unpaid
+        } else if !self.duplicates.is_empty() {
+            MatchStatus::DuplicatePayment {
+                duplicates: self.duplicates,
+            }
+        } else if settled_amount > receivable.amount + tolerance {
+            MatchStatus::Overpaid {
+                excess: settled_amount - receivable.amount,
+            }
+        } else if self.settled_on.is_none() {
+            MatchStatus::PartiallyPaid {
+                missing: receivable.amount - settled_amount,
+            }
+        } else {
+            MatchStatus::Matched
+        };
+        ReceivableMatch {
+            receivable: receivable.clone(),
+            status,
+            paid: self.paid,
+            settled_on: self.settled_on,
+            payments: self.payments,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::txn;
+    use serde_json::json;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 4, day).unwrap()
+    }
+
+    fn receivable(id: &str, vs: i64, amount: i64, due: u32) -> Receivable {
+        Receivable {
+            id: id.to_string(),
+            vs,
+            amount: Decimal::from(amount),
+            currency: "CZK".to_string(),
+            due_date: date(due),
+        }
+    }
+
+    fn payment(id: i64, day: u32, amount: &str, vs: Option<i64>) -> Transaction {
+        txn(id, &date(day).to_string(), amount, json!({"vs": vs}))
+    }
+
+    #[test]
+    fn classifies_receivables_and_payments() {
+        let receivables = [
+            receivable("exact", 1, 1000, 10),
+            receivable("rounded", 2, 500, 10),
+            receivable("partial", 3, 800, 10),
+            receivable("over", 4, 300, 10),
+            receivable("twice", 5, 200, 10),
+            receivable("unpaid", 6, 900, 10),
+            // Two monthly invoices with the same VS.
+            receivable("april", 7, 400, 15),
+            receivable("may", 7, 400, 30),
+            receivable("no-vs", 8, 1234, 20),
+        ];
+        let transactions = [
+            payment(1, 9, "1000", Some(1)),
+            payment(2, 11, "499.60", Some(2)),
+            payment(3, 5, "300", Some(3)),
+            payment(4, 6, "200", Some(3)),
+            payment(5, 8, "350", Some(4)),
+            payment(6, 8, "200", Some(5)),
+            payment(7, 9, "200", Some(5)),
+            payment(8, 14, "400", Some(7)),
+            payment(9, 29, "400", Some(7)),
+            payment(10, 12, "1234", None),
+            payment(11, 12, "75", Some(99)),
+            payment(12, 12, "-1000", Some(1)),
+        ];
+        let rules = Rules {
+            absolute_tolerance: Decimal::ONE,
+            match_amount_without_vs: true,
+            ..Rules::default()
+        };
+        let result = reconcile(&receivables, &transactions, &rules);
+        let statuses: Vec<_> = result
+            .receivables
+            .iter()
+            .map(|m| (m.receivable.id.as_str(), m.status.clone()))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("exact", MatchStatus::Matched),
+                ("rounded", MatchStatus::Matched),
+                (
+                    "partial",
+                    MatchStatus::PartiallyPaid {
+                        missing: Decimal::from(300)
+                    }
+                ),
+                (
+                    "over",
+                    MatchStatus::Overpaid {
+                        excess: Decimal::from(50)
+                    }
+                ),
+                (
+                    "twice",
+                    MatchStatus::DuplicatePayment {
+                        duplicates: vec![7]
+                    }
+                ),
+                ("unpaid", MatchStatus::Unpaid),
+                ("april", MatchStatus::Matched),
+                ("may", MatchStatus::Matched),
+                ("no-vs", MatchStatus::Matched),
+            ]
+        );
+        assert_eq!(result.receivables[0].days_late(), Some(-1));
+        assert_eq!(result.receivables[1].days_late(), Some(1));
+        assert_eq!(result.receivables[7].payments[0].transaction_id, 9);
+        let unmatched: Vec<_> = result.unmatched.iter().map(|t| t.transaction_id).collect();
+        assert_eq!(unmatched, [11]);
+
+        // Without the tolerance the rounded payment is partial.
+        let strict = reconcile(&receivables[1..2], &transactions, &Rules::default());
+        assert!(matches!(
+            strict.receivables[0].status,
+            MatchStatus::PartiallyPaid { .. }
+        ));
+    }
+
+    #[test]
+    fn edge_cases() {
+        let empty = reconcile(&[], &[], &Rules::default());
+        assert!(empty.receivables.is_empty() && empty.unmatched.is_empty());
+
+        let receivables = [
+            receivable("a", 1, 100, 10),
+            receivable("b", 2, 100, 10),
+            receivable("c", 3, 1000, 10),
+        ];
+        let transactions = [
+            // Right VS, wrong currency.
+            txn(1, "2025-04-05", "100", json!({"vs": 1, "currency": "EUR"})),
+            // Amount alone fits both "a" and "b".
+            payment(2, 5, "100", None),
+            // Exactly at the 1 % tolerance.
+            payment(3, 5, "990", Some(3)),
+        ];
+        let rules = Rules {
+            relative_tolerance: Decimal::new(1, 2),
+            match_amount_without_vs: true,
+            ..Rules::default()
+        };
+        let result = reconcile(&receivables, &transactions, &rules);
+        assert_eq!(result.receivables[0].status, MatchStatus::Unpaid);
+        assert_eq!(result.receivables[1].status, MatchStatus::Unpaid);
+        assert_eq!(result.receivables[2].status, MatchStatus::Matched);
+        let unmatched: Vec<_> = result.unmatched.iter().map(|t| t.transaction_id).collect();
+        assert_eq!(unmatched, [1, 2]);
+
+        // Same-day payments settle in transaction id order.
+        let same_day = [
+            payment(21, 5, "100", Some(1)),
+            payment(20, 5, "100", Some(1)),
+        ];
+        let result = reconcile(&receivables[..1], &same_day, &rules);
+        assert_eq!(
+            result.receivables[0].status,
+            MatchStatus::DuplicatePayment {
+                duplicates: vec![21]
+            }
+        );
+    }
+}
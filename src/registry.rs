@@ -0,0 +1,306 @@
+//! Several accounts behind one handle.
+//!
+//! Fio issues one token per account, and a separate one when payments should
+//! be submitted. An [`AccountRegistry`] keeps a named [`Client`] per account
+//! alias (e.g. `czk`, `eur`), optionally with a write-capable client, and
+//! runs `fetch_*` calls against all accounts at once. Every token has its
+//! own 30 s limit, so each client keeps its own rate limiter.
+
+use crate::client::{Client, LastStatementInfo, TransactionReportFmt};
+#[cfg(feature = "reqwest")]
+use crate::credentials::TokenSource;
+use crate::error::FioError;
+use crate::models::{Statement, parse_statement};
+use crate::token::{TokenScope, WriteToken};
+use chrono::NaiveDate;
+use futures_util::future::join_all;
+use log::{debug, info};
+use std::collections::BTreeMap;
+use std::future::Future;
+
+/// Results of a fan-out call keyed by account alias.
+pub type ByAlias<T> = BTreeMap<String, Result<T, FioError>>;
+
+#[derive(Debug, Clone)]
+struct Account {
+    reader: Client,
+    writer: Option<Client<WriteToken>>,
+    /// The reader was derived from the writer's token.
+    shared: bool,
+}
+
+/// Named clients for several Fio accounts.
+#[derive(Debug, Clone, Default)]
+pub struct AccountRegistry {
+    accounts: BTreeMap<String, Account>,
+}
+
+impl AccountRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a read-only client under `alias`, keeping a write client
+    /// already registered for it.
+    pub fn with_account(mut self, alias: impl Into<String>, client: Client) -> Self {
+        let alias = alias.into();
+        info!("Registered account {} (read token)", alias);
+        match self.accounts.get_mut(&alias) {
+            Some(account) => {
+                account.reader = client;
+                account.shared = false;
+            }
+            None => {
+                self.accounts.insert(
+                    alias,
+                    Account {
+                        reader: client,
+                        writer: None,
+                        shared: false,
+                    },
+                );
+            }
+        }
+        self
+    }
+
+    /// Register a write-capable client under `alias`.
+    ///
+    /// When the alias has no read-only client yet, the write token is also
+    /// used for reading.
+    pub fn with_writer(mut self, alias: impl Into<String>, client: Client<WriteToken>) -> Self {
+        let alias = alias.into();
+        info!("Registered account {} (read-write token)", alias);
+        match self.accounts.get_mut(&alias) {
+            Some(account) => account.writer = Some(client),
+            None => {
+                self.accounts.insert(
+                    alias,
+                    Account {
+                        reader: client.to_read(),
+                        writer: Some(client),
+                        shared: true,
+                    },
+                );
+            }
+        }
+        self
+    }
+
+    /// Register a read-only client with a token loaded from `source`.
+    #[cfg(feature = "reqwest")]
+    pub fn with_credentials(
+        self,
+        alias: impl Into<String>,
+        source: &TokenSource,
+    ) -> Result<Self, FioError> {
+        Ok(self.with_account(alias, Client::from_credentials(source)?))
+    }
+
+    /// Register a write-capable client with a token loaded from `source`.
+    ///
+    /// The token is not probed; see [`Client::probe_token_scope`].
+    #[cfg(feature = "reqwest")]
+    pub fn with_write_credentials(
+        self,
+        alias: impl Into<String>,
+        source: &TokenSource,
+    ) -> Result<Self, FioError> {
+        Ok(self.with_writer(alias, Client::from_credentials(source)?))
+    }
+
+    /// Give every registered client its own rate limiter.
+    ///
+    /// A write client that also serves reads for its alias shares one
+    /// limiter between both, as they use the same token.
+    #[cfg(feature = "rate-limit")]
+    pub fn with_rate_limit(mut self, interval: std::time::Duration) -> Self {
+        for account in self.accounts.values_mut() {
+            if let Some(writer) = account.writer.take() {
+                let writer = writer.with_rate_limit(interval);
+                if account.shared {
+                    account.reader = writer.to_read();
+                }
+                account.writer = Some(writer);
+            }
+            if !account.shared {
+                account.reader = account.reader.clone().with_rate_limit(interval);
+            }
+        }
+        self
+    }
+
+    pub fn aliases(&self) -> impl Iterator<Item = &str> {
+        self.accounts.keys().map(String::as_str)
+    }
+
+    /// Client for reading the account registered as `alias`.
+    pub fn client(&self, alias: &str) -> Option<&Client> {
+        self.accounts.get(alias).map(|a| &a.reader)
+    }
+
+    /// Client for submitting payments from the account registered as `alias`.
+    pub fn writer(&self, alias: &str) -> Option<&Client<WriteToken>> {
+        self.accounts.get(alias).and_then(|a| a.writer.as_ref())
+    }
+
+    /// Scope of the tokens registered for `alias`.
+    pub fn scope(&self, alias: &str) -> Option<TokenScope> {
+        self.accounts.get(alias).map(|a| match a.writer {
+            Some(_) => TokenScope::ReadWrite,
+            None => TokenScope::Read,
+        })
+    }
+
+    /// Scopes of all registered aliases.
+    pub fn scopes(&self) -> BTreeMap<&str, TokenScope> {
+        self.aliases()
+            .filter_map(|alias| Some((alias, self.scope(alias)?)))
+            .collect()
+    }
+
+    /// Fetch the transaction report for a period from every account.
+    pub async fn fetch_transaction_report_for_period(
+        &self,
+        date_from: NaiveDate,
+        date_to: NaiveDate,
+        fmt: TransactionReportFmt,
+    ) -> ByAlias<String> {
+        self.fan_out(|client| client.fetch_transaction_report_for_period(date_from, date_to, fmt))
+            .await
+    }
+
+    /// Fetch transactions since the last download from every account.
+    pub async fn fetch_transaction_report_since_last_download(
+        &self,
+        fmt: TransactionReportFmt,
+    ) -> ByAlias<String> {
+        self.fan_out(|client| client.fetch_transaction_report_since_last_download(fmt))
+            .await
+    }
+
+    /// Fetch the last statement metadata of every account.
+    pub async fn fetch_last_account_statement_info(&self) -> ByAlias<LastStatementInfo> {
+        self.fan_out(|client| client.fetch_last_account_statement_info())
+            .await
+    }
+
+    /// Fetch and parse the JSON statement for a period from every account.
+    ///
+    /// Use [`by_account_id`] to key the successful results by account number.
+    pub async fn fetch_statements_for_period(
+        &self,
+        date_from: NaiveDate,
+        date_to: NaiveDate,
+    ) -> ByAlias<Statement> {
+        self.fan_out(|client| async move {
+            let data = client
+                .fetch_transaction_report_for_period(date_from, date_to, TransactionReportFmt::Json)
+                .await?;
+            parse_statement(&data)
+        })
+        .await
+    }
+
+    async fn fan_out<'a, T, F, Fut>(&'a self, call: F) -> ByAlias<T>
+    where
+        F: Fn(&'a Client) -> Fut,
+        Fut: Future<Output = Result<T, FioError>>,
+    {
+        debug!("Fetching from {} accounts", self.accounts.len());
+        let calls = self.accounts.iter().map(|(alias, account)| {
+            let call = call(&account.reader);
+            async move { (alias.clone(), call.await) }
+        });
+        join_all(calls).await.into_iter().collect()
+    }
+}
+
+/// Successful statements keyed by [`AccountInfo::account_id`](crate::AccountInfo::account_id).
+///
+/// Failed aliases and statements without an account number are left out.
+pub fn by_account_id(statements: &ByAlias<Statement>) -> BTreeMap<&str, &Statement> {
+    statements
+        .values()
+        .filter_map(|result| result.as_ref().ok())
+        .filter_map(|statement| Some((statement.info.account_id.as_deref()?, statement)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ApiError;
+    use crate::transport::MemoryTransport;
+    use http::StatusCode;
+
+    fn statement(account_id: &str, currency: &str) -> String {
+        format!(
+            r#"{{"accountStatement":{{"info":{{"accountId":"{account_id}","currency":"{currency}",
+            "dateStart":"2025-01-01","dateEnd":"2025-01-01"}},
+            "transactionList":{{"transaction":[]}}}}}}"#
+        )
+    }
+
+    fn client(token: char, transport: MemoryTransport) -> Client {
+        Client::from_transport(token.to_string().repeat(64), transport).unwrap()
+    }
+
+    fn writer(token: char, transport: MemoryTransport) -> Client<WriteToken> {
+        Client::from_token_and_transport(
+            WriteToken::new(token.to_string().repeat(64)).unwrap(),
+            transport,
+        )
+    }
+
+    #[tokio::test]
+    async fn fans_out_and_keys_by_alias_and_account() {
+        let registry = AccountRegistry::new()
+            .with_account(
+                "czk",
+                client(
+                    'c',
+                    MemoryTransport::new().on_get(
+                        "/periods/",
+                        StatusCode::OK,
+                        statement("2000000001", "CZK"),
+                    ),
+                ),
+            )
+            .with_writer(
+                "eur",
+                writer(
+                    'e',
+                    MemoryTransport::new().on_get(
+                        "/periods/",
+                        StatusCode::OK,
+                        statement("2000000002", "EUR"),
+                    ),
+                ),
+            )
+            .with_account(
+                "usd",
+                client(
+                    'u',
+                    MemoryTransport::new().on_get("/periods/", StatusCode::CONFLICT, ""),
+                ),
+            );
+        let date = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+
+        let results = registry.fetch_statements_for_period(date, date).await;
+        assert_eq!(results.len(), 3);
+        assert!(matches!(
+            results["usd"],
+            Err(FioError::Api(ApiError::TimeLimit))
+        ));
+        let by_id = by_account_id(&results);
+        assert_eq!(by_id.len(), 2);
+        assert_eq!(by_id["2000000002"].info.currency.as_deref(), Some("EUR"));
+
+        assert_eq!(registry.scope("czk"), Some(TokenScope::Read));
+        assert_eq!(registry.scope("eur"), Some(TokenScope::ReadWrite));
+        assert_eq!(registry.scope("gbp"), None);
+        assert!(registry.writer("czk").is_none());
+        assert!(registry.writer("eur").is_some());
+    }
+}
@@ -0,0 +1,369 @@
+//! Spending and income totals per period.
+//!
+//! [`aggregate`] buckets transactions into weeks (ISO, starting Monday),
+//! months or years and sums inflow, outflow and net amount per bucket,
+//! optionally split by category, counterparty, [`TransactionKind`], card or
+//! merchant. When an opening balance is known (e.g.
+//! [`AccountInfo::opening_balance`]), each period also carries the running
+//! balance at its end.
+//!
+//! Amounts are summed as they are; mixing currencies in one report is left
+//! to the caller.
+//!
+//! [`TransactionKind`]: crate::models::TransactionKind
+//! [`AccountInfo::opening_balance`]: crate::models::AccountInfo::opening_balance
+
+use crate::categorize::Categorizer;
+use crate::error::FioError;
+use crate::models::Transaction;
+use chrono::{Datelike, Days, NaiveDate};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Week,
+    Month,
+    Year,
+}
+
+impl Period {
+    /// First day of the period containing `date`.
+    pub fn start_of(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Week => date - Days::new(date.weekday().num_days_from_monday().into()),
+            Period::Month => date.with_day(1).expect("day 1 exists"),
+            Period::Year => date.with_ordinal(1).expect("day 1 exists"),
+        }
+    }
+
+    /// Label of the period starting at `start`: `2025-W07`, `2025-02`, `2025`.
+    pub fn label(self, start: NaiveDate) -> String {
+        match self {
+            Period::Week => {
+                let week = start.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }
+            Period::Month => start.format("%Y-%m").to_string(),
+            Period::Year => start.year().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Period::Week => "week",
+            Period::Month => "month",
+            Period::Year => "year",
+        })
+    }
+}
+
+impl FromStr for Period {
+    type Err = FioError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "week" | "weekly" => Ok(Period::Week),
+            "month" | "monthly" => Ok(Period::Month),
+            "year" | "yearly" => Ok(Period::Year),
+            _ => Err(FioError::InvalidParameter("unknown period")),
+        }
+    }
+}
+
+/// How totals within a period are split.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    /// One total per period.
+    Total,
+    /// Category assigned by a [`Categorizer`].
+    Category,
+    /// Counterparty name, else account number.
+    Counterparty,
+    Kind,
+    /// Masked card number of card payments.
+    Card,
+    /// Merchant of card payments.
+    Merchant,
+}
+
+impl fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            GroupBy::Total => "total",
+            GroupBy::Category => "category",
+            GroupBy::Counterparty => "counterparty",
+            GroupBy::Kind => "kind",
+            GroupBy::Card => "card",
+            GroupBy::Merchant => "merchant",
+        })
+    }
+}
+
+impl FromStr for GroupBy {
+    type Err = FioError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "total" | "none" => Ok(GroupBy::Total),
+            "category" => Ok(GroupBy::Category),
+            "counterparty" | "account" => Ok(GroupBy::Counterparty),
+            "kind" | "type" => Ok(GroupBy::Kind),
+            "card" => Ok(GroupBy::Card),
+            "merchant" => Ok(GroupBy::Merchant),
+            _ => Err(FioError::InvalidParameter("unknown grouping")),
+        }
+    }
+}
+
+/// Sums over a set of transactions; `outflow` is positive.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Totals {
+    pub count: usize,
+    pub inflow: Decimal,
+    pub outflow: Decimal,
+    pub net: Decimal,
+}
+
+impl Totals {
+    fn add(&mut self, amount: Decimal) {
+        self.count += 1;
+        if amount >= Decimal::ZERO {
+            self.inflow += amount;
+        } else {
+            self.outflow -= amount;
+        }
+        self.net += amount;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GroupTotals {
+    pub key: String,
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PeriodReport {
+    pub label: String,
+    pub start: NaiveDate,
+    #[serde(flatten)]
+    pub totals: Totals,
+    /// Balance after the period; `None` without an opening balance.
+    pub closing_balance: Option<Decimal>,
+    /// Totals per group ordered by key; empty for [`GroupBy::Total`].
+    pub groups: Vec<GroupTotals>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Report {
+    pub period: Period,
+    pub group_by: GroupBy,
+    pub opening_balance: Option<Decimal>,
+    /// Periods with at least one transaction, oldest first.
+    pub periods: Vec<PeriodReport>,
+    pub totals: Totals,
+}
+
+/// Aggregate `transactions` per `period`, split by `group_by`.
+///
+/// Grouping by category uses `categorizer`; transactions it leaves without
+/// a category, or all of them when it is `None`, go to `uncategorized`.
+pub fn aggregate(
+    transactions: &[Transaction],
+    period: Period,
+    group_by: GroupBy,
+    categorizer: Option<&Categorizer>,
+    opening_balance: Option<Decimal>,
+) -> Report {
+    let mut buckets: BTreeMap<NaiveDate, (Totals, BTreeMap<String, Totals>)> = BTreeMap::new();
+    let mut totals = Totals::default();
+    for txn in transactions {
+        let (period_totals, groups) = buckets.entry(period.start_of(txn.date)).or_default();
+        period_totals.add(txn.amount);
+        totals.add(txn.amount);
+        if let Some(key) = group_key(txn, group_by, categorizer) {
+            groups.entry(key).or_default().add(txn.amount);
+        }
+    }
+
+    let mut balance = opening_balance;
+    let periods = buckets
+        .into_iter()
+        .map(|(start, (totals, groups))| {
+            balance = balance.map(|b| b + totals.net);
+            PeriodReport {
+                label: period.label(start),
+                start,
+                totals,
+                closing_balance: balance,
+                groups: groups
+                    .into_iter()
+                    .map(|(key, totals)| GroupTotals { key, totals })
+                    .collect(),
+            }
+        })
+        .collect();
+    Report {
+        period,
+        group_by,
+        opening_balance,
+        periods,
+        totals,
+    }
+}
+
+fn group_key(
+    txn: &Transaction,
+    group_by: GroupBy,
+    categorizer: Option<&Categorizer>,
+) -> Option<String> {
+    Some(match group_by {
+        GroupBy::Total => return None,
+        GroupBy::Category => categorizer
+            .and_then(|c| c.categorize(txn).category)
+            .unwrap_or_else(|| "uncategorized".to_string()),
+        GroupBy::Counterparty => match (&txn.account_name, &txn.account_id, &txn.bank_id) {
+            (Some(name), _, _) => name.clone(),
+            (None, Some(account), Some(bank)) => format!("{account}/{bank}"),
+            (None, Some(account), None) => account.clone(),
+            (None, None, _) => "unknown".to_string(),
+        },
+        GroupBy::Kind => txn.kind().to_string(),
+        GroupBy::Card => txn
+            .card_details()
+            .and_then(|d| d.card_mask)
+            .unwrap_or_else(|| "no card".to_string()),
+        GroupBy::Merchant => txn
+            .card_details()
+            .and_then(|d| d.merchant)
+            .unwrap_or_else(|| "no merchant".to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::categorize::{Conditions, Rule};
+    use crate::test_util::txn;
+    use serde_json::json;
+
+    #[test]
+    fn monthly_totals_with_running_balance() {
+        let transactions = [
+            txn(
+                1,
+                "2025-01-05",
+                "30000.00",
+                json!({"account_name": "Employer"}),
+            ),
+            txn(
+                2,
+                "2025-01-20",
+                "-12000.50",
+                json!({"account_name": "Landlord"}),
+            ),
+            txn(3, "2025-02-03", "-0.10", json!({})),
+            txn(
+                4,
+                "2025-01-31",
+                "-99.90",
+                json!({"account_name": "Landlord"}),
+            ),
+        ];
+        let categorizer = Categorizer::new([Rule {
+            name: "salary".to_string(),
+            priority: 0,
+            category: Some("income".to_string()),
+            tags: Vec::new(),
+            when: Conditions {
+                min_amount: Some(Decimal::from(10000)),
+                ..Conditions::default()
+            },
+        }])
+        .unwrap();
+        let report = aggregate(
+            &transactions,
+            Period::Month,
+            GroupBy::Category,
+            Some(&categorizer),
+            Some(Decimal::from(1000)),
+        );
+
+        let labels: Vec<_> = report.periods.iter().map(|p| p.label.as_str()).collect();
+        assert_eq!(labels, ["2025-01", "2025-02"]);
+        let january = &report.periods[0];
+        assert_eq!(
+            january.totals,
+            Totals {
+                count: 3,
+                inflow: Decimal::new(3000000, 2),
+                outflow: Decimal::new(1210040, 2),
+                net: Decimal::new(1789960, 2),
+            }
+        );
+        assert_eq!(january.closing_balance, Some(Decimal::new(1889960, 2)));
+        let keys: Vec<_> = january.groups.iter().map(|g| g.key.as_str()).collect();
+        assert_eq!(keys, ["income", "uncategorized"]);
+        assert_eq!(
+            report.periods[1].closing_balance,
+            Some(Decimal::new(1889950, 2))
+        );
+        assert_eq!(report.totals.count, 4);
+
+        let weekly = aggregate(
+            &transactions,
+            Period::Week,
+            GroupBy::Counterparty,
+            None,
+            None,
+        );
+        let labels: Vec<_> = weekly.periods.iter().map(|p| p.label.as_str()).collect();
+        assert_eq!(labels, ["2025-W01", "2025-W04", "2025-W05", "2025-W06"]);
+        assert_eq!(weekly.periods[2].groups[0].key, "Landlord");
+        assert_eq!(weekly.periods[3].groups[0].key, "unknown");
+        assert_eq!(weekly.periods[0].closing_balance, None);
+    }
+
+    #[test]
+    fn edge_cases() {
+        let empty = aggregate(&[], Period::Month, GroupBy::Kind, None, Some(Decimal::ONE));
+        assert!(empty.periods.is_empty());
+        assert_eq!(empty.totals, Totals::default());
+        assert_eq!(empty.opening_balance, Some(Decimal::ONE));
+
+        // ISO week 1 of 2025 starts on 2024-12-30; a zero amount counts as
+        // inflow; currencies are summed as they are.
+        let transactions = [
+            txn(1, "2024-12-30", "0", json!({})),
+            txn(2, "2025-01-05", "-10", json!({"currency": "EUR"})),
+            txn(3, "2024-12-29", "5", json!({})),
+        ];
+        let report = aggregate(&transactions, Period::Week, GroupBy::Total, None, None);
+        let labels: Vec<_> = report.periods.iter().map(|p| p.label.as_str()).collect();
+        assert_eq!(labels, ["2024-W52", "2025-W01"]);
+        assert_eq!(
+            report.periods[1].totals,
+            Totals {
+                count: 2,
+                inflow: Decimal::ZERO,
+                outflow: Decimal::from(10),
+                net: Decimal::from(-10),
+            }
+        );
+        assert!(report.periods[1].groups.is_empty());
+        assert_eq!(report.totals.net, Decimal::from(-5));
+
+        let yearly = aggregate(&transactions, Period::Year, GroupBy::Total, None, None);
+        let labels: Vec<_> = yearly.periods.iter().map(|p| p.label.as_str()).collect();
+        assert_eq!(labels, ["2024", "2025"]);
+    }
+}
@@ -0,0 +1,19 @@
+//! Fixtures shared by the unit tests.
+
+use crate::models::Transaction;
+use serde_json::{Value, json};
+
+/// A CZK transaction as Fio's columns would deserialize; `extra` adds or
+/// overrides columns, e.g. `json!({"vs": 7, "currency": "EUR"})`.
+pub(crate) fn txn(id: i64, date: &str, amount: &str, extra: Value) -> Transaction {
+    let mut value = json!({
+        "transaction_id": id,
+        "date": date,
+        "amount": amount,
+        "currency": "CZK",
+    });
+    if let (Some(columns), Value::Object(extra)) = (value.as_object_mut(), extra) {
+        columns.extend(extra);
+    }
+    serde_json::from_value(value).unwrap()
+}
@@ -0,0 +1,212 @@
+//! API tokens typed by what they are allowed to do.
+//!
+//! Fio issues tokens that can only read account data and tokens that can
+//! additionally submit payment orders. [`ReadToken`] and [`WriteToken`]
+//! encode that capability so that import methods only exist on a
+//! `Client<WriteToken>`.
+
+use crate::error::FioError;
+use std::fmt;
+use zeroize::Zeroizing;
+
+pub(crate) const TOKEN_LENGTH: usize = 64;
+
+/// Capability of a token as reported by [`Client::probe_token_scope`](crate::Client::probe_token_scope).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenScope {
+    /// Account history and statements only.
+    Read,
+    /// Reading plus submitting payment orders.
+    ReadWrite,
+}
+
+impl TokenScope {
+    pub fn can_write(self) -> bool {
+        matches!(self, TokenScope::ReadWrite)
+    }
+}
+
+impl fmt::Display for TokenScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TokenScope::Read => "read",
+            TokenScope::ReadWrite => "read-write",
+        })
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Token accepted by [`Client`](crate::Client); implemented by [`ReadToken`] and [`WriteToken`].
+pub trait ApiToken: sealed::Sealed + fmt::Debug + Clone + Send + Sync + 'static {
+    /// Scope the token is known to have.
+    const SCOPE: TokenScope;
+
+    /// The underlying secret.
+    fn token(&self) -> &FioToken;
+}
+
+/// Raw API token kept out of logs and wiped from memory on drop.
+///
+/// `Debug` and `Display` never print the value; use
+/// [`FioToken::expose_secret`] where the token has to be sent to the API.
+#[derive(Clone)]
+pub struct FioToken(Zeroizing<String>);
+
+impl FioToken {
+    /// Wrap a token after checking its length.
+    pub fn new(token: impl Into<String>) -> Result<Self, FioError> {
+        let token = Zeroizing::new(token.into());
+        if token.len() != TOKEN_LENGTH {
+            return Err(FioError::InvalidTokenLength {
+                expected: TOKEN_LENGTH,
+                actual: token.len(),
+            });
+        }
+        Ok(Self(token))
+    }
+
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+
+    /// Replace every occurrence of the token in `text` with `<token>`.
+    pub fn redact(&self, text: &str) -> String {
+        text.replace(self.expose_secret(), "<token>")
+    }
+}
+
+impl fmt::Debug for FioToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FioToken(<redacted>)")
+    }
+}
+
+impl fmt::Display for FioToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+/// Token used only for downloading account data.
+#[derive(Debug, Clone)]
+pub struct ReadToken(FioToken);
+
+impl ReadToken {
+    pub fn new(token: impl Into<String>) -> Result<Self, FioError> {
+        FioToken::new(token).map(Self)
+    }
+}
+
+impl From<FioToken> for ReadToken {
+    fn from(token: FioToken) -> Self {
+        Self(token)
+    }
+}
+
+/// Token that may also submit payment orders through the import endpoint.
+#[derive(Debug, Clone)]
+pub struct WriteToken(FioToken);
+
+impl WriteToken {
+    pub fn new(token: impl Into<String>) -> Result<Self, FioError> {
+        FioToken::new(token).map(Self)
+    }
+
+    /// Use the token for reading only.
+    pub fn into_read(self) -> ReadToken {
+        ReadToken(self.0)
+    }
+}
+
+impl From<FioToken> for WriteToken {
+    fn from(token: FioToken) -> Self {
+        Self(token)
+    }
+}
+
+impl From<WriteToken> for ReadToken {
+    fn from(token: WriteToken) -> Self {
+        token.into_read()
+    }
+}
+
+impl sealed::Sealed for ReadToken {}
+impl sealed::Sealed for WriteToken {}
+
+impl ApiToken for ReadToken {
+    const SCOPE: TokenScope = TokenScope::Read;
+
+    fn token(&self) -> &FioToken {
+        &self.0
+    }
+}
+
+impl ApiToken for WriteToken {
+    const SCOPE: TokenScope = TokenScope::ReadWrite;
+
+    fn token(&self) -> &FioToken {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_is_redacted_in_output() {
+        let raw = "s".repeat(64);
+        let token = WriteToken::new(raw.clone()).unwrap();
+        assert_eq!(format!("{:?}", token), "WriteToken(FioToken(<redacted>))");
+        assert_eq!(token.token().to_string(), "<redacted>");
+        assert_eq!(
+            token.token().redact(&format!("/last/{raw}/x")),
+            "/last/<token>/x"
+        );
+        assert!(FioToken::new("short").is_err());
+    }
+
+    #[tokio::test]
+    async fn probe_requires_accepted_import() {
+        use crate::{ApiError, Client, FioError, MemoryTransport};
+        use http::StatusCode;
+
+        let probe = |status, body: &'static str| async move {
+            let transport = MemoryTransport::new().on_post("/import/", status, body);
+            Client::from_transport("p".repeat(64), transport)
+                .unwrap()
+                .with_base_url("http://fio.test")
+                .probe_token_scope()
+                .await
+        };
+        // Assumed answers; Fio does not document the response to an empty
+        // batch and none has been recorded.
+        let accepted = "<responseImport><result><errorCode>0</errorCode>\
+            <status>ok</status></result></responseImport>";
+        let refused = "<responseImport><result><errorCode>11</errorCode>\
+            <status>error</status></result></responseImport>";
+        assert_eq!(
+            probe(StatusCode::OK, accepted).await.unwrap(),
+            TokenScope::ReadWrite
+        );
+        assert_eq!(
+            probe(StatusCode::OK, refused).await.unwrap(),
+            TokenScope::Read
+        );
+        assert!(matches!(
+            probe(StatusCode::OK, "<responseImport/>").await,
+            Err(FioError::InvalidResponse)
+        ));
+        assert_eq!(
+            probe(StatusCode::UNPROCESSABLE_ENTITY, "").await.unwrap(),
+            TokenScope::Read
+        );
+        assert!(matches!(
+            probe(StatusCode::INTERNAL_SERVER_ERROR, "").await,
+            Err(FioError::Api(ApiError::UnexpectedStatus(_)))
+        ));
+    }
+}
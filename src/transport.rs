@@ -0,0 +1,345 @@
+//! Pluggable HTTP transport used by [`Client`](crate::Client).
+//!
+//! [`ReqwestTransport`] is the default (feature `reqwest`). Embedders can implement [`Transport`]
+//! on top of another HTTP stack, and tests can use [`MemoryTransport`] to
+//! serve canned responses without a network.
+
+use crate::token::FioToken;
+use http::StatusCode;
+use log::debug;
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Mutex, MutexGuard};
+#[cfg(feature = "reqwest")]
+use std::time::Duration;
+
+/// Boxed future returned by [`Transport`] methods.
+pub type TransportFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<HttpResponse, TransportError>> + Send + 'a>>;
+
+/// Status and body of an HTTP response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub body: Vec<u8>,
+}
+
+/// Failure to complete an HTTP exchange (connection, TLS, timeout, ...).
+#[derive(Debug)]
+pub struct TransportError {
+    message: String,
+    source: Option<Box<dyn StdError + Send + Sync>>,
+}
+
+impl TransportError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn with_source(
+        message: impl Into<String>,
+        source: impl StdError + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            message: message.into(),
+            source: Some(Box::new(source)),
+        }
+    }
+}
+
+impl TransportError {
+    /// Scrub the token from the message and from any source error.
+    pub(crate) fn redact(self, token: &FioToken) -> Self {
+        let secret = token.expose_secret();
+        let source = match self.source {
+            Some(source) if source.to_string().contains(secret) => Some(Box::new(
+                TransportError::new(token.redact(&source.to_string())),
+            )
+                as Box<dyn StdError + Send + Sync>),
+            source => source,
+        };
+        Self {
+            message: token.redact(&self.message),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl StdError for TransportError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source
+            .as_deref()
+            .map(|e| e as &(dyn StdError + 'static))
+    }
+}
+
+/// HTTP backend capable of the GET and POST requests issued by the client.
+///
+/// Implementations return the response for any status code; mapping
+/// non-success statuses to [`ApiError`](crate::ApiError) is done by the client.
+pub trait Transport: fmt::Debug + Send + Sync {
+    fn get<'a>(&'a self, url: &'a str) -> TransportFuture<'a>;
+
+    fn post<'a>(
+        &'a self,
+        url: &'a str,
+        content_type: &'a str,
+        body: Vec<u8>,
+    ) -> TransportFuture<'a>;
+}
+
+/// Default transport built on `reqwest`.
+#[cfg(feature = "reqwest")]
+#[derive(Debug, Clone)]
+pub struct ReqwestTransport {
+    http: reqwest::Client,
+}
+
+#[cfg(feature = "reqwest")]
+impl ReqwestTransport {
+    /// Create a transport with the default 10 s request timeout.
+    pub fn new() -> Result<Self, TransportError> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .map_err(reqwest_error)?;
+        Ok(Self { http })
+    }
+
+    /// Wrap an existing, preconfigured `reqwest::Client`.
+    pub fn from_client(http: reqwest::Client) -> Self {
+        Self { http }
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<HttpResponse, TransportError> {
+        let response = request.send().await.map_err(reqwest_error)?;
+        let status = response.status();
+        let body = response.bytes().await.map_err(reqwest_error)?.to_vec();
+        Ok(HttpResponse { status, body })
+    }
+}
+
+#[cfg(feature = "reqwest")]
+fn reqwest_error(err: reqwest::Error) -> TransportError {
+    // The URL carries the token; keep it only in the message, which the
+    // client redacts before surfacing the error.
+    TransportError::with_source(err.to_string(), err.without_url())
+}
+
+#[cfg(feature = "reqwest")]
+impl Transport for ReqwestTransport {
+    fn get<'a>(&'a self, url: &'a str) -> TransportFuture<'a> {
+        Box::pin(self.send(self.http.get(url)))
+    }
+
+    fn post<'a>(
+        &'a self,
+        url: &'a str,
+        content_type: &'a str,
+        body: Vec<u8>,
+    ) -> TransportFuture<'a> {
+        Box::pin(
+            self.send(
+                self.http
+                    .post(url)
+                    .header(http::header::CONTENT_TYPE, content_type)
+                    .body(body),
+            ),
+        )
+    }
+}
+
+/// Request captured by [`MemoryTransport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedRequest {
+    pub method: &'static str,
+    pub url: String,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct Route {
+    method: &'static str,
+    pattern: String,
+    response: HttpResponse,
+}
+
+/// In-memory fake transport serving canned responses.
+///
+/// Routes are matched in registration order by method and by a substring
+/// of the request URL; unmatched requests are answered with 404.
+#[derive(Debug, Default)]
+pub struct MemoryTransport {
+    routes: Vec<Route>,
+    requests: Mutex<Vec<RecordedRequest>>,
+}
+
+impl MemoryTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer GET requests whose URL contains `pattern`.
+    pub fn on_get(
+        mut self,
+        pattern: impl Into<String>,
+        status: StatusCode,
+        body: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.routes.push(Route {
+            method: "GET",
+            pattern: pattern.into(),
+            response: HttpResponse {
+                status,
+                body: body.into(),
+            },
+        });
+        self
+    }
+
+    /// Answer POST requests whose URL contains `pattern`.
+    pub fn on_post(
+        mut self,
+        pattern: impl Into<String>,
+        status: StatusCode,
+        body: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.routes.push(Route {
+            method: "POST",
+            pattern: pattern.into(),
+            response: HttpResponse {
+                status,
+                body: body.into(),
+            },
+        });
+        self
+    }
+
+    /// All requests received so far.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.lock().clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<RecordedRequest>> {
+        self.requests.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn respond(&self, request: RecordedRequest) -> HttpResponse {
+        let response = self
+            .routes
+            .iter()
+            .find(|r| r.method == request.method && request.url.contains(&r.pattern))
+            .map(|r| r.response.clone())
+            .unwrap_or(HttpResponse {
+                status: StatusCode::NOT_FOUND,
+                body: Vec::new(),
+            });
+        debug!(
+            "Memory transport answered {} with {}",
+            request.method, response.status
+        );
+        self.lock().push(request);
+        response
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn get<'a>(&'a self, url: &'a str) -> TransportFuture<'a> {
+        let response = self.respond(RecordedRequest {
+            method: "GET",
+            url: url.to_string(),
+            content_type: None,
+            body: Vec::new(),
+        });
+        Box::pin(async move { Ok(response) })
+    }
+
+    fn post<'a>(
+        &'a self,
+        url: &'a str,
+        content_type: &'a str,
+        body: Vec<u8>,
+    ) -> TransportFuture<'a> {
+        let response = self.respond(RecordedRequest {
+            method: "POST",
+            url: url.to_string(),
+            content_type: Some(content_type.to_string()),
+            body,
+        });
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ApiError, Client, FioError};
+
+    #[tokio::test]
+    async fn client_runs_over_memory_transport() {
+        let token = "m".repeat(64);
+        let transport = MemoryTransport::new()
+            .on_get("/lastStatement/", StatusCode::OK, "2025,3")
+            .on_get("/set-last-id/", StatusCode::CONFLICT, "");
+        let client = Client::from_transport(&token, transport)
+            .unwrap()
+            .with_base_url("http://fio.test");
+
+        let info = client.fetch_last_account_statement_info().await.unwrap();
+        assert_eq!((info.year, info.statement_id), (2025, 3));
+        let err = client
+            .set_last_downloaded_transaction_id(5)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FioError::Api(ApiError::TimeLimit)));
+        let err = client
+            .set_last_unsuccessful_download_date(chrono::NaiveDate::MIN)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FioError::Api(ApiError::InvalidRequest)));
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[tokio::test]
+    async fn transport_errors_do_not_leak_token() {
+        let token = "z".repeat(64);
+        let client = Client::new(&token)
+            .unwrap()
+            .with_base_url("http://127.0.0.1:1");
+        let err = client
+            .fetch_last_account_statement_info()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FioError::Http(_)));
+        let rendered = format!("{err} {err:?} {client:?}");
+        assert!(rendered.contains("<token>"));
+        assert!(!rendered.contains(&token));
+    }
+
+    #[tokio::test]
+    async fn memory_transport_records_requests() {
+        let transport = MemoryTransport::new().on_post("/import/", StatusCode::OK, "ok");
+        let response = transport
+            .post("http://fio.test/import/", "text/xml", b"<Import/>".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(response.body, b"ok");
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].content_type.as_deref(), Some("text/xml"));
+        assert_eq!(requests[0].body, b"<Import/>");
+    }
+}